@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
 use std::net::{IpAddr, Ipv4Addr};
-use std::sync::LazyLock;
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
 
 use chrono::Local;
 use eyre::{WrapErr, eyre};
@@ -7,9 +9,26 @@ use reqwest::header::{HeaderMap, HeaderValue};
 use serde::de::DeserializeOwned;
 use serde_json::{Map as JsonMap, Value as JsonValue, json};
 
-use super::model::{CreateResponse, DNSRecord, EditResponse, PingResponse, RetrieveResponse};
-use super::{BASE_URL, BASE_URL_V4, IpAddrExt};
-use crate::config::Target;
+use super::model::{
+    CreateResponse,
+    DNSRecord,
+    DeleteResponse,
+    DomainInfo,
+    EditResponse,
+    GetNsResponse,
+    ListDomainsResponse,
+    PingResponse,
+    RecordData,
+    RecordType,
+    RetrieveResponse,
+    UpdateNsResponse,
+};
+use super::{BASE_URL, BASE_URL_V4, NOTES_MARKER};
+use crate::audit::AuditLog;
+use crate::config::{NotesMode, Target};
+use crate::metrics::ApiTiming;
+use crate::names::{DomainName, Subdomain};
+use crate::plan::Change;
 
 /// The main entrypoint for the Porkbun API.
 #[derive(Debug)]
@@ -17,10 +36,25 @@ pub struct PorkbunClient {
     reqwest: reqwest::Client,
     api_key: String,
     secret_key: String,
+    notes: NotesMode,
+    notes_template: Option<String>,
+    /// Whether to add the time of the update to the default marker.
+    notes_timestamp: bool,
+    /// How long requests have taken so far, by endpoint.
+    timings: Mutex<BTreeMap<String, ApiTiming>>,
+    /// Where every change to a record gets logged, if anywhere.
+    audit: Option<AuditLog>,
 }
 
 impl PorkbunClient {
-    pub fn new(api_key: String, secret_key: String) -> Self {
+    pub fn new(
+        api_key: String,
+        secret_key: String,
+        notes: NotesMode,
+        notes_template: Option<String>,
+        notes_timestamp: bool,
+        audit: Option<AuditLog>,
+    ) -> Self {
         let ua_str = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
         let client = reqwest::ClientBuilder::new()
             .default_headers(HeaderMap::from_iter([
@@ -34,9 +68,37 @@ impl PorkbunClient {
             reqwest: client,
             api_key,
             secret_key,
+            notes,
+            notes_template,
+            notes_timestamp,
+            timings: Mutex::new(BTreeMap::new()),
+            audit,
         }
     }
 
+    /// The log that changes to records are written to, if there is one.
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.as_ref()
+    }
+
+    /// Whether every record that this program writes for the given target gets the marker in its notes, so that any of
+    /// the target's records without it can be assumed to belong to someone else.
+    pub fn marks_records(&self, target: &Target) -> bool {
+        self.notes == NotesMode::Marker && target.writes_notes()
+    }
+
+    /// Writes a change that Porkbun just accepted to the audit log, if there is one.
+    async fn audit(&self, change: Change) {
+        if let Some(audit) = &self.audit {
+            audit.record(&change).await;
+        }
+    }
+
+    /// Gets how long requests to each endpoint have taken so far.
+    pub fn timings(&self) -> BTreeMap<String, ApiTiming> {
+        self.timings.lock().unwrap().clone()
+    }
+
     /// Determine this system's current public IP address using Porkbun's `/ping` endpoint.
     ///
     /// Porkbun may return either an IPv4 or IPv6 address; see [`ping_v4`][Self::ping_v4].
@@ -59,6 +121,39 @@ impl PorkbunClient {
         }
     }
 
+    /// Gets every domain on the account.
+    pub async fn list_domains(&self) -> eyre::Result<Vec<DomainInfo>> {
+        let url = format!("{BASE_URL}/domain/listAll");
+        let mut domains = Vec::new();
+
+        // Porkbun returns at most 1000 domains at a time; keep asking for more until we get an empty page.
+        loop {
+            let payload = json!({ "start": domains.len().to_string() });
+            let res = self.request::<ListDomainsResponse>(&url, Some(payload)).await?;
+            if res.domains.is_empty() {
+                break;
+            }
+            domains.extend(res.domains);
+        }
+
+        Ok(domains)
+    }
+
+    /// Gets the authoritative nameservers for the given domain, as set at the registry.
+    pub async fn get_nameservers(&self, domain: &str) -> eyre::Result<Vec<String>> {
+        let url = format!("{BASE_URL}/domain/getNs/{domain}");
+        let res = self.request::<GetNsResponse>(&url, None).await?;
+        Ok(res.ns)
+    }
+
+    /// Replaces the authoritative nameservers for the given domain.
+    pub async fn update_nameservers(&self, domain: &str, nameservers: &[String]) -> eyre::Result<()> {
+        let url = format!("{BASE_URL}/domain/updateNs/{domain}");
+        let payload = json!({ "ns": nameservers });
+        let _res = self.request::<UpdateNsResponse>(&url, Some(payload)).await?;
+        Ok(())
+    }
+
     /// Gets all the existing records for the given domain name.
     pub async fn get_existing_records(&self, domain: &str) -> eyre::Result<Vec<DNSRecord>> {
         let url = format!("{BASE_URL}/dns/retrieve/{domain}");
@@ -66,34 +161,193 @@ impl PorkbunClient {
         Ok(res.records)
     }
 
+    /// Gets the existing A and AAAA records on the given domain, along with any CNAME or ALIAS records (which conflict
+    /// with them).
+    ///
+    /// Porkbun can't filter a whole domain's records by type, so everything else is thrown away as soon as it arrives.
+    pub async fn get_address_records(&self, domain: &str) -> eyre::Result<Vec<DNSRecord>> {
+        let mut records = self.get_existing_records(domain).await?;
+        records
+            .retain(|rec| matches!(rec.typ, RecordType::A | RecordType::AAAA | RecordType::CNAME | RecordType::ALIAS));
+        Ok(records)
+    }
+
+    /// Gets the existing records for a single name and type on the given domain.
+    ///
+    /// A subdomain of `None` refers to the root of the domain.
+    pub async fn get_records_by_name_type(
+        &self,
+        domain: &DomainName,
+        subdomain: Option<&Subdomain>,
+        dns_type: &str,
+    ) -> eyre::Result<Vec<DNSRecord>> {
+        let url = match subdomain {
+            None => format!("{BASE_URL}/dns/retrieveByNameType/{domain}/{dns_type}"),
+            Some(sub) => format!("{BASE_URL}/dns/retrieveByNameType/{domain}/{dns_type}/{sub}"),
+        };
+        let res = self.request::<RetrieveResponse>(&url, None).await?;
+        Ok(res.records)
+    }
+
     /// Edits an existing record for the given target.
     ///
-    /// `record_id` must be fetched beforehand. It is not double checked to match Porkbun's API status before sending
-    /// the request.
-    pub async fn edit_record(&self, target: &Target, record_id: &str, new_content: IpAddr) -> eyre::Result<()> {
-        let url = format!("{BASE_URL}/dns/edit/{}/{}", target.domain(), record_id);
-        let payload = make_dns_payload(target, new_content);
+    /// `existing` must be fetched beforehand. It is not double checked to match Porkbun's API status before sending the
+    /// request.
+    pub async fn edit_record(
+        &self,
+        target: &Target,
+        existing: &DNSRecord,
+        new_content: &RecordData,
+    ) -> eyre::Result<()> {
+        let notes = match self.notes {
+            _ if !target.writes_notes() => None,
+            NotesMode::Preserve => existing.notes.clone(),
+            NotesMode::Marker | NotesMode::Off => self.make_notes(target),
+        };
+        self.edit(target, existing, new_content, notes).await
+    }
+
+    /// Edits an existing record for the given target, giving it exactly the given notes instead of this program's own
+    /// (e.g., to put a record back exactly the way it was).
+    pub async fn edit_record_with_notes(
+        &self,
+        target: &Target,
+        existing: &DNSRecord,
+        new_content: &RecordData,
+        notes: &str,
+    ) -> eyre::Result<()> {
+        self.edit(target, existing, new_content, Some(notes.to_string())).await
+    }
+
+    async fn edit(
+        &self,
+        target: &Target,
+        existing: &DNSRecord,
+        new_content: &RecordData,
+        notes: Option<String>,
+    ) -> eyre::Result<()> {
+        let url = format!("{BASE_URL}/dns/edit/{}/{}", target.domain(), existing.id);
+        let payload = make_dns_payload(target, new_content, notes);
         let _res = self.request::<EditResponse>(&url, Some(payload)).await?;
+        self.audit(Change::edit(target, existing, new_content)).await;
+        Ok(())
+    }
+
+    /// Edits all existing records for the given target that have the same DNS type as the given content.
+    ///
+    /// Unlike [`edit_record`][Self::edit_record], this does not require the record's ID to be known ahead of time.
+    pub async fn edit_records_by_name_type(&self, target: &Target, new_content: &RecordData) -> eyre::Result<()> {
+        let dns_type = &new_content.typ;
+        let url = match target.subdomain() {
+            None => format!("{BASE_URL}/dns/editByNameType/{}/{dns_type}", target.domain()),
+            Some(sub) => format!("{BASE_URL}/dns/editByNameType/{}/{dns_type}/{sub}", target.domain()),
+        };
+
+        // Without having fetched the record, there are no notes to preserve; leaving them out leaves them unchanged.
+        let notes = match self.notes {
+            NotesMode::Preserve => None,
+            NotesMode::Marker | NotesMode::Off => self.make_notes(target),
+        };
+
+        // The name and type are both part of the URL for this endpoint, so they don't belong in the payload.
+        let mut payload = make_dns_payload(target, new_content, notes);
+        if let Some(map) = payload.as_object_mut() {
+            map.remove("name");
+            map.remove("type");
+        }
+
+        let _res = self.request::<EditResponse>(&url, Some(payload)).await?;
+        self.audit(Change::edit_blind(target, new_content)).await;
         Ok(())
     }
 
     /// Creates a new DNS record for the given target with the given content.
     ///
     /// Returns the ID of the newly created record.
-    pub async fn create_record(&self, target: &Target, content: IpAddr) -> eyre::Result<String> {
+    pub async fn create_record(&self, target: &Target, content: &RecordData) -> eyre::Result<String> {
+        self.create(target, content, self.make_notes(target)).await
+    }
+
+    /// Creates a new record for the given target, giving it exactly the given notes instead of this program's own
+    /// (e.g., to put a record back exactly the way it was).
+    pub async fn create_record_with_notes(
+        &self,
+        target: &Target,
+        content: &RecordData,
+        notes: &str,
+    ) -> eyre::Result<String> {
+        self.create(target, content, Some(notes.to_string())).await
+    }
+
+    async fn create(&self, target: &Target, content: &RecordData, notes: Option<String>) -> eyre::Result<String> {
         let url = format!("{BASE_URL}/dns/create/{}", target.domain());
-        let payload = make_dns_payload(target, content);
+        let payload = make_dns_payload(target, content, notes);
         let res = self.request::<CreateResponse>(&url, Some(payload)).await?;
+        self.audit(Change {
+            id: Some(res.id.clone()),
+            ..Change::create(target, content)
+        })
+        .await;
         Ok(res.id)
     }
 
+    /// Deletes the given record from the given domain.
+    pub async fn delete_record(&self, domain: &str, record: &DNSRecord) -> eyre::Result<()> {
+        let url = format!("{BASE_URL}/dns/delete/{domain}/{}", record.id);
+        let _res = self.request::<DeleteResponse>(&url, None).await?;
+        self.audit(Change::delete(domain, record)).await;
+        Ok(())
+    }
+
+    /// Deletes every record with the given name and type from the given domain.
+    ///
+    /// A subdomain of `None` refers to the root of the domain.
+    pub async fn delete_records_by_name_type(
+        &self,
+        domain: &DomainName,
+        subdomain: Option<&Subdomain>,
+        dns_type: &str,
+    ) -> eyre::Result<()> {
+        let url = match subdomain {
+            None => format!("{BASE_URL}/dns/deleteByNameType/{domain}/{dns_type}"),
+            Some(sub) => format!("{BASE_URL}/dns/deleteByNameType/{domain}/{dns_type}/{sub}"),
+        };
+        let _res = self.request::<DeleteResponse>(&url, None).await?;
+        self.audit(Change::delete_blind(domain, subdomain, dns_type)).await;
+        Ok(())
+    }
+
+    /// Creates the notes for a record that this program is writing for the given target.
+    ///
+    /// This always starts with a marker saying the record is managed by this program, so that it can be recognized later
+    /// (e.g., by `prune`), followed by either a timestamp or the rendered `notes_template`. Returns `None` if notes are
+    /// disabled, either everywhere or just for this target.
+    fn make_notes(&self, target: &Target) -> Option<String> {
+        if self.notes == NotesMode::Off || !target.writes_notes() {
+            return None;
+        }
+
+        let timestamp = Local::now().format_with_items(TIMESTAMP_FMT.iter());
+        let Some(template) = &self.notes_template else {
+            return match self.notes_timestamp {
+                true => Some(format!("{NOTES_MARKER} on {timestamp}")),
+                false => Some(NOTES_MARKER.to_string()),
+            };
+        };
+
+        let notes = template
+            .replace("{hostname}", &HOSTNAME)
+            .replace("{version}", env!("CARGO_PKG_VERSION"))
+            .replace("{timestamp}", &timestamp.to_string())
+            .replace("{target}", &target.to_string());
+        Some(format!("{NOTES_MARKER}: {notes}"))
+    }
+
     /// Makes a POST request to Porkbun's API and returns the result parsed from JSON.
     async fn request<R>(&self, url: &str, payload: Option<JsonValue>) -> eyre::Result<R>
     where
         R: DeserializeOwned,
     {
-        log::trace!("Sending POST request to {url} with payload {payload:?}");
-
         let mut payload = match payload {
             Some(JsonValue::Object(map)) => map,
             Some(JsonValue::Null) | None => JsonMap::new(),
@@ -103,26 +357,79 @@ impl PorkbunClient {
         payload.insert("apikey".to_string(), json!(self.api_key));
         payload.insert("secretapikey".to_string(), json!(self.secret_key));
 
+        // Logged only after the keys are in, so that they always go through redaction (rather than relying on them not
+        // being there yet).
+        log::trace!("Sending POST request to {url} with payload {}", redact_keys(&payload));
+
         // Send the request and get its response as raw text before parsing it to JSON ourselves; lets us be more
         // precise with our error handling.
-        let res_raw = self
-            .reqwest
-            .post(url)
-            .json(&payload)
-            .send()
-            .await
-            .wrap_err("POST request failed")?;
-
-        let res_text = res_raw.text().await.wrap_err("Failed to read POST response body")?;
-        let res_size = res_text.len();
-        log::trace!("Received POST response of size {res_size} bytes");
-
-        match parse_response(&res_text[..]) {
-            Ok(Ok(parsed)) => Ok(parsed),
-            Ok(Err(err)) => Err(err),
-            Err(err) => Err(eyre!("{err:#}. Raw response: {res_text}")),
+        let start = Instant::now();
+        let res_text = async {
+            let res_raw = self
+                .reqwest
+                .post(url)
+                .json(&payload)
+                .send()
+                .await
+                .wrap_err("POST request failed")?;
+            res_raw.text().await.wrap_err("Failed to read POST response body")
+        }
+        .await;
+
+        let res = res_text.and_then(|res_text| {
+            let res_size = res_text.len();
+            log::trace!("Received POST response of size {res_size} bytes");
+
+            match parse_response(&res_text[..]) {
+                Ok(Ok(parsed)) => Ok(parsed),
+                Ok(Err(err)) => Err(err),
+                Err(err) => Err(eyre!("{err:#}. Raw response: {res_text}")),
+            }
+        });
+
+        let elapsed = start.elapsed();
+        log::debug!("POST {url} took {elapsed:.2?}");
+
+        (self.timings.lock().unwrap())
+            .entry(endpoint(url).to_string())
+            .or_default()
+            .record(elapsed, res.is_ok());
+        res
+    }
+}
+
+/// Copies a request payload with its API keys masked, so that it can be logged. Only the keys' prefixes (e.g., `sk1_`)
+/// are kept.
+fn redact_keys(payload: &JsonMap<String, JsonValue>) -> JsonValue {
+    let mut payload = payload.clone();
+    for field in ["apikey", "secretapikey"] {
+        if let Some(value) = payload.get_mut(field) {
+            let prefix = value
+                .as_str()
+                .and_then(|key| key.split_once('_'))
+                .map_or("", |(prefix, _)| prefix);
+            *value = match prefix {
+                "" => json!("****"),
+                prefix => json!(format!("{prefix}_****")),
+            };
         }
     }
+    JsonValue::Object(payload)
+}
+
+/// Gets the name of the API endpoint that a URL points to, without any domains, IDs, etc. (e.g., `dns/retrieve`).
+fn endpoint(url: &str) -> &str {
+    let path = (url.strip_prefix(BASE_URL))
+        .or_else(|| url.strip_prefix(BASE_URL_V4))
+        .unwrap_or(url)
+        .trim_start_matches('/');
+
+    // Every endpoint is either a single segment (`ping`) or a category and a name (`dns/retrieve`); everything after
+    // that is arguments.
+    match path.match_indices('/').nth(1) {
+        Some((i, _)) => &path[..i],
+        None => path,
+    }
 }
 
 /// Timestamp format for DNS records. Format is `Sun Jul 8 2001 at 8:46:23 PM`.
@@ -135,23 +442,44 @@ static TIMESTAMP_FMT: LazyLock<&'static [chrono::format::Item<'static>]> = LazyL
         .leak()
 });
 
+/// The name of this machine, for use in notes templates.
+pub static HOSTNAME: LazyLock<String> = LazyLock::new(|| {
+    // There's nothing in `std` for this, but it's not worth pulling in a crate for just this one thing either.
+    let from_file = |path: &str| std::fs::read_to_string(path).ok();
+    (crate::get_var("HOSTNAME").ok())
+        .or_else(|| from_file("/proc/sys/kernel/hostname"))
+        .or_else(|| from_file("/etc/hostname"))
+        .or_else(|| crate::get_var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+});
+
 /// Creates a JSON payload for creating or editing a DNS record for the given target.
-fn make_dns_payload(target: &Target, addr: IpAddr) -> JsonValue {
-    let timestamp = Local::now().format_with_items(TIMESTAMP_FMT.iter());
-    json!({
+///
+/// The `notes` field is left out entirely if `notes` is `None`.
+fn make_dns_payload(target: &Target, data: &RecordData, notes: Option<String>) -> JsonValue {
+    let mut payload = json!({
         // In both create and edit payloads, the `name` field only includes the subdomain, since the domain itself is a
         // path parameter within the URL:
         // - https://porkbun.com/api/json/v3/documentation#DNS%20Create%20Record
         // - https://porkbun.com/api/json/v3/documentation#DNS%20Edit%20Record%20by%20Domain%20and%20ID
-        "name": match target.subdomain() {
-            Some("@") | None => "",
-            Some(sub) => sub,
-        },
-        "type": addr.dns_type(),
-        "content": addr,
+        "name": target.subdomain().map_or("", Subdomain::as_str),
+        "type": data.typ,
+        "content": data.content,
         "ttl": target.ttl(),
-        "notes": format!("Last updated by {} on {timestamp}", env!("CARGO_PKG_NAME")),
-    })
+    });
+
+    if let Some(notes) = notes {
+        payload["notes"] = json!(notes);
+    }
+
+    // Porkbun's API takes numbers as strings everywhere else, but `prio` is the only one that's optional.
+    if let Some(prio) = data.prio {
+        payload["prio"] = json!(prio.to_string());
+    }
+
+    payload
 }
 
 /// Attempts to parse/deserialize Porkbun's API responses into the right type.
@@ -181,3 +509,50 @@ fn parse_response<R: DeserializeOwned>(body: &str) -> Result<eyre::Result<R>, ey
         _ => Err(eyre!("Response was in an unknown format")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(payload: JsonValue) -> JsonValue {
+        match payload {
+            JsonValue::Object(map) => redact_keys(&map),
+            _ => unreachable!("payloads are always objects"),
+        }
+    }
+
+    #[test]
+    fn redacts_keys_down_to_their_prefixes() {
+        let payload = json!({
+            "apikey": "pk1_8f2c1e0a9b7d6c5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0",
+            "secretapikey": "sk1_0e1d2c3b4a5f6e7d8c9b0a1f2e3d4c5b6a7f8e9d0c1b2a3f4e5d6c7b8a9f0e1d",
+            "content": "203.0.113.10",
+        });
+        assert_eq!(
+            redact(payload),
+            json!({ "apikey": "pk1_****", "secretapikey": "sk1_****", "content": "203.0.113.10" }),
+        );
+    }
+
+    #[test]
+    fn redacts_keys_without_prefixes() {
+        assert_eq!(
+            redact(json!({ "apikey": "abcdef0123456789", "secretapikey": "" })),
+            json!({ "apikey": "****", "secretapikey": "****" }),
+        );
+    }
+
+    #[test]
+    fn redacts_non_string_keys() {
+        assert_eq!(
+            redact(json!({ "apikey": 1234567890, "secretapikey": null })),
+            json!({ "apikey": "****", "secretapikey": "****" }),
+        );
+    }
+
+    #[test]
+    fn leaves_payloads_without_keys_alone() {
+        let payload = json!({ "name": "www", "type": "A", "content": "203.0.113.10" });
+        assert_eq!(redact(payload.clone()), payload);
+    }
+}
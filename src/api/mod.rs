@@ -3,27 +3,68 @@ mod model;
 
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-pub use self::client::PorkbunClient;
-pub use self::model::DNSRecord;
+pub use self::client::{HOSTNAME, PorkbunClient};
+pub use self::model::{DNSRecord, RecordData, RecordType};
 
-const BASE_URL: &'static str = "https://api.porkbun.com/api/json/v3";
-const BASE_URL_V4: &'static str = "https://api-ipv4.porkbun.com/api/json/v3";
+const BASE_URL: &str = "https://api.porkbun.com/api/json/v3";
+const BASE_URL_V4: &str = "https://api-ipv4.porkbun.com/api/json/v3";
+
+/// The start of the notes left on every record this program creates or edits. Used to recognize those records later.
+const NOTES_MARKER: &str = concat!("Last updated by ", env!("CARGO_PKG_NAME"));
+
+/// The placeholders that can be used in a custom notes template.
+pub const NOTES_PLACEHOLDERS: &[&str] = &["hostname", "version", "timestamp", "target"];
 
 pub trait IpAddrExt {
     /// Gets the type of DNS record associated with this IP address type.
     fn dns_type(&self) -> &'static str;
+
+    /// If this address is in a range that isn't reachable from the public internet, returns a description of that
+    /// range (for use in error messages).
+    fn private_kind(&self) -> Option<&'static str>;
 }
 
 impl IpAddrExt for Ipv4Addr {
     fn dns_type(&self) -> &'static str {
         "A"
     }
+
+    fn private_kind(&self) -> Option<&'static str> {
+        let [a, b, ..] = self.octets();
+        if self.is_private() {
+            Some("a private (RFC 1918)")
+        } else if a == 100 && (b & 0b1100_0000) == 64 {
+            Some("a carrier-grade NAT (100.64.0.0/10)")
+        } else if self.is_loopback() {
+            Some("a loopback")
+        } else if self.is_link_local() {
+            Some("a link-local")
+        } else if self.is_unspecified() {
+            Some("an unspecified")
+        } else {
+            None
+        }
+    }
 }
 
 impl IpAddrExt for Ipv6Addr {
     fn dns_type(&self) -> &'static str {
         "AAAA"
     }
+
+    fn private_kind(&self) -> Option<&'static str> {
+        if self.is_unique_local() {
+            Some("a unique local (fc00::/7)")
+        } else if self.is_unicast_link_local() {
+            Some("a link-local")
+        } else if self.is_loopback() {
+            Some("a loopback")
+        } else if self.is_unspecified() {
+            Some("an unspecified")
+        } else {
+            None
+        }
+    }
 }
 
 impl IpAddrExt for IpAddr {
@@ -33,4 +74,11 @@ impl IpAddrExt for IpAddr {
             IpAddr::V6(addr) => addr.dns_type(),
         }
     }
+
+    fn private_kind(&self) -> Option<&'static str> {
+        match self {
+            IpAddr::V4(addr) => addr.private_kind(),
+            IpAddr::V6(addr) => addr.private_kind(),
+        }
+    }
 }
@@ -1,9 +1,10 @@
+use std::fmt::Display;
 use std::net::IpAddr;
 
 use eyre::eyre;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::IpAddrExt;
+use super::NOTES_MARKER;
 
 /// Response returned by Porkbun's `/ping` endpoint.
 #[derive(Debug, Deserialize)]
@@ -30,6 +31,13 @@ pub struct CreateResponse {
 #[serde(rename_all = "camelCase")]
 pub struct EditResponse {}
 
+/// Response returned by Porkbun's `/delete` endpoint.
+///
+/// Just like [`EditResponse`], there are no fields other than `status`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
+
 /// Response returned by Porkbun's `/retrieve` endpoint.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -37,6 +45,162 @@ pub struct RetrieveResponse {
     pub records: Vec<DNSRecord>,
 }
 
+/// Response returned by Porkbun's `/domain/getNs` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetNsResponse {
+    pub ns: Vec<String>,
+}
+
+/// Response returned by Porkbun's `/domain/updateNs` endpoint.
+///
+/// Just like [`EditResponse`], there are no fields other than `status`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateNsResponse {}
+
+/// Response returned by Porkbun's `/domain/listAll` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDomainsResponse {
+    // Porkbun leaves this key off entirely when the `start` offset is past the end of the list.
+    #[serde(default)]
+    pub domains: Vec<DomainInfo>,
+}
+
+/// A single domain on a Porkbun account, as returned by `/domain/listAll`.
+///
+/// Only the fields this program actually uses are included.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainInfo {
+    pub domain: String,
+    pub status: Option<String>,
+}
+
+/// The type of a DNS record.
+///
+/// Every type that Porkbun supports has its own variant. Anything else is kept as-is (in uppercase), just in case.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    AAAA,
+    CNAME,
+    ALIAS,
+    TXT,
+    NS,
+    MX,
+    SRV,
+    TLSA,
+    CAA,
+    HTTPS,
+    SVCB,
+    SSHFP,
+    Other(String),
+}
+
+impl RecordType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RecordType::A => "A",
+            RecordType::AAAA => "AAAA",
+            RecordType::CNAME => "CNAME",
+            RecordType::ALIAS => "ALIAS",
+            RecordType::TXT => "TXT",
+            RecordType::NS => "NS",
+            RecordType::MX => "MX",
+            RecordType::SRV => "SRV",
+            RecordType::TLSA => "TLSA",
+            RecordType::CAA => "CAA",
+            RecordType::HTTPS => "HTTPS",
+            RecordType::SVCB => "SVCB",
+            RecordType::SSHFP => "SSHFP",
+            RecordType::Other(other) => other,
+        }
+    }
+}
+
+impl From<&str> for RecordType {
+    fn from(typ: &str) -> Self {
+        match &typ.to_ascii_uppercase()[..] {
+            "A" => RecordType::A,
+            "AAAA" => RecordType::AAAA,
+            "CNAME" => RecordType::CNAME,
+            "ALIAS" => RecordType::ALIAS,
+            "TXT" => RecordType::TXT,
+            "NS" => RecordType::NS,
+            "MX" => RecordType::MX,
+            "SRV" => RecordType::SRV,
+            "TLSA" => RecordType::TLSA,
+            "CAA" => RecordType::CAA,
+            "HTTPS" => RecordType::HTTPS,
+            "SVCB" => RecordType::SVCB,
+            "SSHFP" => RecordType::SSHFP,
+            other => RecordType::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<IpAddr> for RecordType {
+    fn from(addr: IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => RecordType::A,
+            IpAddr::V6(_) => RecordType::AAAA,
+        }
+    }
+}
+
+impl Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl PartialEq<str> for RecordType {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for RecordType {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Serialize for RecordType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let typ = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        Ok(RecordType::from(&typ[..]))
+    }
+}
+
+/// The type and content of a DNS record: everything needed to create or edit one, other than its name and TTL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordData {
+    pub typ: RecordType,
+    pub content: String,
+    /// Priority, for record types which have one (MX and SRV).
+    pub prio: Option<u32>,
+}
+
+impl From<IpAddr> for RecordData {
+    fn from(addr: IpAddr) -> Self {
+        Self {
+            typ: RecordType::from(addr),
+            content: addr.to_string(),
+            prio: None,
+        }
+    }
+}
+
 /// A single Porkbun DNS record.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -45,7 +209,7 @@ pub struct DNSRecord {
     pub id: String,
     pub name: String,
     #[serde(rename = "type")]
-    pub typ: String,
+    pub typ: RecordType,
     pub content: String,
     #[serde(with = "optional_or_stringified_number")]
     pub ttl: Option<u32>,
@@ -55,22 +219,30 @@ pub struct DNSRecord {
 }
 
 impl DNSRecord {
+    /// Checks whether this record was created or last edited by this program, based on the marker it leaves in
+    /// records' notes.
+    pub fn is_managed(&self) -> bool {
+        self.notes.as_deref().is_some_and(|notes| notes.starts_with(NOTES_MARKER))
+    }
+
     /// Attempts to parse an IP address out of this DNS record's [`content`][Self::content] field.
     ///
     /// Returns an error if the IP address is not valid, if this is not an A/AAAA record, or if the type of IP address
     /// does not match what is expected for the record's type.
     pub fn try_parse_ip(&self) -> eyre::Result<IpAddr> {
-        if !(self.typ == "A" || self.typ == "AAAA") {
-            return Err(eyre!("cannot parse IP address from record with type {}", self.typ));
-        }
+        let exp = match self.typ {
+            RecordType::A => "IPv4",
+            RecordType::AAAA => "IPv6",
+            _ => return Err(eyre!("cannot parse IP address from record with type {}", self.typ)),
+        };
 
         let addr = self.content.parse::<IpAddr>()?;
-        if addr.dns_type() != self.typ {
-            let exp = if self.typ == "A" { "IPv4" } else { "IPv6" };
-            let acc = if addr.is_ipv4() { "IPv4" } else { "IPv6" };
-            Err(eyre!("record of type {} has the wrong IP address type (should have {exp}, has {acc})", self.typ))
-        } else {
-            Ok(addr)
+        match (&self.typ, addr) {
+            (RecordType::A, IpAddr::V4(_)) | (RecordType::AAAA, IpAddr::V6(_)) => Ok(addr),
+            _ => {
+                let acc = if addr.is_ipv4() { "IPv4" } else { "IPv6" };
+                Err(eyre!("record of type {} has the wrong IP address type (should have {exp}, has {acc})", self.typ))
+            },
         }
     }
 }
@@ -195,7 +367,7 @@ mod primitive_as_string {
         fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> { Ok(v.to_string()) }
 
         fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
-            Ok(v.then_some("true").unwrap_or("false").to_string())
+            Ok(if v { "true" } else { "false" }.to_string())
         }
     }
 
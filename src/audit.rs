@@ -0,0 +1,87 @@
+//! An append-only log of every change made to records on Porkbun.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use eyre::WrapErr;
+use serde::Serialize;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::plan::Change;
+use crate::sources::random_id;
+
+/// A single line of the audit log.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    /// When the change was made, in RFC 3339 format.
+    timestamp: String,
+    /// Identifies the run that the change was made during, so that changes made together can be grouped.
+    run_id: &'a str,
+    #[serde(flatten)]
+    change: &'a Change,
+}
+
+/// Appends a line of JSON to a file for every record that gets created, edited, or deleted.
+///
+/// Only changes that Porkbun has accepted are logged, so nothing is ever written during dry runs.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    run_id: Mutex<String>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            run_id: Mutex::new(new_run_id()),
+        }
+    }
+
+    /// Starts a new run, so that the changes made from now on are logged under a different run ID.
+    pub fn start_run(&self) {
+        *self.run_id.lock().unwrap() = new_run_id();
+    }
+
+    /// Adds a change to the log. Failures are only logged, since the change itself has already been made.
+    pub async fn record(&self, change: &Change) {
+        if let Err(err) = self.append(change).await {
+            log::error!("Failed to write to audit log {}: {err:#}", self.path.display());
+        }
+    }
+
+    async fn append(&self, change: &Change) -> eyre::Result<()> {
+        let run_id = self.run_id.lock().unwrap().clone();
+        let entry = Entry {
+            timestamp: Utc::now().to_rfc3339(),
+            run_id: &run_id,
+            change,
+        };
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        if let Some(dir) = self.path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir).await.wrap_err("Failed to create audit log directory")?;
+        }
+
+        // Each line is written all at once, so that entries from concurrent updates never end up interleaved.
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .wrap_err("Failed to open audit log")?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+/// Makes a short random ID for a run.
+fn new_run_id() -> String {
+    random_id::<8>().iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,94 @@
+//! Snapshots of domains' DNS records, taken before anything on them is changed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use eyre::WrapErr;
+use futures::lock::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::api::{DNSRecord, PorkbunClient};
+
+/// The contents of a single backup file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The domain that these records were fetched from.
+    pub domain: String,
+    /// When the snapshot was taken, in RFC 3339 format.
+    pub timestamp: String,
+    /// Every record on the domain at the time of the snapshot.
+    pub records: Vec<DNSRecord>,
+}
+
+/// Keeps track of which domains have already been backed up during this run.
+#[derive(Debug)]
+pub struct Backups {
+    dir: Option<PathBuf>,
+    done: Mutex<HashSet<String>>,
+}
+
+impl Backups {
+    /// Creates a new backup tracker. If `dir` is `None`, backups are disabled.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            done: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Saves a snapshot of all of a domain's records, unless one has already been saved during this run (or backups
+    /// are disabled).
+    ///
+    /// This should be called before any of a domain's records are edited or deleted. Since targets are updated
+    /// concurrently, the lock is held for the whole backup so that every change waits for the snapshot to be written.
+    pub async fn ensure(&self, client: &PorkbunClient, domain: &str) -> eyre::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+
+        let key = domain.to_ascii_lowercase();
+        let mut done = self.done.lock().await;
+        if done.contains(&key) {
+            return Ok(());
+        }
+
+        let res = async {
+            let records = client.get_existing_records(domain).await?;
+            let path = save(dir, domain, records).await?;
+            log::info!("Backed up DNS records for {domain} to {}.", path.display());
+            eyre::Ok(())
+        };
+
+        res.await
+            .wrap_err_with(|| format!("Failed to back up DNS records for {domain}"))?;
+        done.insert(key);
+        Ok(())
+    }
+
+    /// Forgets which domains have already been backed up, so that they're backed up again before the next run changes
+    /// them.
+    pub async fn reset(&self) {
+        self.done.lock().await.clear();
+    }
+}
+
+/// Writes a snapshot of a domain's records to a new, timestamped file in the given directory. Returns the path of the
+/// new file.
+async fn save(dir: &Path, domain: &str, records: Vec<DNSRecord>) -> eyre::Result<PathBuf> {
+    let now = Utc::now();
+    let snapshot = Snapshot {
+        domain: domain.to_string(),
+        timestamp: now.to_rfc3339(),
+        records,
+    };
+
+    fs::create_dir_all(dir).await.wrap_err("Failed to create backup directory")?;
+
+    let path = dir.join(format!("{domain}_{}.json", now.format("%Y%m%dT%H%M%SZ")));
+    let json = serde_json::to_string_pretty(&snapshot)?;
+    fs::write(&path, json).await.wrap_err("Failed to write backup file")?;
+
+    Ok(path)
+}
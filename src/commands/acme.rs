@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+
+use super::split_fqdn;
+use crate::App;
+use crate::api::{RecordData, RecordType};
+use crate::config::{MIN_TTL, Target};
+
+#[derive(Debug, clap::Args)]
+pub struct AcmeArgs {
+    #[command(subcommand)]
+    pub action: AcmeAction,
+
+    /// Seconds to wait after creating a challenge record before exiting, to give it time to propagate.
+    #[arg(long, value_name = "SECONDS", default_value_t = 0)]
+    pub wait: u64,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum AcmeAction {
+    /// Create a challenge TXT record (compatible with lego's `exec` provider).
+    Present {
+        /// Full name of the challenge record, e.g. `_acme-challenge.example.com.`
+        fqdn: String,
+        /// The challenge token to put in the record.
+        value: String,
+    },
+
+    /// Delete a challenge TXT record (compatible with lego's `exec` provider).
+    Cleanup {
+        /// Full name of the challenge record, e.g. `_acme-challenge.example.com.`
+        fqdn: String,
+        /// The challenge token in the record to delete.
+        value: String,
+    },
+
+    /// Create a challenge TXT record from certbot's environment (for use with `--manual-auth-hook`).
+    CertbotAuth,
+
+    /// Delete a challenge TXT record from certbot's environment (for use with `--manual-cleanup-hook`).
+    CertbotCleanup,
+}
+
+/// Challenge records only need to exist for a few minutes, so there's no reason to use a longer TTL than Porkbun's
+/// minimum.
+const CHALLENGE_TTL: u32 = MIN_TTL;
+
+/// Creates or deletes `_acme-challenge` TXT records for ACME DNS-01 challenges.
+pub async fn acme(app: &App, args: AcmeArgs) -> eyre::Result<()> {
+    match args.action {
+        AcmeAction::Present { fqdn, value } => present(app, &fqdn, value, args.wait).await,
+        AcmeAction::Cleanup { fqdn, value } => cleanup(app, &fqdn, &value).await,
+        AcmeAction::CertbotAuth => {
+            let (domain, value) = certbot_env()?;
+            present(app, &format!("_acme-challenge.{domain}"), value, args.wait).await
+        },
+        AcmeAction::CertbotCleanup => {
+            let (domain, value) = certbot_env()?;
+            cleanup(app, &format!("_acme-challenge.{domain}"), &value).await
+        },
+    }
+}
+
+/// Reads the domain being validated and the validation token from the environment variables certbot sets for hooks.
+fn certbot_env() -> eyre::Result<(String, String)> {
+    let domain = crate::get_var("CERTBOT_DOMAIN").wrap_err("Failed to get CERTBOT_DOMAIN from environment")?;
+    let value = crate::get_var("CERTBOT_VALIDATION").wrap_err("Failed to get CERTBOT_VALIDATION from environment")?;
+    Ok((domain, value))
+}
+
+async fn present(app: &App, fqdn: &str, value: String, wait: u64) -> eyre::Result<()> {
+    let (domain, subdomain) = split_fqdn(app, fqdn).await?;
+    let target = Target::new(domain, subdomain, CHALLENGE_TTL);
+    let data = RecordData {
+        typ: RecordType::TXT,
+        content: value,
+        prio: None,
+    };
+
+    if !app.dry_run {
+        app.client
+            .create_record(&target, &data)
+            .await
+            .wrap_err("Failed to create challenge record")?;
+    }
+
+    log::info!("{target}: Created challenge TXT record with content {:?}.", data.content);
+
+    if wait > 0 && !app.dry_run {
+        log::info!("Waiting {wait} seconds for the challenge record to propagate...");
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+    }
+
+    Ok(())
+}
+
+async fn cleanup(app: &App, fqdn: &str, value: &str) -> eyre::Result<()> {
+    let (domain, subdomain) = split_fqdn(app, fqdn).await?;
+    let target = Target::new(domain, subdomain, CHALLENGE_TTL);
+
+    let records = app
+        .client
+        .get_records_by_name_type(target.domain(), target.subdomain(), "TXT")
+        .await
+        .wrap_err("Failed to fetch existing challenge records")?;
+
+    let matching = records.iter().filter(|rec| rec.content == value).collect::<Vec<_>>();
+    if matching.is_empty() {
+        log::warn!("{target}: Found no challenge TXT record with content {value:?}. Nothing to do.");
+        return Ok(());
+    }
+
+    app.backup(target.domain()).await?;
+
+    let mut err_count = 0usize;
+    for record in matching {
+        if !app.dry_run
+            && let Err(err) = app.client.delete_record(target.domain(), record).await
+        {
+            log::error!("{target}: Failed to delete challenge TXT record: {err:#}");
+            err_count += 1;
+            continue;
+        }
+
+        log::info!("{target}: Deleted challenge TXT record with content {value:?}.");
+    }
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!("Failed to delete {n} challenge {records}", records = pluralize!("record", "records", n))),
+    }
+}
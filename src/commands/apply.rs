@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use eyre::{WrapErr, eyre};
+use tokio::fs;
+
+use super::name_to_target;
+use crate::App;
+use crate::api::DNSRecord;
+use crate::config::{DEFAULT_TTL, Target};
+use crate::plan::{Action, Change, Plan, RecordState};
+
+#[derive(Debug, clap::Args)]
+pub struct ApplyArgs {
+    /// A plan file, as written by the `plan` subcommand.
+    #[arg(value_name = "FILE", required_unless_present = "plan")]
+    pub file: Option<PathBuf>,
+
+    /// The plan file, given as an option instead.
+    #[arg(long, value_name = "FILE", conflicts_with = "file")]
+    pub plan: Option<PathBuf>,
+
+    /// Make exactly the planned changes, without needing anything from this machine.
+    ///
+    /// The config file is optional (the API keys can come from the environment instead), and no domains are
+    /// discovered. Every change is checked against Porkbun's current records before any of them are made, and if any
+    /// record has drifted since the plan was made, nothing is changed at all.
+    #[arg(long)]
+    pub no_detect: bool,
+}
+
+/// What to do for a single planned change, once it has been checked against the current records.
+enum Step<'a> {
+    /// The planned record already exists, so there's nothing left to do.
+    Done(Target),
+    Create(Target, &'a RecordState),
+    Edit(Target, &'a DNSRecord, &'a RecordState),
+    Delete(&'a DNSRecord),
+}
+
+/// Makes the changes listed in a plan file.
+///
+/// Before each change is made, the record it affects is checked to still be in the state it was in when the plan was
+/// made. Any change whose record has been modified since then is skipped and reported as an error, since it was planned
+/// against out-of-date information. With `--no-detect`, every change is checked before any are made, and any drift
+/// means nothing is made at all.
+pub async fn apply(app: &App, args: ApplyArgs) -> eyre::Result<()> {
+    let path = args.file.or(args.plan).expect("clap requires a plan file");
+    let text = fs::read_to_string(&path).await.wrap_err("Failed to read plan file")?;
+    let plan = serde_json::from_str::<Plan>(&text).wrap_err("Failed to parse plan file")?;
+    let strict = args.no_detect;
+
+    if app.dry_run {
+        log::warn!("dry_run is enabled: no create/edit/delete requests will be sent through to Porkbun.");
+    }
+
+    log::info!("Applying plan made at {}.", plan.timestamp);
+
+    let mut by_domain = BTreeMap::<&str, Vec<&Change>>::new();
+    for change in &plan.changes {
+        by_domain.entry(&change.domain).or_default().push(change);
+    }
+
+    let mut err_count = 0usize;
+    let mut current = BTreeMap::<&str, Vec<DNSRecord>>::new();
+    for (&domain, changes) in &by_domain {
+        match app.client.get_existing_records(domain).await {
+            Ok(records) => _ = current.insert(domain, records),
+            Err(err) => {
+                log::error!("Failed to fetch DNS records for {domain}: {err:#}");
+                err_count += changes.len();
+            },
+        }
+    }
+
+    let mut steps = BTreeMap::<&str, Vec<(&Change, Step)>>::new();
+    for (domain, records) in &current {
+        for &change in &by_domain[domain] {
+            match check_change(change, records, strict) {
+                Ok(step) => steps.entry(domain).or_default().push((change, step)),
+                Err(err) => {
+                    log::error!("{}: {err:#}", change.name);
+                    err_count += 1;
+                },
+            }
+        }
+    }
+
+    if strict && err_count > 0 {
+        return Err(eyre!(
+            "{err_count} planned {changes} could not be made exactly as planned. Nothing was changed.",
+            changes = pluralize!("change", "changes", err_count),
+        ));
+    }
+
+    for (domain, steps) in steps {
+        if let Err(err) = app.backup(domain).await {
+            log::error!("Not applying changes to {domain}: {err:#}");
+            err_count += steps.len();
+            continue;
+        }
+
+        for (change, step) in steps {
+            if let Err(err) = make_change(app, change, step).await {
+                log::error!("{}: {err:#}", change.name);
+                err_count += 1;
+            }
+        }
+    }
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!("Failed to apply {n} {changes}", changes = pluralize!("change", "changes", n))),
+    }
+}
+
+/// Checks that a change from a plan still makes sense to make, against the current records on its domain.
+///
+/// When `strict`, a record that has already been created counts as having drifted too, rather than as being done.
+fn check_change<'a>(change: &'a Change, current: &'a [DNSRecord], strict: bool) -> eyre::Result<Step<'a>> {
+    let typ = &change.typ;
+    let find_planned = || -> eyre::Result<&DNSRecord> {
+        let id = change
+            .id
+            .as_deref()
+            .ok_or_else(|| eyre!("Planned {typ} record change has no record ID"))?;
+        let record = (current.iter().find(|rec| rec.id == id))
+            .ok_or_else(|| eyre!("{typ} record with ID {id} no longer exists"))?;
+
+        if change.old.as_ref() != Some(&RecordState::from(record)) {
+            return Err(eyre!("{typ} record with ID {id} has changed since the plan was made"));
+        }
+        Ok(record)
+    };
+
+    let step = match change.action {
+        Action::Create => {
+            let new = change
+                .new
+                .as_ref()
+                .ok_or_else(|| eyre!("Planned {typ} record creation has no content"))?;
+            let target = name_to_target(&change.domain, &change.name, new.ttl.unwrap_or(DEFAULT_TTL))?;
+            let content = &new.content;
+
+            let exists = current
+                .iter()
+                .any(|rec| rec.typ == *typ && target.matches_record(rec) && rec.content == *content);
+            match exists {
+                true if strict => {
+                    return Err(eyre!("{typ} record with content {content:?} was created since the plan was made"));
+                },
+                true => Step::Done(target),
+                false => Step::Create(target, new),
+            }
+        },
+        Action::Edit => {
+            let record = find_planned()?;
+            let new = change
+                .new
+                .as_ref()
+                .ok_or_else(|| eyre!("Planned {typ} record edit has no content"))?;
+            let target = name_to_target(&change.domain, &change.name, new.ttl.unwrap_or(DEFAULT_TTL))?;
+            Step::Edit(target, record, new)
+        },
+        Action::Delete => Step::Delete(find_planned()?),
+    };
+
+    Ok(step)
+}
+
+/// Makes a single change from a plan, once it has been checked.
+async fn make_change(app: &App, change: &Change, step: Step<'_>) -> eyre::Result<()> {
+    let typ = &change.typ;
+    match step {
+        Step::Done(target) => {
+            let content = change.new.as_ref().map_or("", |new| &new.content);
+            log::info!("{target}: {typ} record with content {content:?} already exists. Nothing to do.");
+        },
+        Step::Create(target, new) => {
+            if !app.dry_run {
+                let id = (app.client.create_record(&target, &new.to_data(typ)).await)
+                    .wrap_err("Failed to create DNS record")?;
+                log::trace!("{target}: New record has ID {id}");
+            }
+
+            log::info!("{target}: Created new {typ} record with content {:?}.", new.content);
+        },
+        Step::Edit(target, record, new) => {
+            if !app.dry_run {
+                (app.client.edit_record(&target, record, &new.to_data(typ)).await)
+                    .wrap_err("Failed to edit DNS record")?;
+            }
+
+            let (old, new) = (&record.content, &new.content);
+            log::info!("{target}: Edited existing {typ} record from {old:?} to {new:?}.");
+        },
+        Step::Delete(record) => {
+            if !app.dry_run {
+                (app.client.delete_record(&change.domain, record).await).wrap_err("Failed to delete DNS record")?;
+            }
+
+            log::info!("{}: Deleted {typ} record with content {:?}.", change.name, record.content);
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use eyre::WrapErr;
+
+use crate::config::{Args, Config};
+
+/// Checks that the config file can be loaded and that API keys are available, without making any network requests.
+///
+/// Everything that is checked when starting a normal run is checked here too, so a config that passes should start up
+/// fine. A few settings that are valid but almost certainly mistakes are reported as warnings.
+pub async fn check(args: Args) -> eyre::Result<()> {
+    let paths = args.config.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+    let mut config = Config::from_args(args).await?;
+    crate::load_keys(&config).await.wrap_err("Failed to load API keys")?;
+    let providers = crate::init_providers(&mut config).wrap_err("Invalid configuration")?;
+    let notifiers = crate::init_notifiers(&mut config).wrap_err("Invalid configuration")?;
+
+    let ipv4 = config.ipv4.is_enabled();
+    let ipv6 = config.ipv6.is_enabled();
+    if !ipv4 && !ipv6 && (!config.targets.is_empty() || !providers.is_empty()) {
+        log::warn!("IPv4 and IPv6 are both disabled, so no targets will ever be updated.");
+    }
+
+    if !ipv6 && let Some(target) = config.targets.iter().find(|tgt| tgt.has_ipv6_suffix()) {
+        log::warn!("{target} has an ipv6_suffix, but IPv6 is disabled.");
+    }
+
+    if config.refresh_every.is_some() && config.state_file.is_none() {
+        log::warn!("refresh_every has no effect without a state_file.");
+    }
+
+    if config.auto_discover {
+        log::info!("auto_discover is enabled; discovered targets can only be checked during a real run.");
+    }
+
+    let n = config.targets.len();
+    let m = config.records.len();
+    let k = config.sync.len();
+    let p = providers.len();
+    let q = notifiers.len();
+    println!(
+        "{}: OK ({n} {targets}, {m} static {records}, {k} synced {domains}, {p} other {providers}, {q} {notifiers})",
+        paths.join(", "),
+        targets = pluralize!("target", "targets", n),
+        records = pluralize!("record", "records", m),
+        domains = pluralize!("domain", "domains", k),
+        providers = pluralize!("provider", "providers", p),
+        notifiers = pluralize!("notifier", "notifiers", q),
+    );
+
+    Ok(())
+}
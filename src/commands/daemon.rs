@@ -0,0 +1,73 @@
+use std::net::SocketAddr;
+use std::pin::pin;
+use std::time::Duration;
+
+use futures::future;
+
+use crate::config::parse_duration;
+use crate::{App, server};
+
+#[derive(Debug, clap::Args)]
+pub struct DaemonArgs {
+    /// How long to wait between updates (e.g., "5m" or "1h").
+    #[arg(
+        long,
+        env = "PORKBUN_INTERVAL",
+        value_name = "DURATION",
+        default_value = "5m",
+        value_parser = parse_interval,
+    )]
+    pub interval: Duration,
+
+    /// Serve Prometheus metrics and a JSON status API on this address (e.g., "127.0.0.1:9853").
+    #[arg(long, env = "PORKBUN_LISTEN", value_name = "ADDR")]
+    pub listen: Option<SocketAddr>,
+}
+
+/// Keeps running, updating records every `interval` until the process is stopped.
+///
+/// Each update works exactly like a single run without a subcommand would, except that errors don't stop the next one
+/// from happening. The state file is kept in memory between updates, too. Once asked to stop, the current update is
+/// wrapped up (if there is one) and no more are started.
+pub async fn daemon(app: &App, args: DaemonArgs) -> eyre::Result<()> {
+    app.status.lock().unwrap().set_interval(args.interval);
+
+    let listener = match args.listen {
+        Some(addr) => Some(server::bind(addr).await?),
+        None => None,
+    };
+
+    let server = async {
+        match listener {
+            Some(listener) => server::serve(listener, |req| server::handle(app, req)).await,
+            None => future::pending().await,
+        }
+    };
+
+    let updates = async {
+        loop {
+            crate::update(app).await;
+            if app.shutdown.is_requested() {
+                break;
+            }
+
+            log::debug!("Next update in {}s.", args.interval.as_secs());
+            tokio::select! {
+                _ = tokio::time::sleep(args.interval) => {},
+                _ = app.shutdown.requested() => break,
+            }
+        }
+    };
+
+    // The server is only there to report on updates, so it stops along with them.
+    future::select(pin!(server), pin!(updates)).await;
+    log::info!("Stopped.");
+    Ok(())
+}
+
+pub(super) fn parse_interval(str: &str) -> Result<Duration, String> {
+    match parse_duration(str)? {
+        Duration::ZERO => Err("interval must be longer than zero".to_string()),
+        interval => Ok(interval),
+    }
+}
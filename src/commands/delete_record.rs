@@ -0,0 +1,93 @@
+use eyre::{WrapErr, eyre};
+
+use super::confirm;
+use crate::App;
+use crate::api::DNSRecord;
+use crate::names::{DomainName, Subdomain};
+
+#[derive(Debug, clap::Args)]
+pub struct DeleteRecordArgs {
+    /// The domain to delete records from.
+    pub domain: DomainName,
+
+    /// The ID of the record to delete, as shown by `list-records`.
+    #[arg(long, required_unless_present = "typ", conflicts_with_all = ["name", "typ"])]
+    pub id: Option<String>,
+
+    /// Delete every record with this subdomain (and `--type`). Omit it, or use "@", for the root of the domain.
+    #[arg(long, value_name = "SUBDOMAIN", requires = "typ")]
+    pub name: Option<String>,
+
+    /// Delete every record of this type (with the given `--name`).
+    #[arg(long = "type", value_name = "TYPE")]
+    pub typ: Option<String>,
+
+    /// Delete the records without asking for confirmation first.
+    #[arg(long)]
+    pub confirm: bool,
+}
+
+/// Deletes a single record by ID, or every record with a given name and type.
+///
+/// The records to be deleted are always listed first, and must be confirmed interactively (or with `--confirm`).
+pub async fn delete_record(app: &App, args: DeleteRecordArgs) -> eyre::Result<()> {
+    let domain = &args.domain;
+    let subdomain = Subdomain::parse(args.name.as_deref().unwrap_or_default()).map_err(|err| eyre!(err))?;
+    let subdomain = subdomain.as_ref();
+    let typ = args.typ.as_deref().map(str::to_ascii_uppercase);
+
+    let records = match (&args.id, &typ) {
+        (Some(id), _) => {
+            let records = app.client.get_existing_records(domain).await;
+            let records = records.wrap_err_with(|| format!("Failed to fetch DNS records for {domain}"))?;
+            records.into_iter().filter(|rec| rec.id == *id).collect::<Vec<_>>()
+        },
+        (None, Some(typ)) => {
+            let records = app.client.get_records_by_name_type(domain, subdomain, typ).await;
+            records.wrap_err_with(|| format!("Failed to fetch DNS records for {domain}"))?
+        },
+        (None, None) => unreachable!("clap requires either --id or --type"),
+    };
+
+    if records.is_empty() {
+        return Err(eyre!("No matching records found on {domain}"));
+    }
+
+    for record in &records {
+        println!("- {}", describe(record));
+    }
+
+    let n = records.len();
+    let what = pluralize!("record", "records", n);
+    if app.dry_run {
+        log::info!("dry_run is enabled: {n} {what} would have been deleted.");
+        return Ok(());
+    }
+
+    if !args.confirm && !confirm(&format!("Delete {n} {what} from {domain}?"))? {
+        log::info!("Nothing was deleted.");
+        return Ok(());
+    }
+
+    app.backup(domain).await?;
+
+    match (&args.id, &typ) {
+        (Some(_), _) => app.client.delete_record(domain, &records[0]).await,
+        (None, Some(typ)) => app.client.delete_records_by_name_type(domain, subdomain, typ).await,
+        (None, None) => unreachable!("clap requires either --id or --type"),
+    }
+    .wrap_err_with(|| format!("Failed to delete {what}"))?;
+
+    log::info!("Deleted {n} {what} from {domain}.");
+    Ok(())
+}
+
+/// Formats a record as a single line for the list of records to be deleted.
+fn describe(record: &DNSRecord) -> String {
+    let ttl = record
+        .ttl
+        .map(|ttl| format!("TTL {ttl}"))
+        .unwrap_or_else(|| "default TTL".to_string());
+    let prio = record.prio.map(|prio| format!(", prio {prio}")).unwrap_or_default();
+    format!("{:<5} {} {:?} ({ttl}{prio}, ID {})", record.typ, record.name, record.content, record.id)
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+use eyre::{WrapErr, eyre};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::name_to_target;
+use crate::api::{DNSRecord, RecordData, RecordType};
+use crate::config::{DEFAULT_TTL, MIN_TTL, Target};
+use crate::{App, server};
+
+/// The media type that external-dns expects every response to use.
+const MEDIA_TYPE: &str = "application/external.dns.webhook+json;version=1";
+
+/// The record types that are handed over to external-dns. Anything else is left alone.
+const SUPPORTED_TYPES: &[&str] = &["A", "AAAA", "CNAME", "TXT", "MX", "SRV", "NS"];
+
+#[derive(Debug, clap::Args)]
+pub struct ExternalDnsArgs {
+    /// The address to serve the webhook API on.
+    #[arg(long, env = "PORKBUN_LISTEN", value_name = "ADDR", default_value = "127.0.0.1:8888")]
+    pub listen: SocketAddr,
+
+    /// A domain that external-dns may manage records on. Can be given more than once. Defaults to every active domain
+    /// on the account.
+    #[arg(long = "domain", value_name = "DOMAIN")]
+    pub domains: Vec<String>,
+}
+
+/// A set of records with the same name and type, as external-dns sees them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Endpoint {
+    dns_name: String,
+    #[serde(default, deserialize_with = "nullable")]
+    targets: Vec<String>,
+    record_type: String,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "String::is_empty")]
+    set_identifier: String,
+    #[serde(rename = "recordTTL", default, skip_serializing_if = "is_zero")]
+    record_ttl: u64,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "Vec::is_empty")]
+    provider_specific: Vec<serde_json::Value>,
+}
+
+/// The changes that external-dns wants made. Older versions of external-dns capitalize these field names.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Changes {
+    #[serde(default, alias = "Create", deserialize_with = "nullable")]
+    create: Vec<Endpoint>,
+    #[serde(default, alias = "UpdateOld", deserialize_with = "nullable")]
+    update_old: Vec<Endpoint>,
+    #[serde(default, alias = "UpdateNew", deserialize_with = "nullable")]
+    update_new: Vec<Endpoint>,
+    #[serde(default, alias = "Delete", deserialize_with = "nullable")]
+    delete: Vec<Endpoint>,
+}
+
+/// The domains external-dns is allowed to manage.
+#[derive(Debug, Serialize)]
+struct DomainFilter<'a> {
+    include: &'a [String],
+}
+
+/// Serves the [external-dns webhook provider API][api], so that a Kubernetes cluster's records can be managed through
+/// Porkbun.
+///
+/// Records are listed and changed with the same client (and notes, backups, and dry-run settings) as everything else.
+/// Updates are made by deleting the old records for a name and type and then creating the new ones.
+///
+/// [api]: https://kubernetes-sigs.github.io/external-dns/latest/docs/tutorials/webhook-provider/
+pub async fn external_dns(app: &App, args: ExternalDnsArgs) -> eyre::Result<()> {
+    let domains = match &args.domains[..] {
+        [] => (app.client.list_domains().await.wrap_err("Failed to list domains on account")?)
+            .into_iter()
+            .filter(|info| info.status.as_deref().is_none_or(|status| status == "ACTIVE"))
+            .map(|info| info.domain.to_ascii_lowercase())
+            .collect::<Vec<_>>(),
+        domains => (domains.iter())
+            .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase())
+            .collect(),
+    };
+
+    if domains.is_empty() {
+        return Err(eyre!("There are no domains for external-dns to manage"));
+    }
+
+    log::info!("Managing records for external-dns on {}.", domains.join(", "));
+    let listener = server::bind(args.listen).await?;
+    server::serve(listener, |req| handle(app, &domains, req)).await;
+    Ok(())
+}
+
+/// Works out the response to a single request from external-dns.
+async fn handle(app: &App, domains: &[String], req: Request<Incoming>) -> Response<Full<Bytes>> {
+    let res = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/") => Ok(server::json(StatusCode::OK, MEDIA_TYPE, &DomainFilter { include: domains })),
+        (&Method::GET, "/records") => records(app, domains).await,
+        (&Method::POST, "/records") => apply_changes(app, domains, req).await,
+        (&Method::POST, "/adjustendpoints") => adjust_endpoints(req).await,
+        (_, "/" | "/records" | "/adjustendpoints") => Ok(server::method_not_allowed()),
+        _ => Ok(server::not_found()),
+    };
+
+    res.unwrap_or_else(|err| {
+        log::error!("external-dns: {err:#}");
+        server::error(&err)
+    })
+}
+
+/// Lists every record that external-dns can manage, grouped into endpoints.
+async fn records(app: &App, domains: &[String]) -> eyre::Result<Response<Full<Bytes>>> {
+    let fetches = domains.iter().map(|domain| async move {
+        (app.client.get_existing_records(domain).await)
+            .wrap_err_with(|| format!("Failed to fetch DNS records for {domain}"))
+            .map(|records| (domain, records))
+    });
+
+    let mut endpoints = BTreeMap::<(String, String), Endpoint>::new();
+    for (domain, records) in futures::future::try_join_all(fetches).await? {
+        for rec in records {
+            // The root's NS records belong to the registrar, not to the cluster.
+            let is_root_ns = rec.typ == RecordType::NS && rec.name.eq_ignore_ascii_case(domain);
+            if !SUPPORTED_TYPES.contains(&rec.typ.as_str()) || is_root_ns {
+                continue;
+            }
+
+            let key = (rec.name.to_ascii_lowercase(), rec.typ.to_string());
+            let endpoint = endpoints.entry(key).or_insert_with(|| Endpoint {
+                dns_name: rec.name.to_ascii_lowercase(),
+                targets: Vec::new(),
+                record_type: rec.typ.to_string(),
+                set_identifier: String::new(),
+                record_ttl: rec.ttl.unwrap_or(DEFAULT_TTL).into(),
+                labels: BTreeMap::new(),
+                provider_specific: Vec::new(),
+            });
+            endpoint.targets.push(record_target(&rec));
+        }
+    }
+
+    let endpoints = endpoints.into_values().collect::<Vec<_>>();
+    Ok(server::json(StatusCode::OK, MEDIA_TYPE, &endpoints))
+}
+
+/// Makes the changes that external-dns asks for. Deletions are made before creations, so that e.g. a CNAME can be
+/// replaced by an A record.
+async fn apply_changes(app: &App, domains: &[String], req: Request<Incoming>) -> eyre::Result<Response<Full<Bytes>>> {
+    let body = server::read_body(req).await?;
+    let changes = serde_json::from_slice::<Changes>(&body).wrap_err("Invalid changes from external-dns")?;
+
+    let deletes = changes.delete.iter().chain(&changes.update_old);
+    let creates = changes.create.iter().chain(&changes.update_new);
+
+    let mut err_count = 0usize;
+    for endpoint in deletes {
+        if let Err(err) = delete(app, domains, endpoint).await {
+            log::error!("{}: {err:#}", endpoint.dns_name);
+            err_count += 1;
+        }
+    }
+    for endpoint in creates {
+        if let Err(err) = create(app, domains, endpoint).await {
+            log::error!("{}: {err:#}", endpoint.dns_name);
+            err_count += 1;
+        }
+    }
+
+    match err_count {
+        0 => Ok(server::respond(StatusCode::NO_CONTENT, MEDIA_TYPE, Bytes::new())),
+        n => Err(eyre!("Failed to apply {n} {changes}", changes = pluralize!("change", "changes", n))),
+    }
+}
+
+/// Deletes every record with an endpoint's name and type.
+async fn delete(app: &App, domains: &[String], endpoint: &Endpoint) -> eyre::Result<()> {
+    let target = to_target(domains, endpoint)?;
+    let typ = &endpoint.record_type;
+    if app.dry_run {
+        log::info!("{target}: Would delete {typ} records (dry run).");
+        return Ok(());
+    }
+
+    app.backup(target.domain()).await?;
+    (app.client.delete_records_by_name_type(target.domain(), target.subdomain(), typ))
+        .await
+        .wrap_err_with(|| format!("Failed to delete {typ} records"))?;
+    log::info!("{target}: Deleted {typ} records for external-dns.");
+    Ok(())
+}
+
+/// Creates one record for each of an endpoint's targets.
+async fn create(app: &App, domains: &[String], endpoint: &Endpoint) -> eyre::Result<()> {
+    let target = to_target(domains, endpoint)?;
+    for content in &endpoint.targets {
+        let data = record_data(&endpoint.record_type, content)?;
+        if app.dry_run {
+            log::info!("{target}: Would create {} record with content {content:?} (dry run).", data.typ);
+            continue;
+        }
+
+        app.client
+            .create_record(&target, &data)
+            .await
+            .wrap_err_with(|| format!("Failed to create {} record with content {content:?}", data.typ))?;
+        log::info!("{target}: Created {} record with content {content:?} for external-dns.", data.typ);
+    }
+    Ok(())
+}
+
+/// Lets external-dns know about Porkbun's minimum TTL, so that it doesn't keep trying to change records' TTLs to
+/// values that can't be set.
+async fn adjust_endpoints(req: Request<Incoming>) -> eyre::Result<Response<Full<Bytes>>> {
+    let body = server::read_body(req).await?;
+    let mut endpoints =
+        serde_json::from_slice::<Vec<Endpoint>>(&body).wrap_err("Invalid endpoints from external-dns")?;
+    for endpoint in &mut endpoints {
+        endpoint.record_ttl = endpoint.record_ttl.max(MIN_TTL.into());
+    }
+    Ok(server::json(StatusCode::OK, MEDIA_TYPE, &endpoints))
+}
+
+/// Works out which managed domain an endpoint belongs to, and turns it into a target on that domain.
+fn to_target(domains: &[String], endpoint: &Endpoint) -> eyre::Result<Target> {
+    let name = endpoint.dns_name.trim_end_matches('.').to_ascii_lowercase();
+    let ttl = endpoint.record_ttl.clamp(MIN_TTL.into(), u32::MAX.into()) as u32;
+
+    // The longest matching domain wins, in case one managed domain is a subdomain of another.
+    let domain = (domains.iter())
+        .filter(|domain| name == **domain || name.ends_with(&format!(".{domain}")))
+        .max_by_key(|domain| domain.len())
+        .ok_or_else(|| eyre!("{name} is not part of any managed domain"))?;
+
+    name_to_target(domain, &name, ttl)
+}
+
+/// Formats a record's content the way external-dns expects. MX and SRV records have their priority at the start.
+fn record_target(rec: &DNSRecord) -> String {
+    match (&rec.typ, rec.prio) {
+        (RecordType::MX | RecordType::SRV, Some(prio)) => format!("{prio} {}", rec.content),
+        _ => rec.content.clone(),
+    }
+}
+
+/// The reverse of [`record_target`].
+fn record_data(typ: &str, target: &str) -> eyre::Result<RecordData> {
+    let (prio, content) = match typ {
+        "MX" | "SRV" => {
+            let (prio, rest) = (target.split_once(' '))
+                .and_then(|(prio, rest)| Some((prio.parse::<u32>().ok()?, rest)))
+                .ok_or_else(|| eyre!("{typ} record {target:?} does not start with a priority"))?;
+            (Some(prio), rest.to_string())
+        },
+        _ => (None, target.to_string()),
+    };
+
+    Ok(RecordData {
+        typ: RecordType::from(typ),
+        content,
+        prio,
+    })
+}
+
+/// Deserializes a value that Go may have encoded as `null` instead of leaving it empty.
+fn nullable<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+fn is_zero(num: &u64) -> bool {
+    *num == 0
+}
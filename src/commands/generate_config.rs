@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use eyre::{WrapErr, eyre};
+use tokio::fs;
+
+/// The example config, with every setting commented out at its default.
+const EXAMPLE: &str = include_str!("../../ddns.example.toml");
+
+#[derive(Debug, clap::Args)]
+pub struct GenerateConfigArgs {
+    /// Where to write the example config. It's printed to stdout if no path is given.
+    #[arg(value_name = "FILE")]
+    pub path: Option<PathBuf>,
+
+    /// Overwrite the file if it already exists.
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Writes out a fully commented example config file, which works as-is once a target is added.
+pub async fn generate_config(args: GenerateConfigArgs) -> eyre::Result<()> {
+    let Some(path) = args.path else {
+        print!("{EXAMPLE}");
+        return Ok(());
+    };
+
+    if !args.force && fs::try_exists(&path).await.unwrap_or(false) {
+        return Err(eyre!("{} already exists (use --force to overwrite it)", path.display()));
+    }
+
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        fs::create_dir_all(dir).await.wrap_err("Failed to create config directory")?;
+    }
+
+    fs::write(&path, EXAMPLE).await.wrap_err("Failed to write config file")?;
+    log::info!("Wrote example config to {}.", path.display());
+    Ok(())
+}
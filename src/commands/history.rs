@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anstyle::{AnsiColor, Style};
+use chrono::{DateTime, Local};
+use eyre::eyre;
+use serde::Serialize;
+
+use crate::api::IpAddrExt;
+use crate::config::{Args, Config, OutputFormat, format_duration};
+use crate::state::{AddressChange, State};
+
+/// A single address change, as printed with `--output json`.
+#[derive(Debug, Serialize)]
+struct JsonChange {
+    /// When the address was pushed, in RFC 3339 format.
+    timestamp: String,
+    addr: IpAddr,
+}
+
+/// Prints every address that has been pushed to each target, and when, as remembered in the state file.
+///
+/// Only the state file is read, so this makes no network requests and doesn't need API keys.
+pub async fn history(args: Args) -> eyre::Result<()> {
+    const NAME: Style = Style::new().bold();
+    const DIM: Style = AnsiColor::BrightBlack.on_default();
+
+    let output_format = args.output;
+    let config = Config::from_args(args).await?;
+    let Some(path) = &config.state_file else {
+        return Err(eyre!("No state_file is configured, so no history has been kept"));
+    };
+
+    let state = State::load(path).await;
+
+    if output_format == OutputFormat::Json {
+        let history = (state.history())
+            .map(|(target, changes)| {
+                let changes = changes.iter().map(|change| JsonChange {
+                    timestamp: timestamp(change).to_rfc3339(),
+                    addr: change.addr,
+                });
+                (target, changes.collect::<Vec<_>>())
+            })
+            .collect::<BTreeMap<_, _>>();
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        return Ok(());
+    }
+
+    let mut output = anstream::stdout().lock();
+    let mut empty = true;
+    for (target, changes) in state.history() {
+        empty = false;
+        writeln!(output, "{NAME}{target}{NAME:#}")?;
+
+        for ipv6 in [false, true] {
+            let changes = changes
+                .iter()
+                .filter(|change| change.addr.is_ipv6() == ipv6)
+                .collect::<Vec<_>>();
+            let Some((first, last)) = changes.first().zip(changes.last()) else {
+                continue;
+            };
+
+            for (i, change) in changes.iter().enumerate() {
+                // How long each address lasted, up until the next one replaced it.
+                let lasted = match changes.get(i + 1) {
+                    Some(next) => format!("for {}", format_duration(elapsed(change, next))),
+                    None => "current".to_string(),
+                };
+
+                writeln!(
+                    output,
+                    "  {}  {:<5} {} {DIM}({lasted}){DIM:#}",
+                    timestamp(change).format("%Y-%m-%d %H:%M:%S"),
+                    change.addr.dns_type(),
+                    change.addr,
+                )?;
+            }
+
+            if changes.len() > 1 {
+                let n = changes.len() - 1;
+                let average = elapsed(first, last) / n as u32;
+                writeln!(
+                    output,
+                    "  {DIM}{n} {changed}, on average every {}{DIM:#}",
+                    format_duration(average),
+                    changed = pluralize!("change", "changes", n),
+                )?;
+            }
+        }
+    }
+
+    if empty {
+        writeln!(output, "No addresses have been pushed to any targets yet.")?;
+    }
+
+    output.flush()?;
+    Ok(())
+}
+
+/// Converts a change's timestamp into local time.
+fn timestamp(change: &AddressChange) -> DateTime<Local> {
+    DateTime::from_timestamp(change.timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&Local)
+}
+
+/// Works out how long it was between two changes.
+fn elapsed(from: &AddressChange, to: &AddressChange) -> Duration {
+    Duration::from_secs(to.timestamp.saturating_sub(from.timestamp).max(0) as u64)
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use eyre::{WrapErr, eyre};
+use tokio::fs;
+
+use super::{confirm, name_to_target};
+use crate::api::RecordType;
+use crate::config::{DEFAULT_TTL, Target};
+use crate::{App, zonefile};
+
+#[derive(Debug, clap::Args)]
+pub struct ImportArgs {
+    /// A BIND-style zone file to create records from.
+    #[arg(long, value_name = "FILE", required_unless_present = "targets")]
+    pub zone_file: Option<PathBuf>,
+
+    /// Instead of creating records, print a `targets` list for the config file, covering every existing A/AAAA record
+    /// that points at this machine's current address.
+    #[arg(long, conflicts_with_all = ["zone_file", "confirm"])]
+    pub targets: bool,
+
+    /// The domain to import records into.
+    ///
+    /// Defaults to the zone file's first `$ORIGIN`. When given, it's also used as the starting `$ORIGIN`. With
+    /// `--targets`, this limits the search to a single domain, instead of every domain on the account.
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Create the records without asking for confirmation first.
+    #[arg(long)]
+    pub confirm: bool,
+}
+
+/// Creates records on Porkbun from a zone file.
+///
+/// Importing is additive: records that already exist on Porkbun with the same name, type, and content are skipped, and
+/// nothing is ever edited or deleted. The list of records to create is always printed first, and must be confirmed
+/// before anything is created.
+pub async fn import(app: &App, args: ImportArgs) -> eyre::Result<()> {
+    let Some(zone_file) = &args.zone_file else {
+        return import_targets(app, args.domain.as_deref()).await;
+    };
+
+    let text = fs::read_to_string(zone_file).await.wrap_err("Failed to read zone file")?;
+    let records = zonefile::parse(&text, args.domain.as_deref())?;
+
+    let domain = match &args.domain {
+        Some(domain) => domain.trim_end_matches('.').to_ascii_lowercase(),
+        None => find_origin(&text).ok_or_else(|| eyre!("Zone file has no $ORIGIN; use --domain to specify one"))?,
+    };
+
+    let mut to_import = Vec::new();
+    for record in records {
+        // SOA and root NS records are managed by Porkbun and the registry, not by regular DNS records.
+        if record.data.typ == "SOA" || (record.data.typ == RecordType::NS && record.name == domain) {
+            log::debug!("Skipping {} record for {}.", record.data.typ, record.name);
+            continue;
+        }
+
+        let ttl = record.ttl.unwrap_or(DEFAULT_TTL);
+        let target = name_to_target(&domain, &record.name, ttl).wrap_err("Invalid zone file")?;
+        to_import.push((target, record));
+    }
+
+    let existing = app
+        .client
+        .get_existing_records(&domain)
+        .await
+        .wrap_err_with(|| format!("Failed to fetch DNS records for {domain}"))?;
+
+    // Show what's about to happen before doing anything:
+    let mut to_create = Vec::new();
+    for (target, record) in to_import {
+        let data = &record.data;
+        let exists = existing.iter().any(|rec| {
+            rec.typ == data.typ && target.matches_record(rec) && rec.content.eq_ignore_ascii_case(&data.content)
+        });
+
+        let prio = data.prio.map(|prio| format!(", prio {prio}")).unwrap_or_default();
+        let line = format!("{:<5} {} {:?} (TTL {}{prio})", data.typ, record.name, data.content, target.ttl());
+        if exists {
+            println!("  {line}");
+        } else {
+            println!("+ {line}");
+            to_create.push((target, record));
+        }
+    }
+
+    if to_create.is_empty() {
+        log::info!("All records in the zone file already exist on {domain}. Nothing to do.");
+        return Ok(());
+    }
+
+    let n = to_create.len();
+    let records = pluralize!("record", "records", n);
+
+    if app.dry_run {
+        log::info!("dry_run is enabled: {n} {records} would have been created.");
+        return Ok(());
+    }
+
+    if !args.confirm && !confirm(&format!("Create {n} {records} on {domain}?"))? {
+        return Err(eyre!("Import into {domain} was not confirmed"));
+    }
+
+    let mut err_count = 0usize;
+    for (target, record) in to_create {
+        let typ = &record.data.typ;
+        match app.client.create_record(&target, &record.data).await {
+            Ok(id) => {
+                log::info!("{target}: Created new {typ} record with content {:?}.", record.data.content);
+                log::trace!("{target}: New record has ID {id}");
+            },
+            Err(err) => {
+                log::error!("{target}: Failed to create {typ} record: {err:#}");
+                err_count += 1;
+            },
+        }
+    }
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!("Failed to create {n} {records}", records = pluralize!("record", "records", n))),
+    }
+}
+
+/// Prints a `targets` list for the config file, with a target for every A/AAAA record that already points at this
+/// machine. This makes it easy to take over records from some other dynamic DNS setup.
+///
+/// Every active domain on the account is searched, unless a single domain is given.
+async fn import_targets(app: &App, domain: Option<&str>) -> eyre::Result<()> {
+    let (ipv4, ipv6) = app.get_addresses().await.wrap_err("Failed to determine current IP addresses")?;
+    let addrs = [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)];
+
+    let domains = match domain {
+        Some(domain) => vec![domain.trim_end_matches('.').to_ascii_lowercase()],
+        None => (app.client.list_domains().await.wrap_err("Failed to list domains on account")?)
+            .into_iter()
+            .filter(|info| info.status.as_deref().is_none_or(|status| status == "ACTIVE"))
+            .map(|info| info.domain)
+            .collect(),
+    };
+
+    // Names are kept in order, and only listed once even if they have both an A and an AAAA record.
+    let mut targets = BTreeMap::<String, Target>::new();
+    for domain in &domains {
+        let records = match app.client.get_existing_records(domain).await {
+            Ok(records) => records,
+            Err(err) => {
+                log::warn!("Skipping {domain}: Failed to fetch DNS records: {err:#}");
+                continue;
+            },
+        };
+
+        for record in records {
+            if record.try_parse_ip().is_ok_and(|addr| addrs.contains(&Some(addr))) {
+                let target = name_to_target(domain, &record.name, record.ttl.unwrap_or(DEFAULT_TTL))?;
+                targets.entry(target.to_string()).or_insert(target);
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        log::info!("No A or AAAA records point at this machine.");
+        return Ok(());
+    }
+
+    let quote = |str: &str| toml::Value::from(str).to_string();
+    println!("targets = [");
+    for target in targets.values() {
+        match (target.subdomain(), target.ttl()) {
+            (None, DEFAULT_TTL) => println!("  {},", quote(target.domain())),
+            (None, ttl) => println!("  {{ domain = {}, ttl = {ttl} }},", quote(target.domain())),
+            (Some(sub), ttl) => {
+                let (domain, sub) = (quote(target.domain()), quote(sub));
+                println!("  {{ domain = {domain}, subdomain = {sub}, ttl = {ttl} }},");
+            },
+        }
+    }
+    println!("]");
+
+    Ok(())
+}
+
+/// Finds the first `$ORIGIN` directive in a zone file.
+fn find_origin(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some(dir) if dir.eq_ignore_ascii_case("$ORIGIN") => {
+                words.next().map(|origin| origin.trim_end_matches('.').to_ascii_lowercase())
+            },
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,60 @@
+use eyre::WrapErr;
+
+use crate::App;
+
+#[derive(Debug, clap::Args)]
+pub struct ListRecordsArgs {
+    /// The domain to list records for.
+    pub domain: String,
+}
+
+/// Prints every record on a domain as a table.
+pub async fn list_records(app: &App, args: ListRecordsArgs) -> eyre::Result<()> {
+    let domain = &args.domain[..];
+    let mut records = app
+        .client
+        .get_existing_records(domain)
+        .await
+        .wrap_err_with(|| format!("Failed to fetch DNS records for {domain}"))?;
+
+    if records.is_empty() {
+        log::info!("{domain} has no records.");
+        return Ok(());
+    }
+
+    records.sort_by(|a, b| (&a.name, a.typ.as_str(), &a.content).cmp(&(&b.name, b.typ.as_str(), &b.content)));
+
+    let header = ["ID", "TYPE", "NAME", "TTL", "PRIO", "CONTENT", "NOTES"].map(String::from);
+    let rows = records.into_iter().map(|rec| {
+        let opt = |num: Option<u32>| num.map(|n| n.to_string()).unwrap_or_default();
+        [
+            rec.id,
+            rec.typ.to_string(),
+            rec.name,
+            opt(rec.ttl),
+            opt(rec.prio),
+            rec.content,
+            rec.notes.unwrap_or_default(),
+        ]
+    });
+    let rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+
+    // Every column but the last is padded to line up; notes can be as long as they like.
+    let mut widths = [0usize; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in rows {
+        let mut line = String::new();
+        for (cell, width) in row.iter().zip(widths) {
+            line += &format!("{cell:<width$}  ");
+        }
+        line += &row[6];
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
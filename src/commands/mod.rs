@@ -0,0 +1,126 @@
+//! Subcommands for tasks other than the main update process.
+
+mod acme;
+mod apply;
+mod check;
+mod daemon;
+mod delete_record;
+mod external_dns;
+mod generate_config;
+mod history;
+mod import;
+mod list_records;
+mod ns;
+mod plan;
+mod print_config;
+mod prune;
+mod restore;
+mod service;
+mod status;
+mod test_auth;
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use eyre::{WrapErr, eyre};
+
+pub use self::acme::{AcmeArgs, acme};
+pub use self::apply::{ApplyArgs, apply};
+pub use self::check::check;
+pub use self::daemon::{DaemonArgs, daemon};
+pub use self::delete_record::{DeleteRecordArgs, delete_record};
+pub use self::external_dns::{ExternalDnsArgs, external_dns};
+pub use self::generate_config::{GenerateConfigArgs, generate_config};
+pub use self::history::history;
+pub use self::import::{ImportArgs, import};
+pub use self::list_records::{ListRecordsArgs, list_records};
+pub use self::ns::{NsArgs, ns};
+pub use self::plan::{PlanArgs, plan};
+pub use self::print_config::print_config;
+pub use self::prune::{PruneArgs, prune};
+pub use self::restore::{RestoreArgs, restore};
+pub use self::service::{ServiceArgs, service};
+pub use self::status::status;
+pub use self::test_auth::test_auth;
+use crate::App;
+use crate::config::Target;
+use crate::names::{DomainName, Subdomain};
+
+/// Asks the user a yes/no question on the terminal, returning `true` only if they answer yes.
+///
+/// Returns an error if there is no terminal to ask on, since that almost certainly means the program is being run from
+/// a script; there should be some other way to give confirmation in that case (e.g. a `--confirm` flag).
+fn confirm(prompt: &str) -> eyre::Result<bool> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return Err(eyre!("Cannot ask for confirmation: stdin is not a terminal"));
+    }
+
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{prompt} [y/N] ").and_then(|_| stderr.flush())?;
+
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer).wrap_err("Failed to read confirmation")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes" | "YES"))
+}
+
+/// Splits a fully-qualified domain name into a root domain and a subdomain.
+///
+/// There's no simple way to tell where the root domain starts in general (consider `sub.example.co.uk`), so this picks
+/// the longest domain it knows about that the name ends with: first checking domains from the config file, then the
+/// domains on the Porkbun account.
+async fn split_fqdn(app: &App, fqdn: &str) -> eyre::Result<(DomainName, Option<Subdomain>)> {
+    let fqdn = fqdn.trim_end_matches('.').to_ascii_lowercase();
+
+    let split = |domain: &str| -> Option<(String, Option<String>)> {
+        if fqdn == domain {
+            Some((domain.to_string(), None))
+        } else {
+            let sub = fqdn.strip_suffix(domain)?.strip_suffix('.')?;
+            Some((domain.to_string(), Some(sub.to_string())))
+        }
+    };
+
+    let configured = (app.targets.iter())
+        .chain(app.records.iter().map(|rec| rec.target()))
+        .map(|tgt| tgt.domain().to_ascii_lowercase());
+    let found = match longest_match(configured, split) {
+        Some(found) => found,
+        None => {
+            log::debug!("No configured domain matches {fqdn}; checking domains on account...");
+            let domains = app.client.list_domains().await.wrap_err("Failed to list domains on account")?;
+            let account = domains.into_iter().map(|info| info.domain.to_ascii_lowercase());
+            longest_match(account, split)
+                .ok_or_else(|| eyre!("{fqdn} does not belong to any domain on this account"))?
+        },
+    };
+
+    let (domain, sub) = found;
+    let domain = DomainName::parse(&domain).map_err(|err| eyre!(err))?;
+    let sub = Subdomain::parse(sub.as_deref().unwrap_or_default()).map_err(|err| eyre!(err))?;
+    Ok((domain, sub))
+}
+
+/// Converts a record's fully-qualified name into a [`Target`] on the given domain.
+fn name_to_target(domain: &str, name: &str, ttl: u32) -> eyre::Result<Target> {
+    let domain = DomainName::parse(domain).map_err(|err| eyre!(err))?;
+    if name.eq_ignore_ascii_case(&domain) {
+        return Ok(Target::new(domain, None, ttl));
+    }
+
+    let sub = (name.to_ascii_lowercase().strip_suffix(domain.as_str()))
+        .and_then(|rest| rest.strip_suffix('.'))
+        .map(Subdomain::parse)
+        .ok_or_else(|| eyre!("Record {name} is not part of {domain}"))?
+        .map_err(|err| eyre!(err))?;
+    Ok(Target::new(domain, sub, ttl))
+}
+
+/// Helper for [`split_fqdn`]: finds the longest domain that gives a successful split.
+fn longest_match<F>(domains: impl Iterator<Item = String>, split: F) -> Option<(String, Option<String>)>
+where
+    F: Fn(&str) -> Option<(String, Option<String>)>,
+{
+    domains
+        .filter_map(|domain| split(&domain))
+        .max_by_key(|(domain, _)| domain.len())
+}
@@ -0,0 +1,65 @@
+use eyre::{WrapErr, eyre};
+
+use super::confirm;
+use crate::App;
+
+#[derive(Debug, clap::Args)]
+pub struct NsArgs {
+    /// The domain to get or set nameservers for.
+    pub domain: String,
+
+    /// New nameservers for the domain. If omitted, the current nameservers are printed instead.
+    #[arg(value_name = "NAMESERVER")]
+    pub nameservers: Vec<String>,
+
+    /// Change the nameservers without asking for confirmation first.
+    #[arg(long)]
+    pub confirm: bool,
+}
+
+/// Prints or updates the authoritative nameservers for a domain.
+///
+/// Pointing a domain at the wrong nameservers will take every one of its records offline, so changes must be confirmed
+/// interactively (or with `--confirm`) first.
+pub async fn ns(app: &App, args: NsArgs) -> eyre::Result<()> {
+    let domain = &args.domain[..];
+    let current = app
+        .client
+        .get_nameservers(domain)
+        .await
+        .wrap_err_with(|| format!("Failed to get nameservers for {domain}"))?;
+
+    if args.nameservers.is_empty() {
+        for ns in current {
+            println!("{ns}");
+        }
+        return Ok(());
+    }
+
+    if current == args.nameservers {
+        log::info!("{domain}: Nameservers are already set to {}. Nothing to do.", current.join(", "));
+        return Ok(());
+    }
+
+    let prompt = format!(
+        "Change nameservers for {domain} from [{}] to [{}]?",
+        current.join(", "),
+        args.nameservers.join(", "),
+    );
+
+    if !args.confirm && !confirm(&prompt)? {
+        return Err(eyre!("Nameserver change for {domain} was not confirmed"));
+    }
+
+    if app.dry_run {
+        log::warn!("dry_run is enabled: nameservers will not actually be changed.");
+    } else {
+        app.client
+            .update_nameservers(domain, &args.nameservers)
+            .await
+            .wrap_err_with(|| format!("Failed to update nameservers for {domain}"))?;
+    }
+
+    log::info!("{domain}: Changed nameservers to {}.", args.nameservers.join(", "));
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use eyre::{WrapErr, eyre};
+use tokio::fs;
+
+use crate::App;
+use crate::plan::Plan;
+
+#[derive(Debug, clap::Args)]
+pub struct PlanArgs {
+    /// Where to write the plan. Defaults to printing it to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// Works out every change that a regular update would make to Porkbun records, without making any of them, and saves
+/// them as a plan to be run later with `apply`.
+///
+/// The update is run exactly as it would be in a dry run, so other providers are only checked, not included in the
+/// plan.
+pub async fn plan(app: &App, args: PlanArgs) -> eyre::Result<()> {
+    let (ipv4, ipv6) = app.get_addresses().await.wrap_err("Failed to determine current IP addresses")?;
+
+    // A plan that's missing some of its changes would be misleading, so errors mean no plan at all.
+    match app.run(ipv4, ipv6).await {
+        0 => {},
+        n => {
+            let errors = pluralize!("error", "errors", n);
+            return Err(eyre!("Encountered {n} {errors} while planning. See output for details."));
+        },
+    }
+
+    let plan = Plan {
+        timestamp: Utc::now().to_rfc3339(),
+        changes: std::mem::take(&mut *app.changes.lock().unwrap()),
+    };
+
+    let json = serde_json::to_string_pretty(&plan)?;
+    match &args.output {
+        Some(path) => fs::write(path, json).await.wrap_err("Failed to write plan file")?,
+        None => println!("{json}"),
+    }
+
+    let n = plan.changes.len();
+    log::info!("Planned {n} {changes}.", changes = pluralize!("change", "changes", n));
+    Ok(())
+}
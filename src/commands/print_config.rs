@@ -0,0 +1,16 @@
+use eyre::WrapErr;
+
+use crate::config::{Args, Config, ConfigFormat};
+
+/// Prints the fully resolved configuration, in the same format as the config file.
+///
+/// This is the config after defaults have been filled in and command line options have been applied, which makes it
+/// easier to see why a setting isn't taking effect. Secrets are redacted, so the output is safe to share.
+pub async fn print_config(args: Args) -> eyre::Result<()> {
+    // When several files are merged, the first one is assumed to be the base that the rest are layered on.
+    let format = args.config_format.unwrap_or_else(|| ConfigFormat::from_path(&args.config[0]));
+    let config = Config::from_args(args).await?;
+    let text = format.render(&config).wrap_err("Failed to print config")?;
+    print!("{text}");
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+use eyre::{WrapErr, eyre};
+
+use crate::App;
+use crate::api::RecordType;
+use crate::names::DomainName;
+
+#[derive(Debug, clap::Args)]
+pub struct PruneArgs {
+    /// Additional domains to check for stale records, besides those that appear in the config file.
+    ///
+    /// Useful after removing every target for a domain from the config.
+    #[arg(value_name = "DOMAIN")]
+    pub domains: Vec<DomainName>,
+}
+
+/// Deletes A/AAAA records that were created by this program, but which no longer match any configured target.
+///
+/// Records are recognized as having been created by this program by the marker left in their notes. Records without
+/// that marker are never touched.
+pub async fn prune(app: &App, args: PruneArgs) -> eyre::Result<()> {
+    if app.dry_run {
+        log::warn!("dry_run is enabled: no delete requests will be sent through to Porkbun.");
+    }
+
+    let mut domains = app.targets.iter().map(|tgt| tgt.domain()).collect::<BTreeSet<_>>();
+    domains.extend(args.domains.iter());
+
+    let mut err_count = 0usize;
+    let mut del_count = 0usize;
+
+    for domain in domains {
+        let records = match app.client.get_existing_records(domain).await {
+            Ok(records) => records,
+            Err(err) => {
+                log::error!("Failed to fetch DNS records for {domain}: {err:#}");
+                err_count += 1;
+                continue;
+            },
+        };
+
+        let stale = records
+            .iter()
+            .filter(|rec| {
+                matches!(rec.typ, RecordType::A | RecordType::AAAA)
+                    && rec.is_managed()
+                    && !app.targets.iter().any(|tgt| tgt.matches_record(rec))
+            })
+            .collect::<Vec<_>>();
+
+        if !stale.is_empty()
+            && let Err(err) = app.backup(domain).await
+        {
+            log::error!("Not pruning {domain}: {err:#}");
+            err_count += 1;
+            continue;
+        }
+
+        for record in stale {
+            let (name, typ, content) = (&record.name, &record.typ, &record.content);
+
+            if !app.dry_run {
+                let res = app.client.delete_record(domain, record).await;
+                if let Err(err) = res.wrap_err_with(|| format!("Failed to delete {typ} record for {name}")) {
+                    log::error!("{err:#}");
+                    err_count += 1;
+                    continue;
+                }
+            }
+
+            log::info!("{name}: Deleted stale {typ} record with content {content}.");
+            log::trace!("{name}: Deleted record had ID {}", record.id);
+            del_count += 1;
+        }
+    }
+
+    log::info!("Deleted {del_count} stale {records}.", records = pluralize!("record", "records", del_count));
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!(
+            "Encountered {n} {errors}. See output for details.",
+            errors = pluralize!("error", "errors", n)
+        )),
+    }
+}
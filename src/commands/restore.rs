@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use eyre::{WrapErr, eyre};
+use tokio::fs;
+
+use super::{confirm, name_to_target};
+use crate::App;
+use crate::api::{DNSRecord, RecordData};
+use crate::backup::Snapshot;
+use crate::config::DEFAULT_TTL;
+
+#[derive(Debug, clap::Args)]
+pub struct RestoreArgs {
+    /// A backup file, as saved to the configured `backup_dir`.
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Restore every record without asking for confirmation of each one.
+    #[arg(long)]
+    pub confirm: bool,
+}
+
+/// A change needed to bring a record back in line with a backup.
+enum Change<'a> {
+    /// The record no longer exists, and needs to be created again.
+    Create(&'a DNSRecord),
+    /// The record still exists, but has been changed since. Holds the current record, then the saved one.
+    Edit(&'a DNSRecord, &'a DNSRecord),
+}
+
+/// Re-creates and edits records so that they match a backup taken before an earlier run.
+///
+/// Records that were created after the backup was taken are left alone. Every change is listed before anything is
+/// done, and each one must be confirmed individually (unless `--confirm` is given).
+pub async fn restore(app: &App, args: RestoreArgs) -> eyre::Result<()> {
+    let text = fs::read_to_string(&args.file).await.wrap_err("Failed to read backup file")?;
+    let snapshot = serde_json::from_str::<Snapshot>(&text).wrap_err("Failed to parse backup file")?;
+    let domain = &snapshot.domain[..];
+
+    log::info!("Restoring {domain} from backup taken at {}.", snapshot.timestamp);
+
+    let current = app
+        .client
+        .get_existing_records(domain)
+        .await
+        .wrap_err_with(|| format!("Failed to fetch DNS records for {domain}"))?;
+
+    // Records are matched up by ID first, since that survives edits. A record that was deleted and re-created with the
+    // same content has a new ID, though, so that counts as well.
+    let mut changes = Vec::new();
+    for saved in &snapshot.records {
+        let same_name = |rec: &&DNSRecord| rec.name.eq_ignore_ascii_case(&saved.name) && rec.typ == saved.typ;
+        let existing = (current.iter().find(|rec| rec.id == saved.id))
+            .or_else(|| current.iter().filter(same_name).find(|rec| rec.content == saved.content));
+
+        match existing {
+            Some(rec) if matches_saved(rec, saved) => {
+                println!("  {}", describe(saved));
+            },
+            Some(rec) => {
+                println!("~ {}", describe(rec));
+                println!("  → {}", describe(saved));
+                if notes(rec) != notes(saved) {
+                    println!("  notes {:?} → {:?}", notes(rec), notes(saved));
+                }
+                changes.push(Change::Edit(rec, saved));
+            },
+            None => {
+                println!("+ {}", describe(saved));
+                changes.push(Change::Create(saved));
+            },
+        }
+    }
+
+    if changes.is_empty() {
+        log::info!("All records in the backup already match {domain}. Nothing to do.");
+        return Ok(());
+    }
+
+    if app.dry_run {
+        let n = changes.len();
+        log::info!(
+            "dry_run is enabled: {n} {records} would have been restored.",
+            records = pluralize!("record", "records", n)
+        );
+        return Ok(());
+    }
+
+    app.backup(domain).await?;
+
+    let mut err_count = 0usize;
+    let mut skip_count = 0usize;
+    for change in changes {
+        let (verb, saved) = match change {
+            Change::Create(saved) => ("Re-create", saved),
+            Change::Edit(_, saved) => ("Restore", saved),
+        };
+
+        if !args.confirm && !confirm(&format!("{verb} {}?", describe(saved)))? {
+            skip_count += 1;
+            continue;
+        }
+
+        let res = async {
+            let target = name_to_target(domain, &saved.name, saved.ttl.unwrap_or(DEFAULT_TTL))?;
+            let data = RecordData {
+                typ: saved.typ.clone(),
+                content: saved.content.clone(),
+                prio: saved.prio,
+            };
+
+            // The saved notes are put back as well (even if they were empty), instead of this program's own marker.
+            match change {
+                Change::Create(_) => {
+                    let id = app.client.create_record_with_notes(&target, &data, notes(saved)).await?;
+                    log::info!("{target}: Re-created {} record with content {:?}.", data.typ, data.content);
+                    log::trace!("{target}: New record has ID {id}");
+                },
+                Change::Edit(current, _) => {
+                    app.client.edit_record_with_notes(&target, current, &data, notes(saved)).await?;
+                    log::info!("{target}: Restored {} record to content {:?}.", data.typ, data.content);
+                },
+            }
+
+            eyre::Ok(())
+        };
+
+        if let Err(err) = res.await {
+            log::error!("{}: Failed to restore {} record: {err:#}", saved.name, saved.typ);
+            err_count += 1;
+        }
+    }
+
+    if skip_count > 0 {
+        log::warn!("Skipped {skip_count} {records}.", records = pluralize!("record", "records", skip_count));
+    }
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!("Failed to restore {n} {records}", records = pluralize!("record", "records", n))),
+    }
+}
+
+/// Checks whether a current record has the same name, type, content, TTL, priority, and notes as a saved one.
+fn matches_saved(current: &DNSRecord, saved: &DNSRecord) -> bool {
+    current.name.eq_ignore_ascii_case(&saved.name)
+        && current.typ == saved.typ
+        && current.content == saved.content
+        && current.ttl == saved.ttl
+        && current.prio == saved.prio
+        && notes(current) == notes(saved)
+}
+
+/// Gets a record's notes, treating missing notes the same as empty ones.
+fn notes(record: &DNSRecord) -> &str {
+    record.notes.as_deref().unwrap_or("")
+}
+
+/// Formats a record as a single line for the list of changes.
+fn describe(record: &DNSRecord) -> String {
+    let ttl = record
+        .ttl
+        .map(|ttl| format!("TTL {ttl}"))
+        .unwrap_or_else(|| "default TTL".to_string());
+    let prio = record.prio.map(|prio| format!(", prio {prio}")).unwrap_or_default();
+    format!("{:<5} {} {:?} ({ttl}{prio})", record.typ, record.name, record.content)
+}
@@ -0,0 +1,288 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use tokio::fs;
+use tokio::process::Command;
+
+use super::daemon::parse_interval;
+use crate::config::format_duration;
+
+/// The label that launchd knows the service by, unless another one is given.
+const DEFAULT_LABEL: &str = "com.github.matthew-e-brown.porkbun-ddns";
+
+#[derive(Debug, clap::Args)]
+pub struct ServiceArgs {
+    #[command(subcommand)]
+    pub action: ServiceAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ServiceAction {
+    /// Write a service definition that runs an update every so often, and start it.
+    Install(InstallArgs),
+
+    /// Stop the service and remove its definition.
+    Uninstall(UninstallArgs),
+}
+
+/// Which service manager to use, and which of its services to manage.
+#[derive(Debug, clap::Args)]
+pub struct ServiceOptions {
+    /// Install a launchd job (on macOS). This is currently the only kind of service that can be installed; for systemd,
+    /// see the example unit files in the repository instead.
+    #[arg(long, required = true)]
+    pub launchd: bool,
+
+    /// Install a system-wide LaunchDaemon (in /Library/LaunchDaemons, which needs root) instead of a LaunchAgent for
+    /// the current user.
+    #[arg(long)]
+    pub system: bool,
+
+    /// The label to give the job.
+    #[arg(long, default_value = DEFAULT_LABEL)]
+    pub label: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct InstallArgs {
+    #[command(flatten)]
+    pub options: ServiceOptions,
+
+    /// How long to wait between updates (e.g., "5m" or "1h").
+    #[arg(long, value_name = "DURATION", default_value = "15m", value_parser = parse_interval)]
+    pub interval: Duration,
+
+    /// Only write the job's definition, without loading it into launchd.
+    #[arg(long)]
+    pub no_load: bool,
+
+    /// Replace the job if it's already installed.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct UninstallArgs {
+    #[command(flatten)]
+    pub options: ServiceOptions,
+}
+
+/// Installs or uninstalls a service that runs updates in the background.
+pub async fn service(args: ServiceArgs, config: &[PathBuf]) -> eyre::Result<()> {
+    match args.action {
+        ServiceAction::Install(args) => install(args, config).await,
+        ServiceAction::Uninstall(args) => uninstall(args).await,
+    }
+}
+
+/// Writes a launchd property list that runs a single update every `interval` (and once as soon as it's loaded), then
+/// loads it.
+///
+/// The job is given the config files' full paths, and any `PORKBUN_*` variables set in the current environment (e.g.,
+/// API keys), so that it runs the same way it would from this shell.
+async fn install(args: InstallArgs, config: &[PathBuf]) -> eyre::Result<()> {
+    let opts = &args.options;
+    let path = plist_path(opts)?;
+
+    if !args.force && fs::try_exists(&path).await.unwrap_or(false) {
+        return Err(eyre!("{} already exists (use --force to replace it)", path.display()));
+    }
+
+    if !args.no_load && !cfg!(target_os = "macos") {
+        return Err(eyre!("launchd is only available on macOS (use --no-load to only write the job's definition)"));
+    }
+
+    let exe = std::env::current_exe().wrap_err("Failed to find the path to this program")?;
+    let config = (config.iter())
+        .map(|path| std::path::absolute(path).wrap_err("Failed to find the full path to the config file"))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    for path in config.iter().filter(|path| !path.is_file()) {
+        log::warn!("{} does not exist yet; the job will fail until it does.", path.display());
+    }
+
+    // The config is passed by argument instead, and an address given for this run shouldn't be stuck there forever.
+    let skip = ["PORKBUN_CONFIG_PATH", "PORKBUN_IPV4_ADDRESS", "PORKBUN_IPV6_ADDRESS"];
+    let env = std::env::vars()
+        .filter(|(key, _)| key.starts_with("PORKBUN_") && !skip.contains(&key.as_str()))
+        .collect::<Vec<_>>();
+
+    let logs = match opts.system {
+        true => PathBuf::from("/Library/Logs/porkbun-ddns.log"),
+        false => home_dir()?.join("Library/Logs/porkbun-ddns.log"),
+    };
+
+    let plist = make_plist(&opts.label, &exe, &config, args.interval, &env, &logs);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await.wrap_err("Failed to create launchd directory")?;
+    }
+
+    // Unloading first (if it's there at all) is the only way to get launchd to pick up changes to a job.
+    if !args.no_load && args.force {
+        let _ = launchctl(&["bootout", &service_target(opts)]).await;
+    }
+
+    write_private(&path, &plist).await.wrap_err("Failed to write launchd job")?;
+    log::info!("Wrote launchd job to {}.", path.display());
+
+    if args.no_load {
+        return Ok(());
+    }
+
+    launchctl(&["bootstrap", &domain_target(opts), &path.to_string_lossy()]).await?;
+    log::info!(
+        "Loaded {}, which will update records every {}. Logs are written to {}.",
+        opts.label,
+        format_duration(args.interval),
+        logs.display(),
+    );
+    Ok(())
+}
+
+/// Unloads the launchd job, if it's loaded, and removes its property list.
+async fn uninstall(args: UninstallArgs) -> eyre::Result<()> {
+    let opts = &args.options;
+    let path = plist_path(opts)?;
+
+    if cfg!(target_os = "macos")
+        && let Err(err) = launchctl(&["bootout", &service_target(opts)]).await
+    {
+        log::debug!("{err:#}");
+    }
+
+    match fs::remove_file(&path).await {
+        Ok(()) => {
+            log::info!("Removed launchd job {}.", path.display());
+            Ok(())
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Err(eyre!("{} is not installed", opts.label)),
+        Err(err) => Err(eyre!(err).wrap_err(format!("Failed to remove {}", path.display()))),
+    }
+}
+
+/// Works out where the job's property list goes: with the system's LaunchDaemons, or the current user's LaunchAgents.
+fn plist_path(opts: &ServiceOptions) -> eyre::Result<PathBuf> {
+    let dir = match opts.system {
+        true => PathBuf::from("/Library/LaunchDaemons"),
+        false => home_dir()?.join("Library/LaunchAgents"),
+    };
+    Ok(dir.join(format!("{}.plist", opts.label)))
+}
+
+fn home_dir() -> eyre::Result<PathBuf> {
+    (std::env::var_os("HOME").map(PathBuf::from))
+        .filter(|path| path.is_absolute())
+        .ok_or_else(|| eyre!("Failed to find home directory (HOME is not set)"))
+}
+
+/// The launchd domain that the job is loaded into: the system's, or the current user's GUI session.
+fn domain_target(opts: &ServiceOptions) -> String {
+    match opts.system {
+        true => "system".to_string(),
+        false => format!("gui/{}", current_uid()),
+    }
+}
+
+/// The job itself, within its domain.
+fn service_target(opts: &ServiceOptions) -> String {
+    format!("{}/{}", domain_target(opts), opts.label)
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: `getuid` has no preconditions, and always succeeds.
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> u32 {
+    0
+}
+
+/// Runs `launchctl` with the given arguments.
+async fn launchctl(args: &[&str]) -> eyre::Result<()> {
+    let output = Command::new("launchctl")
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .wrap_err("Failed to run launchctl")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(eyre!("launchctl {} failed: {}", args[0], stderr.trim()));
+    }
+    Ok(())
+}
+
+/// Writes a file that only its owner can read, since it might hold API keys.
+async fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path).await?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, contents.as_bytes()).await
+}
+
+/// Builds the property list for a launchd job that runs an update every `interval`, and once as soon as it's loaded.
+fn make_plist(
+    label: &str,
+    exe: &Path,
+    config: &[PathBuf],
+    interval: Duration,
+    env: &[(String, String)],
+    logs: &Path,
+) -> String {
+    let string = |text: &str| format!("<string>{}</string>", escape(text));
+
+    let config = config.iter().flat_map(|path| [Path::new("--config"), path]);
+    let args = (std::iter::once(exe).chain(config))
+        .map(|arg| format!("\n        {}", string(&arg.to_string_lossy())))
+        .collect::<String>();
+
+    let env = match env {
+        [] => String::new(),
+        env => {
+            let vars = (env.iter())
+                .map(|(key, value)| format!("\n        <key>{}</key>\n        {}", escape(key), string(value)))
+                .collect::<String>();
+            format!("\n    <key>EnvironmentVariables</key>\n    <dict>{vars}\n    </dict>")
+        },
+    };
+
+    let label = string(label);
+    let secs = interval.as_secs();
+    let logs = string(&logs.to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    {label}
+    <key>ProgramArguments</key>
+    <array>{args}
+    </array>{env}
+    <key>StartInterval</key>
+    <integer>{secs}</integer>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>StandardOutPath</key>
+    {logs}
+    <key>StandardErrorPath</key>
+    {logs}
+</dict>
+</plist>
+"#
+    )
+}
+
+/// Escapes text for use in XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
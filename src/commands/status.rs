@@ -0,0 +1,125 @@
+use std::io::Write;
+use std::net::IpAddr;
+
+use anstyle::{AnsiColor, Style};
+use eyre::{WrapErr, eyre};
+
+use crate::App;
+use crate::api::{DNSRecord, IpAddrExt};
+use crate::config::{RecordLookup, Target};
+
+/// How a target's existing record compares to the address it should have.
+enum Status<'a> {
+    /// There is exactly one record, and it already has the right address.
+    Current(&'a DNSRecord),
+    /// There is exactly one record, but it has some other content.
+    Outdated(&'a DNSRecord),
+    /// There's no record at all yet.
+    Missing,
+    /// There's more than one record, so it depends on `multiple_records` what an update would do.
+    Multiple(Vec<&'a DNSRecord>),
+}
+
+/// Prints a report comparing every target's existing records to this machine's current addresses, without changing
+/// anything.
+///
+/// This is the same comparison that an update makes, except that the state file is ignored (every target is looked up)
+/// and nothing is ever changed, not even for records that are out of date.
+pub async fn status(app: &App) -> eyre::Result<()> {
+    const CURRENT: Style = AnsiColor::Green.on_default();
+    const OUTDATED: Style = AnsiColor::Yellow.on_default();
+    const PROBLEM: Style = AnsiColor::Red.on_default();
+    const NAME: Style = Style::new().bold();
+
+    let (ipv4, ipv6) = app.get_addresses().await.wrap_err("Failed to determine current IP addresses")?;
+
+    let targets = app.targets.iter().collect::<Vec<_>>();
+    let (records, err_count) = match app.record_lookup {
+        RecordLookup::Zone => app.fetch_zone_records(&targets).await,
+        // Skipping lookups makes no sense here, so that means looking each target up individually too.
+        RecordLookup::Target | RecordLookup::Skip => {
+            app.fetch_target_records(&targets, ipv4.is_some(), ipv6.is_some()).await
+        },
+    };
+
+    let mut output = anstream::stdout().lock();
+    for target in targets {
+        writeln!(output, "{NAME}{target}{NAME:#}")?;
+
+        let Some(records) = records.get(&app.records_key(target)[..]) else {
+            writeln!(output, "{PROBLEM}  Could not fetch records{PROBLEM:#}")?;
+            continue;
+        };
+
+        let (ipv4, ipv6) = target.families(ipv4, ipv6);
+        for addr in [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)].into_iter().flatten() {
+            let addr = target.address_for(addr);
+            let typ = addr.dns_type();
+            let ttl = |rec: &DNSRecord| rec.ttl.map_or("default".to_string(), |ttl| ttl.to_string());
+
+            match compare(target, records, addr) {
+                Status::Current(rec) => writeln!(
+                    output,
+                    "{CURRENT}  {typ:<5} {addr} (up to date){CURRENT:#} TTL {}, ID {}",
+                    ttl(rec),
+                    rec.id
+                )?,
+                Status::Outdated(rec) => writeln!(
+                    output,
+                    "{OUTDATED}  {typ:<5} {} → {addr} (out of date){OUTDATED:#} TTL {}, ID {}",
+                    rec.content,
+                    ttl(rec),
+                    rec.id
+                )?,
+                Status::Missing => writeln!(output, "{OUTDATED}  {typ:<5} none → {addr} (missing){OUTDATED:#}")?,
+                Status::Multiple(recs) => {
+                    writeln!(output, "{PROBLEM}  {typ:<5} {} records (expected {addr}){PROBLEM:#}", recs.len())?;
+                    for rec in recs {
+                        writeln!(output, "          {} TTL {}, ID {}", rec.content, ttl(rec), rec.id)?;
+                    }
+                },
+            }
+        }
+    }
+
+    output.flush()?;
+    drop(output);
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!(
+            "Failed to fetch records for {n} {what}",
+            what = match app.record_lookup {
+                RecordLookup::Zone => pluralize!("domain", "domains", n),
+                _ => pluralize!("target", "targets", n),
+            }
+        )),
+    }
+}
+
+/// Finds a target's records for the given address's type, and checks whether they have the right content.
+fn compare<'a>(target: &Target, records: &'a [DNSRecord], addr: IpAddr) -> Status<'a> {
+    let existing = (records.iter())
+        .filter(|rec| rec.typ == addr.dns_type() && target.matches_record(rec))
+        .collect::<Vec<_>>();
+
+    match &existing[..] {
+        [] => Status::Missing,
+        [rec] => {
+            // When tracking the IPv6 prefix without a fixed suffix, only the prefix needs to match.
+            let current = match (addr, rec.try_parse_ip()) {
+                (IpAddr::V6(new), Ok(IpAddr::V6(old))) if target.tracks_ipv6_prefix() && !target.has_ipv6_suffix() => {
+                    target.same_ipv6_prefix(new, old)
+                },
+                (_, Ok(old)) => old == addr,
+                (_, Err(_)) => false,
+            };
+
+            match current {
+                true => Status::Current(rec),
+                false => Status::Outdated(rec),
+            }
+        },
+        _ => Status::Multiple(existing),
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use eyre::{WrapErr, eyre};
+
+use crate::App;
+
+/// Checks that the configured API keys work, and which of the configured domains they can actually be used with.
+///
+/// Porkbun only allows API access to domains which have had it turned on individually, so working keys aren't enough
+/// on their own; each configured domain is checked with a small lookup as well.
+pub async fn test_auth(app: &App) -> eyre::Result<()> {
+    let start = Instant::now();
+    let addr = app.client.ping().await.wrap_err("Authentication failed")?;
+    let latency = start.elapsed().as_millis();
+    println!("Authenticated successfully in {latency} ms (Porkbun sees this machine as {addr}).");
+
+    let start = Instant::now();
+    let account = app.client.list_domains().await.wrap_err("Failed to list domains on account")?;
+    let latency = start.elapsed().as_millis();
+    println!(
+        "Found {n} {domains} on account in {latency} ms:",
+        n = account.len(),
+        domains = pluralize!("domain", "domains", account.len())
+    );
+    for info in &account {
+        println!("  {} ({})", info.domain, info.status.as_deref().unwrap_or("unknown status"));
+    }
+
+    let configured = (app.targets.iter().map(|tgt| tgt.domain()))
+        .chain(app.records.iter().map(|rec| rec.target().domain()))
+        .chain(app.sync.iter().map(|sync| &sync.domain))
+        .collect::<BTreeSet<_>>();
+    if configured.is_empty() {
+        return Ok(());
+    }
+
+    println!("Configured domains:");
+    let mut err_count = 0usize;
+    for domain in configured {
+        if !account.iter().any(|info| info.domain.eq_ignore_ascii_case(domain)) {
+            println!("  {domain}: not on this account");
+            err_count += 1;
+            continue;
+        }
+
+        match app.client.get_records_by_name_type(domain, None, "A").await {
+            Ok(_) => println!("  {domain}: API access OK"),
+            Err(err) => {
+                println!("  {domain}: no API access ({err:#})");
+                err_count += 1;
+            },
+        }
+    }
+
+    match err_count {
+        0 => Ok(()),
+        n => Err(eyre!(
+            "{n} configured {domains} cannot be updated with these API keys",
+            domains = pluralize!("domain", "domains", n)
+        )),
+    }
+}
@@ -1,35 +1,152 @@
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
 use std::fmt::{Debug, Display};
-use std::path::PathBuf;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 use eyre::{WrapErr, eyre};
-use serde::de::DeserializeSeed;
-use serde::{Deserialize, Deserializer, de};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use tokio::fs;
 
-use crate::api::DNSRecord;
+use crate::api::{DNSRecord, NOTES_PLACEHOLDERS, RecordData, RecordType};
+use crate::commands::{
+    AcmeArgs,
+    ApplyArgs,
+    DaemonArgs,
+    DeleteRecordArgs,
+    ExternalDnsArgs,
+    GenerateConfigArgs,
+    ImportArgs,
+    ListRecordsArgs,
+    NsArgs,
+    PlanArgs,
+    PruneArgs,
+    RestoreArgs,
+    ServiceArgs,
+};
+use crate::logging::LogFilter;
+use crate::names::{DomainName, MAX_NAME_LEN, Subdomain};
+use crate::sources::{DEFAULT_FRITZBOX_URL, DEFAULT_IPV4_URLS, DEFAULT_IPV6_URLS, DEFAULT_STUN_SERVERS};
+
+/// The TTL given to records when neither they nor the config's `default_ttl` set one.
+pub const DEFAULT_TTL: u32 = 600;
+
+/// The lowest TTL that Porkbun accepts for any record.
+pub const MIN_TTL: u32 = 600;
+
+/// What secrets are replaced with when the config is printed.
+const REDACTED: &str = "<redacted>";
 
 #[derive(Debug, clap::Parser)]
 #[command(version, about, max_term_width = 100)]
 pub struct Args {
-    /// Path to TOML file containing configuration for the domains to update.
-    #[arg(short, long, env = "PORKBUN_CONFIG_PATH", value_name = "FILE")]
-    #[cfg_attr(unix, arg(default_value = "/etc/porkbun-ddns/ddns.toml"))]
-    pub config: PathBuf,
+    /// An alternate task to run, instead of updating records.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path to TOML, YAML, or JSON file containing configuration for the domains to update.
+    ///
+    /// May be given more than once, to layer several files (e.g., a shared base config and host-specific targets).
+    /// Settings in later files override earlier ones, except for the lists of targets, static records, and synced
+    /// domains, which are combined.
+    #[arg(short, long, global = true, env = "PORKBUN_CONFIG_PATH", value_name = "FILE")]
+    #[arg(default_values_os_t = [default_config_path()])]
+    pub config: Vec<PathBuf>,
 
-    /// Skip creating or modifying any DNS records on Porkbun.
+    /// The format of the config file.
+    ///
+    /// By default, this is determined by the file's extension: '.yaml' and '.yml' files are read as YAML, '.json' files
+    /// as JSON, and anything else as TOML.
+    #[arg(long, global = true, env = "PORKBUN_CONFIG_FORMAT", value_name = "FORMAT")]
+    pub config_format: Option<ConfigFormat>,
+
+    /// Skip creating, modifying, or deleting any DNS records on Porkbun.
     ///
     /// When this option is enabled, current IP addresses will be fetched and existing records will be checked, but no
-    /// changes will actually be made.
-    #[arg(short = 'n', long)]
+    /// changes will actually be made. Once finished, the changes that would have been made are printed to stdout.
+    #[arg(short = 'n', long, global = true)]
     pub dry_run: bool,
 
+    /// Re-send every record to Porkbun, even ones which are already up to date.
+    ///
+    /// Useful for reasserting ownership of records after editing them by hand (e.g., to restore their notes). This also
+    /// ignores the state file.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Give up on the rest of the run once this many errors have happened.
+    ///
+    /// Useful when a single problem (e.g., an expired API key) would otherwise cause the same error for every target.
+    /// Overrides the 'max_errors' setting in the config file.
+    #[arg(long, value_name = "N")]
+    pub max_errors: Option<usize>,
+
+    /// How to report the results of an update.
+    ///
+    /// With 'json', a summary of what happened to each target (and every change made) is printed to stdout at the end
+    /// of the run. Logs are still written to stderr.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t)]
+    pub output: OutputFormat,
+
+    /// Write a JSON summary of each update to this file, for other programs (e.g., status pages) to pick up.
+    ///
+    /// The summary has the same contents as '--output json': the detected addresses, what happened to each target, and
+    /// the number of errors. It's written even if the run fails.
+    #[arg(long, env = "PORKBUN_SUMMARY_FILE", value_name = "FILE")]
+    pub summary_file: Option<PathBuf>,
+
     /// Controls the verbosity of logs.
     ///
-    /// Possible log levels are 'error', 'warn', 'info', 'debug', and 'trace' (in that order).
-    #[arg(long, env = "PORKBUN_LOG_LEVEL", value_name = "LEVEL", default_value = "info")]
-    pub log_level: log::LevelFilter,
+    /// Possible log levels are 'error', 'warn', 'info', 'debug', and 'trace' (in that order). Individual modules can be
+    /// given their own levels, like 'RUST_LOG': for example, 'info,porkbun_ddns::api=trace'.
+    #[arg(
+        long,
+        global = true,
+        env = "PORKBUN_LOG_LEVEL",
+        value_name = "LEVEL",
+        default_value = "info"
+    )]
+    pub log_level: LogFilter,
+
+    /// Only log warnings and errors, plus a summary at the end of any run that changed something or had errors.
+    ///
+    /// Meant for cron jobs: runs where nothing happened print nothing at all, so there's no mail unless there's
+    /// something to read. Overrides '--log-level'.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Log more: '-v' shows debug messages, and '-vv' shows trace messages.
+    ///
+    /// This raises the default level from '--log-level' (if it isn't already that verbose), keeping any per-module
+    /// levels.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Show log messages from dependencies (e.g., reqwest and hyper) as well.
+    ///
+    /// These are normally hidden, but can help with diagnosing TLS or connection issues. Dependencies can also be shown
+    /// one at a time by giving them their own level in '--log-level' (e.g., 'info,hyper_util=debug').
+    #[arg(long, global = true)]
+    pub log_deps: bool,
+
+    /// The format of log messages.
+    ///
+    /// With 'logfmt', each message is written as a line of 'key=value' pairs (time, level, target, and msg), which is
+    /// easier for log shippers and grep to pick apart.
+    #[arg(
+        long,
+        global = true,
+        env = "PORKBUN_LOG_FORMAT",
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t
+    )]
+    pub log_format: LogFormat,
 
     /// Update IPv4 (A) records for all domains.
     ///
@@ -66,11 +183,340 @@ pub struct Args {
     /// This flag forces the IPv6 mode to "disabled", regardless of what the 'ipv6' setting in the config file says.
     #[arg(long, conflicts_with_all = ["ipv6", "try_ipv6"])]
     pub no_ipv6: bool,
+
+    /// Use this IPv4 address instead of detecting one.
+    ///
+    /// Giving either this or '--ipv6-address' skips address detection entirely (including 'ip_source'), and enables
+    /// updating IPv4 (A) records if they were disabled.
+    #[arg(long, env = "PORKBUN_IPV4_ADDRESS", value_name = "ADDR", conflicts_with = "no_ipv4")]
+    pub ipv4_address: Option<Ipv4Addr>,
+
+    /// Use this IPv6 address instead of detecting one.
+    ///
+    /// Giving either this or '--ipv4-address' skips address detection entirely (including 'ip_source'), and enables
+    /// updating IPv6 (AAAA) records if they were disabled.
+    #[arg(long, env = "PORKBUN_IPV6_ADDRESS", value_name = "ADDR", conflicts_with = "no_ipv6")]
+    pub ipv6_address: Option<Ipv6Addr>,
+
+    /// Set by `apply --no-detect`: nothing about this machine is needed, so the config file may be missing entirely
+    /// (in which case the defaults are used), and no domains are discovered.
+    #[arg(skip)]
+    pub no_detect: bool,
+}
+
+/// The config file used when none is given: `/etc/porkbun-ddns/ddns.toml`, or `%ProgramData%\porkbun-ddns\ddns.toml` on
+/// Windows.
+///
+/// When not running as root, `$XDG_CONFIG_HOME/porkbun-ddns/ddns.toml` (usually in `~/.config`) is used instead, as long
+/// as it exists. That way, running as a regular user (e.g., from a user's crontab) doesn't always need `--config`.
+#[cfg(windows)]
+fn default_config_path() -> PathBuf {
+    program_data_config_path(std::env::var_os("ProgramData"))
+}
+
+/// Finds where the system-wide config file goes on Windows, given the value of `%ProgramData%`. Its usual location is
+/// assumed if it isn't set.
+#[cfg(any(windows, test))]
+fn program_data_config_path(program_data: Option<OsString>) -> PathBuf {
+    let program_data =
+        (program_data.filter(|dir| !dir.is_empty())).map_or_else(|| PathBuf::from(r"C:\ProgramData"), PathBuf::from);
+    program_data.join("porkbun-ddns").join("ddns.toml")
+}
+
+#[cfg(not(windows))]
+fn default_config_path() -> PathBuf {
+    // SAFETY: `geteuid` has no preconditions, and always succeeds.
+    #[cfg(unix)]
+    if unsafe { libc::geteuid() } != 0
+        && let Some(path) = user_config_path()
+        && path.is_file()
+    {
+        return path;
+    }
+
+    PathBuf::from("/etc/porkbun-ddns/ddns.toml")
+}
+
+/// Finds where a per-user config file would go, following the XDG base directory spec.
+#[cfg(unix)]
+fn user_config_path() -> Option<PathBuf> {
+    xdg_config_path(std::env::var_os("XDG_CONFIG_HOME"), std::env::var_os("HOME"))
+}
+
+/// Finds where a per-user config file would go, given the values of `$XDG_CONFIG_HOME` and `$HOME`.
+#[cfg(unix)]
+fn xdg_config_path(config_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    // The spec says that relative paths should be ignored.
+    let absolute = |path: &PathBuf| path.is_absolute();
+    let config_home = (config_home.map(PathBuf::from).filter(absolute))
+        .or_else(|| home.map(|home| PathBuf::from(home).join(".config")).filter(absolute))?;
+    Some(config_home.join("porkbun-ddns").join("ddns.toml"))
+}
+
+/// The file formats that the config file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Guesses a config file's format from its extension, defaulting to TOML.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        match &ext.to_ascii_lowercase()[..] {
+            "yaml" | "yml" => ConfigFormat::Yaml,
+            "json" => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    /// Parses the text of a config file in this format, expanding any `${VAR}` placeholders in its strings.
+    pub fn parse<T: DeserializeOwned>(self, text: &str) -> eyre::Result<T> {
+        from_value(self.parse_value(text)?)
+    }
+
+    /// Parses the text of a config file in this format into a plain JSON value, expanding any `${VAR}` placeholders in
+    /// its strings.
+    ///
+    /// Every format is parsed into the same kind of value so that the placeholders can be expanded in one place (and
+    /// files can be merged), regardless of the format.
+    fn parse_value(self, text: &str) -> eyre::Result<serde_json::Value> {
+        let mut value = match self {
+            ConfigFormat::Toml => serde_json::to_value(toml::from_str::<toml::Table>(text)?)?,
+            ConfigFormat::Json => serde_json::from_str(text)?,
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml_ng::from_str(text)?,
+            #[cfg(not(feature = "yaml"))]
+            ConfigFormat::Yaml => return Err(eyre!("This build of {} does not support YAML", env!("CARGO_PKG_NAME"))),
+        };
+
+        interpolate_value(&mut value, "")?;
+        Ok(value)
+    }
+
+    /// Writes out a value (e.g., a whole [`Config`]) as text in this format.
+    pub fn render<T: Serialize>(self, value: &T) -> eyre::Result<String> {
+        Ok(match self {
+            ConfigFormat::Toml => toml::to_string_pretty(value)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml_ng::to_string(value)?,
+            #[cfg(not(feature = "yaml"))]
+            ConfigFormat::Yaml => return Err(eyre!("This build of {} does not support YAML", env!("CARGO_PKG_NAME"))),
+        })
+    }
+}
+
+/// Deserializes a parsed config file.
+fn from_value<T: DeserializeOwned>(value: serde_json::Value) -> eyre::Result<T> {
+    // Errors from a plain value don't say where in the file they came from, so the path to them is tracked instead.
+    Ok(serde_path_to_error::deserialize(value)?)
+}
+
+/// Lists which are combined when several config files are given, rather than being replaced by later files.
+const MERGED_LISTS: [&str; 6] = [
+    "targets",
+    "records",
+    "sync",
+    "gandi.targets",
+    "hetzner.targets",
+    "duckdns.domains",
+];
+
+/// Merges one parsed config file into another. `path` is where in the file `value` came from, like in
+/// [`interpolate_value`].
+///
+/// Tables are merged key by key, and the lists in [`MERGED_LISTS`] are joined together. Everything else in `over`
+/// replaces what was in `base`.
+fn merge_values(base: &mut serde_json::Value, over: serde_json::Value, path: &str) {
+    use serde_json::Value;
+
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, item) in over {
+                let path = match path {
+                    "" => key.clone(),
+                    path => format!("{path}.{key}"),
+                };
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, item, &path),
+                    None => _ = base.insert(key, item),
+                }
+            }
+        },
+        (Value::Array(base), Value::Array(over)) if MERGED_LISTS.contains(&path) => base.extend(over),
+        (base, over) => *base = over,
+    }
+}
+
+/// Expands placeholders in every string within a parsed config file. `path` is where in the file `value` came from, used
+/// in error messages.
+fn interpolate_value(value: &mut serde_json::Value, path: &str) -> eyre::Result<()> {
+    use serde_json::Value;
+
+    match value {
+        Value::String(str) if str.contains('$') => {
+            *str = interpolate(str).wrap_err_with(|| format!("Failed to expand {path}"))?;
+        },
+        Value::Array(items) => {
+            for (idx, item) in items.iter_mut().enumerate() {
+                interpolate_value(item, &format!("{path}[{idx}]"))?;
+            }
+        },
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                match path {
+                    "" => interpolate_value(item, key)?,
+                    path => interpolate_value(item, &format!("{path}.{key}"))?,
+                }
+            }
+        },
+        _ => {},
+    }
+
+    Ok(())
+}
+
+/// Replaces `${VAR}` placeholders in a string with the values of environment variables.
+///
+/// Like in a shell, `${VAR:-default}` gives `default` when `VAR` is unset or empty. Any other unset variable is an
+/// error, rather than silently becoming an empty string. `$${` can be used to write a literal `${`.
+fn interpolate(text: &str) -> eyre::Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(idx) = rest.find('$') {
+        output.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            output.push_str("${");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after.find('}').ok_or_else(|| eyre!("Unterminated placeholder {rest:?}"))?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+
+            match (crate::get_var(name).ok(), default) {
+                (Some(val), Some(default)) if val.is_empty() => output.push_str(default),
+                (Some(val), _) => output.push_str(&val),
+                (None, Some(default)) => output.push_str(default),
+                (None, None) => return Err(eyre!("Environment variable {name} is not set")),
+            }
+
+            rest = &after[end + 1..];
+        } else {
+            output.push('$');
+            rest = &rest[1..];
+        }
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// The output format for the results of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Only log messages (and, in a dry run, a diff of the changes that would have been made).
+    #[default]
+    Text,
+    /// A JSON summary of the run, printed to stdout once it's finished.
+    Json,
+}
+
+/// The format of log messages written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines, coloured by level when writing to a terminal.
+    #[default]
+    Pretty,
+    /// `key=value` pairs, one message per line.
+    Logfmt,
+}
+
+/// Subcommands for tasks other than the main update process.
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Delete A/AAAA records created by this program that no longer match any configured target.
+    Prune(PruneArgs),
+
+    /// Print or change the authoritative nameservers for a domain.
+    Ns(NsArgs),
+
+    /// Create or delete TXT records for ACME DNS-01 challenges (for use as a certbot or lego hook).
+    Acme(AcmeArgs),
+
+    /// Create records from a BIND-style zone file, or print config targets for records that already exist.
+    Import(ImportArgs),
+
+    /// Restore a domain's records from a backup file.
+    Restore(RestoreArgs),
+
+    /// Work out what an update would change, and save it to a file to be applied later (implies --dry-run).
+    Plan(PlanArgs),
+
+    /// Make the changes saved in a plan file.
+    Apply(ApplyArgs),
+
+    /// Check that the config file is valid and that API keys are available, without making any network requests.
+    Check,
+
+    /// Write a fully commented example config file to stdout, or to the given path.
+    GenerateConfig(GenerateConfigArgs),
+
+    /// Print the configuration that would be used, after applying defaults and command line options, with secrets
+    /// redacted.
+    PrintConfig,
+
+    /// Show every address that has been pushed to each target, and when, from the state file.
+    History,
+
+    /// Check that the API keys work, and which of the configured domains they can be used with.
+    TestAuth,
+
+    /// Show how each target's existing records compare to the current addresses, without changing anything.
+    Status,
+
+    /// Print every record on a domain.
+    ListRecords(ListRecordsArgs),
+
+    /// Delete a record by ID, or every record with a given name and type.
+    DeleteRecord(DeleteRecordArgs),
+
+    /// Keep running in the foreground, updating records every so often instead of just once.
+    Daemon(DaemonArgs),
+
+    /// Install (or uninstall) a background service that updates records every so often.
+    Service(ServiceArgs),
+
+    /// Serve the external-dns webhook provider API, so that Kubernetes can manage records on Porkbun.
+    ExternalDns(ExternalDnsArgs),
 }
 
 /// Main program configuration and job specification.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
+    /// A file to read the Porkbun API key from, if it isn't set in the environment.
+    #[serde(default)]
+    pub api_key_file: Option<PathBuf>,
+
+    /// A file to read the Porkbun secret API key from, if it isn't set in the environment.
+    #[serde(default)]
+    pub secret_key_file: Option<PathBuf>,
+
+    /// An age- or SOPS-encrypted file holding the API keys, used when they aren't given any other way.
+    #[serde(default)]
+    pub secrets_file: Option<PathBuf>,
+
+    /// The identity (private key) file used to decrypt an age-encrypted `secrets_file`.
+    #[serde(default)]
+    pub age_identity: Option<PathBuf>,
+
     /// Enables updating of `A` records with an IPv4 address.
     #[serde(default = "enabled")]
     pub ipv4: AddrMode,
@@ -79,55 +525,421 @@ pub struct Config {
     #[serde(default = "disabled")]
     pub ipv6: AddrMode,
 
+    /// Deletes the A or AAAA records that this program created for its targets once their address family is disabled.
+    #[serde(default)]
+    pub cleanup_disabled: bool,
+
+    /// Where to find this machine's current addresses. Porkbun's API is asked by default.
+    #[serde(default)]
+    pub ip_source: IpSourceConfig,
+
+    /// Sources to try, in order, whenever `ip_source` fails.
+    #[serde(default)]
+    pub ip_fallback: Option<IpSourceConfig>,
+
+    /// How the answers from several IP sources are combined, when more than one is configured.
+    #[serde(default)]
+    pub ip_consensus: ConsensusPolicy,
+
+    /// Tracks a delegated IPv6 prefix of this length, rather than full addresses.
+    #[serde(default)]
+    pub ipv6_prefix_len: Option<u8>,
+
+    /// Allows publishing addresses in private ranges (e.g., for split-horizon setups), which are refused by default.
+    #[serde(default)]
+    pub allow_private_ips: bool,
+
+    /// How existing records are fetched from Porkbun before updating them.
+    #[serde(default)]
+    pub record_lookup: RecordLookup,
+
+    /// Asks Porkbun's nameservers for each target's records first, so that only targets whose records are out of date
+    /// need any API calls.
+    #[serde(default)]
+    pub dns_precheck: bool,
+
+    /// What to put in the notes of records that are created or edited.
+    #[serde(default)]
+    pub notes: NotesMode,
+
+    /// A custom template for records' notes, which is added after the marker in place of its timestamp.
+    #[serde(default)]
+    pub notes_template: Option<String>,
+
+    /// Whether the built-in marker says when the record was last updated. Without the time, the notes stay the same
+    /// from one update to the next. Ignored when there's a `notes_template`, which has its own `{timestamp}`.
+    #[serde(default = "yes")]
+    pub notes_timestamp: bool,
+
+    /// What to do when a target has more than one existing A or AAAA record.
+    #[serde(default)]
+    pub multiple_records: MultipleRecords,
+
+    /// Automatically adds a target for the root of every domain on the Porkbun account.
+    #[serde(default)]
+    pub auto_discover: bool,
+
+    /// The TTL of every target and static record that doesn't set its own.
+    #[serde(default = "default_ttl")]
+    pub default_ttl: u32,
+
     /// A list of jobs describing domains/subdomains to update.
     // Better to let the program print "nothing enabled" than to throw an error, I think.
+    #[serde(default = "empty", deserialize_with = "targets")]
+    pub targets: Vec<Target>,
+
+    /// A list of records with fixed content (of any type) to keep in place alongside the dynamic A/AAAA records.
     #[serde(default = "empty")]
+    pub records: Vec<StaticRecord>,
+
+    /// Domains whose records should exactly match the config file. Anything not listed gets deleted.
+    #[serde(default = "empty")]
+    pub sync: Vec<SyncDomain>,
+
+    /// A directory to save a snapshot of each domain's records to, before any of them are edited or deleted.
+    #[serde(default)]
+    pub backup_dir: Option<PathBuf>,
+
+    /// A file to append a line of JSON to for every record that gets created, edited, or deleted.
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+
+    /// A file to remember the last addresses pushed to each target in. Targets whose addresses haven't changed since
+    /// then are skipped without making any API calls.
+    #[serde(default)]
+    pub state_file: Option<PathBuf>,
+
+    /// How long the state file can be trusted for before records are checked against Porkbun anyway, even if the
+    /// addresses haven't changed.
+    #[serde(default, deserialize_with = "duration", serialize_with = "duration_str")]
+    pub refresh_every: Option<Duration>,
+
+    /// How many errors a run can hit before the rest of it is abandoned. By default, every target is always attempted.
+    #[serde(default)]
+    pub max_errors: Option<usize>,
+
+    /// Checks that records can actually be resolved after they're created or changed.
+    #[serde(default)]
+    pub verify: Option<VerifyConfig>,
+
+    /// Settings for updating DuckDNS domains alongside Porkbun ones.
+    #[serde(default)]
+    pub duckdns: Option<DuckDnsConfig>,
+
+    /// Settings for updating Gandi LiveDNS records alongside Porkbun ones.
+    #[serde(default)]
+    pub gandi: Option<GandiConfig>,
+
+    /// Settings for updating Hetzner DNS records alongside Porkbun ones.
+    #[serde(default)]
+    pub hetzner: Option<HetznerConfig>,
+
+    /// Where to send notifications when records change or updates fail.
+    #[serde(default)]
+    pub notify: Option<NotifyConfig>,
+
+    /// A program to run (with its arguments) whenever a target's record is created or changed.
+    #[serde(default, deserialize_with = "command")]
+    pub on_change: Option<Vec<String>>,
+
+    /// A program to run (with its arguments) whenever something goes wrong.
+    #[serde(default, deserialize_with = "command")]
+    pub on_failure: Option<Vec<String>>,
+
+    /// An MQTT broker to publish current addresses and update results to.
+    #[serde(default)]
+    pub mqtt: Option<MqttConfig>,
+}
+
+/// Configuration for [verifying][crate::verify] that changed records have propagated.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyConfig {
+    /// Which servers to ask for the new records.
+    #[serde(default)]
+    pub servers: VerifyServers,
+
+    /// How long to keep asking before giving up. Two minutes by default.
+    #[serde(default, deserialize_with = "duration", serialize_with = "duration_str")]
+    pub timeout: Option<Duration>,
+}
+
+/// Configuration for the [DuckDNS provider][crate::providers::DuckDns].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DuckDnsConfig {
+    /// The account token shown on DuckDNS's dashboard.
+    #[serde(serialize_with = "redacted")]
+    pub token: String,
+
+    /// The DuckDNS subdomains to update, without the `.duckdns.org` suffix.
+    pub domains: Vec<String>,
+}
+
+/// Configuration for the [Gandi LiveDNS provider][crate::providers::Gandi].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GandiConfig {
+    /// A LiveDNS API key, generated from Gandi's account settings.
+    #[serde(serialize_with = "redacted")]
+    pub api_key: String,
+
+    /// Domains/subdomains to update on Gandi. These are specified the exact same way as Porkbun targets.
+    #[serde(default = "empty", deserialize_with = "targets")]
+    pub targets: Vec<Target>,
+}
+
+/// Configuration for the [Hetzner DNS provider][crate::providers::Hetzner].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HetznerConfig {
+    /// An API token, generated from the Hetzner DNS Console.
+    #[serde(serialize_with = "redacted")]
+    pub token: String,
+
+    /// Domains/subdomains to update on Hetzner. These are specified the exact same way as Porkbun targets.
+    #[serde(default = "empty", deserialize_with = "targets")]
     pub targets: Vec<Target>,
 }
 
+/// Configuration for [notifications][crate::notify].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Sends each event to a URL of your choosing.
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+
+    /// Sends push notifications through an ntfy server (e.g., ntfy.sh).
+    #[serde(default)]
+    pub ntfy: Option<NtfyConfig>,
+
+    /// Posts messages to a Discord channel through a webhook.
+    #[serde(default)]
+    pub discord: Option<DiscordConfig>,
+}
+
+/// Configuration for the [webhook notifier][crate::notify::Webhook].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// The URL to POST each event to.
+    pub url: String,
+
+    /// Which events to send.
+    #[serde(default)]
+    pub events: NotifyEvents,
+
+    /// A template for the request body. If not given, the event is sent as a JSON object.
+    #[serde(default)]
+    pub body: Option<String>,
+
+    /// The `Content-Type` of the request body.
+    #[serde(default = "json_content_type")]
+    pub content_type: String,
+
+    /// Extra headers to send with each request (e.g., for authentication).
+    #[serde(default, serialize_with = "redacted_values")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Configuration for the [ntfy notifier][crate::notify::Ntfy].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    /// The full URL of the topic to publish to, e.g. `https://ntfy.sh/my-topic`.
+    pub url: String,
+
+    /// Which events to send.
+    #[serde(default)]
+    pub events: NotifyEvents,
+
+    /// How urgently notifications should be shown.
+    #[serde(default)]
+    pub priority: NtfyPriority,
+
+    /// An access token, for topics that require one.
+    #[serde(default, serialize_with = "redacted", skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// A username to log in with, for topics that require one (along with `password`).
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// The password for `username`.
+    #[serde(default, serialize_with = "redacted", skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// The priority levels that ntfy supports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NtfyPriority {
+    Min,
+    Low,
+    #[default]
+    Default,
+    High,
+    #[serde(alias = "max")]
+    Urgent,
+}
+
+/// Configuration for the [Discord notifier][crate::notify::Discord].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiscordConfig {
+    /// The webhook URL, from the channel's integration settings. Includes the webhook's token.
+    #[serde(serialize_with = "redacted")]
+    pub url: String,
+
+    /// Which events to send.
+    #[serde(default)]
+    pub events: NotifyEvents,
+}
+
+/// Configuration for publishing to MQTT.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+pub struct MqttConfig {
+    /// The broker's hostname or IP address.
+    pub host: String,
+
+    /// The broker's port. Defaults to 1883, or 8883 when using TLS.
+    #[serde(default)]
+    pub port: Option<u16>,
+
+    /// Connects to the broker over TLS.
+    #[serde(default)]
+    pub tls: bool,
+
+    /// A username to log in to the broker with.
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// The password for `username`.
+    #[serde(default, serialize_with = "redacted", skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// The client ID to connect with. Defaults to the program's name followed by this machine's hostname.
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// The prefix for every topic that gets published to.
+    #[serde(default = "mqtt_topic")]
+    pub topic: String,
+}
+
+/// Which kinds of events a notifier should be sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyEvents {
+    /// Both changes and failures.
+    #[default]
+    All,
+    /// Only records being created or changed.
+    Change,
+    /// Only errors.
+    Failure,
+}
+
 // [FIXME] Serde does not support literals as default values yet: https://github.com/serde-rs/serde/issues/368
 #[rustfmt::skip] const fn empty<T>() -> Vec<T> { Vec::new() }
 #[rustfmt::skip] const fn enabled() -> AddrMode { AddrMode::Enabled }
 #[rustfmt::skip] const fn disabled() -> AddrMode { AddrMode::Disabled }
+#[rustfmt::skip] const fn default_ttl() -> u32 { DEFAULT_TTL }
+#[rustfmt::skip] const fn yes() -> bool { true }
+#[rustfmt::skip] fn mqtt_topic() -> String { env!("CARGO_PKG_NAME").to_string() }
+#[rustfmt::skip] fn json_content_type() -> String { "application/json".to_string() }
 
 impl Config {
     /// Loads runtime configuration from command line arguments and configuration file.
     pub async fn from_args(args: Args) -> eyre::Result<Self> {
-        if log::log_enabled!(log::Level::Trace) {
-            log::trace!("Reading configuration from {}", &args.config.to_string_lossy());
+        let mut value = serde_json::Value::Object(Default::default());
+        for path in &args.config {
+            log::trace!("Reading configuration from {}", path.display());
+
+            let text = match fs::read_to_string(path).await {
+                Err(err) if args.no_detect && err.kind() == std::io::ErrorKind::NotFound => {
+                    log::debug!("No config file at {}; skipping it.", path.display());
+                    continue;
+                },
+                res => res.wrap_err_with(|| format!("Failed to read config file {}", path.display()))?,
+            };
+
+            let format = args.config_format.unwrap_or_else(|| ConfigFormat::from_path(path));
+            let file = (format.parse_value(&text))
+                .wrap_err_with(|| format!("Failed to parse config file {}", path.display()))?;
+            merge_values(&mut value, file, "");
         }
 
-        let text = fs::read_to_string(&args.config).await.wrap_err("Failed to read config file")?;
-        let mut config: Config = toml::from_str(&text).wrap_err("Failed to parse config file")?;
+        let mut config: Config = from_value(value).wrap_err("Failed to parse config file")?;
 
         config.extend_from_args(&args);
 
+        if config.default_ttl < MIN_TTL {
+            return Err(eyre!("default_ttl must be at least {MIN_TTL}, Porkbun's minimum"))
+                .wrap_err("Invalid configuration");
+        }
+
+        // Targets and records can't see the rest of the config while they're being parsed, so any without a TTL of
+        // their own only get the default one now.
+        let gandi = config.gandi.iter_mut().flat_map(|gandi| &mut gandi.targets);
+        let hetzner = config.hetzner.iter_mut().flat_map(|hetzner| &mut hetzner.targets);
+        let records = config.records.iter_mut().map(|record| &mut record.target);
+        for target in config.targets.iter_mut().chain(gandi).chain(hetzner).chain(records) {
+            target.ttl.get_or_insert(config.default_ttl);
+        }
+
+        if let Some(len) = config.ipv6_prefix_len {
+            if !(1..=128).contains(&len) {
+                return Err(eyre!("ipv6_prefix_len must be between 1 and 128")).wrap_err("Invalid configuration");
+            }
+
+            let gandi = config.gandi.iter_mut().flat_map(|gandi| &mut gandi.targets);
+            let hetzner = config.hetzner.iter_mut().flat_map(|hetzner| &mut hetzner.targets);
+            for target in config.targets.iter_mut().chain(gandi).chain(hetzner) {
+                target.ipv6_prefix_len = Some(len);
+            }
+        }
+
         // Again, this will run in a cron job / timer. This is a lot of unnecessary stuff to dump into logs.
         // It may be helpful to have again later, though...
         /* log::trace!("Final config: {config:?}"); */
 
-        // Check that all targets are unique:
-        let mut tgt_labels = HashMap::with_capacity(config.targets.len());
-        let mut idx = 0usize;
-        for tgt in &config.targets {
-            idx += 1;
-            match tgt_labels.entry(tgt.to_string()) {
-                Entry::Vacant(entry) => {
-                    entry.insert(idx);
-                },
-                Entry::Occupied(entry) => {
-                    let idx1 = *entry.get();
-                    let key = entry.key();
-                    return Err(eyre!("Target {key} specified more than once (targets {idx1} and {idx})")
-                        .wrap_err("Invalid configuration"));
-                },
-            }
+        if let (IpSourceConfig::Many(sources), ConsensusPolicy::AtLeast(n)) = (&config.ip_source, config.ip_consensus)
+            && n > sources.len()
+        {
+            return Err(eyre!("ip_consensus needs {n} sources to agree, but only {} are configured", sources.len()))
+                .wrap_err("Invalid configuration");
+        }
+
+        if config.max_errors == Some(0) {
+            return Err(eyre!("max_errors must be at least 1")).wrap_err("Invalid configuration");
+        }
+
+        check_unique(&config.targets, "targets").wrap_err("Invalid configuration")?;
+        check_unique_records(&config.records).wrap_err("Invalid configuration")?;
+        check_ttls(&config.targets, "targets").wrap_err("Invalid configuration")?;
+        check_ttls(config.records.iter().map(StaticRecord::target), "records").wrap_err("Invalid configuration")?;
+        if let Some(template) = &config.notes_template {
+            check_notes_template(template).wrap_err("Invalid configuration")?;
+        }
+        if let Some(gandi) = &config.gandi {
+            check_unique(&gandi.targets, "gandi.targets").wrap_err("Invalid configuration")?;
+        }
+        if let Some(hetzner) = &config.hetzner {
+            check_unique(&hetzner.targets, "hetzner.targets").wrap_err("Invalid configuration")?;
+        }
+        #[cfg(not(feature = "mqtt"))]
+        if config.mqtt.is_some() {
+            return Err(eyre!("This build of {} does not support MQTT", env!("CARGO_PKG_NAME")));
         }
 
         Ok(config)
     }
 
-    /// Copies over non-TOML settings from the command line into this [`Config`] struct.
+    /// Copies over non-config-file settings from the command line into this [`Config`] struct.
     fn extend_from_args(&mut self, args: &Args) {
         // Only copy the values from args if they were actually specified in args,
         // otherwise let the config values through (both for true and for false).
@@ -139,24 +951,421 @@ impl Config {
             self.ipv4 = AddrMode::Try;
         }
 
-        if args.ipv6 {
-            self.ipv6 = AddrMode::Enabled;
-        } else if args.no_ipv6 {
-            self.ipv6 = AddrMode::Disabled;
-        } else if args.try_ipv6 {
-            self.ipv6 = AddrMode::Try;
-        }
+        if args.ipv6 {
+            self.ipv6 = AddrMode::Enabled;
+        } else if args.no_ipv6 {
+            self.ipv6 = AddrMode::Disabled;
+        } else if args.try_ipv6 {
+            self.ipv6 = AddrMode::Try;
+        }
+
+        if args.ipv4_address.is_some() || args.ipv6_address.is_some() {
+            if args.ipv4_address.is_some() && self.ipv4 == AddrMode::Disabled {
+                self.ipv4 = AddrMode::Enabled;
+            }
+            if args.ipv6_address.is_some() && self.ipv6 == AddrMode::Disabled {
+                self.ipv6 = AddrMode::Enabled;
+            }
+            self.ip_source = IpSourceConfig::Given(args.ipv4_address, args.ipv6_address);
+        }
+
+        if args.max_errors.is_some() {
+            self.max_errors = args.max_errors;
+        }
+
+        // ...other future settings.
+    }
+}
+
+/// Deserializes an optional duration, given either as a number of seconds or as a string like `"1h30m"`.
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Secs(u64),
+        Str(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Secs(secs) => Ok(Some(Duration::from_secs(secs))),
+        Raw::Str(str) => parse_duration(&str).map(Some).map_err(de::Error::custom),
+    }
+}
+
+/// Deserializes the subdomain of a protection rule, where `@` (or an empty string) means the root of the domain.
+fn protected_subdomain<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<Subdomain>>, D::Error> {
+    let str = String::deserialize(deserializer)?;
+    Subdomain::parse(&str).map(Some).map_err(de::Error::custom)
+}
+
+/// Deserializes a command to run, given either as a single program or as a list of a program and its arguments.
+fn command<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Program(String),
+        Command(Vec<String>),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Program(program) => Ok(Some(vec![program])),
+        Raw::Command(command) if command.is_empty() => Err(de::Error::custom("command may not be empty")),
+        Raw::Command(command) => Ok(Some(command)),
+    }
+}
+
+/// Parses a duration made up of numbers with `s`, `m`, `h`, `d`, or `w` units, e.g. `"1h30m"` or `"7d"`.
+pub fn parse_duration(str: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {str:?} (expected something like \"30m\", \"24h\", or \"1h30m\")");
+
+    let mut secs = 0u64;
+    let mut num = String::new();
+    for c in str.trim().chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+
+        let mult = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            _ => return Err(invalid()),
+        };
+
+        let n = num.parse::<u64>().map_err(|_| invalid())?;
+        secs = secs.saturating_add(n.saturating_mul(mult));
+        num.clear();
+    }
+
+    // Empty strings and trailing numbers without units aren't allowed.
+    if str.trim().is_empty() || !num.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(secs))
+}
+
+/// Formats a duration the same way that [`parse_duration`] reads them, using the largest units possible, e.g. `"1h30m"`.
+pub fn format_duration(duration: Duration) -> String {
+    const UNITS: [(char, u64); 5] = [
+        ('w', 60 * 60 * 24 * 7),
+        ('d', 60 * 60 * 24),
+        ('h', 60 * 60),
+        ('m', 60),
+        ('s', 1),
+    ];
+
+    let mut secs = duration.as_secs();
+    if secs == 0 {
+        return "0s".to_string();
+    }
+
+    let mut str = String::new();
+    for (unit, mult) in UNITS {
+        if secs >= mult {
+            str += &format!("{}{unit}", secs / mult);
+            secs %= mult;
+        }
+    }
+    str
+}
+
+/// Serializes an optional duration as a string like `"1h30m"`, which [`duration`] can read back.
+fn duration_str<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_str(&format_duration(*duration)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serializes the subdomain of a protection rule, writing the root of the domain as `@`.
+fn protected_subdomain_str<S: Serializer>(
+    subdomain: &Option<Option<Subdomain>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match subdomain {
+        Some(sub) => serializer.serialize_str(sub.as_ref().map_or("@", Subdomain::as_str)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serializes a secret (an API key, password, etc.) without giving it away, so that the config can be printed safely.
+fn redacted<T, S: Serializer>(_: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+/// Serializes a map whose values might be secrets (e.g., HTTP headers), keeping only its keys.
+fn redacted_values<S: Serializer>(map: &BTreeMap<String, String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.keys().map(|key| (key, REDACTED)))
+}
+
+/// Checks that all targets in a list are unique. `field` is the name of the list, used in error messages.
+///
+/// Since one entry in the list may expand into several targets (with `subdomains`), the position of the duplicate isn't
+/// reported; just its name.
+fn check_unique(targets: &[Target], field: &str) -> eyre::Result<()> {
+    let mut tgt_labels = HashSet::with_capacity(targets.len());
+    for tgt in targets {
+        let key = tgt.to_string();
+        if !tgt_labels.insert(key.clone()) {
+            return Err(eyre!("Target {key} specified more than once in {field}"));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every target in a list has a TTL that Porkbun accepts. `field` is the name of the list, used in error
+/// messages.
+///
+/// Porkbun rejects records with lower TTLs outright, with an error that doesn't say which part of the record was wrong.
+/// Other providers have their own limits, so their targets aren't checked.
+fn check_ttls<'a>(targets: impl IntoIterator<Item = &'a Target>, field: &str) -> eyre::Result<()> {
+    for tgt in targets {
+        let ttl = tgt.ttl();
+        if ttl < MIN_TTL {
+            return Err(eyre!("Target {tgt} in {field} has a TTL of {ttl}, but Porkbun's minimum is {MIN_TTL}"));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a notes template only uses placeholders that exist.
+fn check_notes_template(template: &str) -> eyre::Result<()> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+
+        let name = &rest[start + 1..start + len];
+        if !NOTES_PLACEHOLDERS.contains(&name) {
+            let expected = NOTES_PLACEHOLDERS.iter().map(|p| format!("{{{p}}}")).collect::<Vec<_>>();
+            return Err(eyre!(
+                "Unknown placeholder {{{name}}} in notes_template (expected one of {})",
+                expected.join(", ")
+            ));
+        }
+
+        rest = &rest[start + len + 1..];
+    }
+    Ok(())
+}
+
+/// Checks that no two static records have the exact same name, type, and content (or command).
+fn check_unique_records(records: &[StaticRecord]) -> eyre::Result<()> {
+    for (i, rec) in records.iter().enumerate() {
+        let later = records[i + 1..].iter().position(|other| {
+            other.target.to_string() == rec.target.to_string()
+                && other.data.typ == rec.data.typ
+                && other.data.content == rec.data.content
+                && other.command == rec.command
+        });
+
+        if let Some(j) = later {
+            let (idx1, idx2) = (i + 1, i + j + 2);
+            return Err(eyre!("Record {rec} specified more than once (records {idx1} and {idx2})"));
+        }
+    }
+    Ok(())
+}
+
+/// Specification for a single domain or subdomain to update.
+#[derive(Debug, Clone)]
+pub struct Target {
+    domain: DomainName,
+    subdomain: Option<Subdomain>,
+    /// The TTL given to this target's records. Left unset by the config file if the target doesn't give one, until the
+    /// config's `default_ttl` is filled in.
+    ttl: Option<u32>,
+    /// A fixed interface identifier to combine with the detected IPv6 prefix, for targets that point at some other
+    /// machine on the same network. Only the bits after the prefix (the lower 64, by default) are used.
+    ipv6_suffix: Option<Ipv6Addr>,
+    /// The length of the delegated IPv6 prefix to track, if prefix tracking is enabled. Copied from the config.
+    ipv6_prefix_len: Option<u8>,
+    /// More addresses to publish alongside this machine's own, making a round-robin set of records.
+    extra_addresses: Vec<ExtraAddress>,
+    /// Whether to only pretend to update this target's records, as if the whole run were a dry run.
+    dry_run: bool,
+    /// Whether to write notes on this target's records at all. If not, their notes are never sent, whatever the
+    /// config's `notes` setting is.
+    notes: bool,
+    /// Whether to update this target's A records, as long as IPv4 is enabled at all.
+    ipv4: bool,
+    /// Whether to update this target's AAAA records, as long as IPv6 is enabled at all.
+    ipv6: bool,
+}
+
+/// An address that a round-robin target publishes alongside this machine's own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtraAddress {
+    /// A fixed address.
+    Fixed(IpAddr),
+    /// Every public address of a local network interface, read each time records are updated.
+    Interface(String),
+}
+
+/// A DNS record of any type whose content is given directly in the config file, or printed by a program.
+#[derive(Debug, Clone)]
+pub struct StaticRecord {
+    /// The name and TTL of the record.
+    target: Target,
+    /// The type and content of the record. The content is left empty when it comes from `command`.
+    data: RecordData,
+    /// A program (with its arguments) whose output is the record's content, run every time the record is updated.
+    command: Option<Vec<String>>,
+}
+
+/// A domain whose records are fully described by the config file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncDomain {
+    pub domain: DomainName,
+
+    /// Records which are never touched, even though they don't appear in the config file.
+    #[serde(default = "empty")]
+    pub protect: Vec<Protection>,
+}
+
+/// A pattern matching records on a synced domain which should never be modified. Either field can be left out to match
+/// any name or any type.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Protection {
+    /// `Some(None)` protects the root of the domain.
+    #[serde(
+        default,
+        deserialize_with = "protected_subdomain",
+        serialize_with = "protected_subdomain_str"
+    )]
+    pub subdomain: Option<Option<Subdomain>>,
+    #[serde(default, rename = "type")]
+    pub typ: Option<String>,
+}
+
+/// Where this machine's current addresses are found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum IpSourceConfig {
+    /// Ask Porkbun's API which address requests come from.
+    #[default]
+    Porkbun,
+    /// Read addresses from the network interface with the given name.
+    Interface(String),
+    /// Run a program (with arguments) and read addresses from its output.
+    Command(Vec<String>),
+    /// Read addresses from a file, optionally refusing to trust it once it's older than `max_age`.
+    File { path: PathBuf, max_age: Option<Duration> },
+    /// Ask special DNS names on public resolvers, in order, which address our queries come from.
+    Dns(Vec<WhoamiService>),
+    /// Ask plain "what's my IP" web services, in order, which address our requests come from.
+    Http { ipv4: Vec<Url>, ipv6: Vec<Url> },
+    /// Ask STUN servers (`host:port`), in order, which address our packets come from.
+    Stun(Vec<String>),
+    /// Ask the local router with UPnP IGD, either found with SSDP or at the given device description URL.
+    Upnp(Option<Url>),
+    /// Ask the local router with NAT-PMP (or PCP), either the default gateway or the one at the given address.
+    NatPmp(Option<Ipv4Addr>),
+    /// Ask an AVM Fritz!Box over TR-064, logging in if a password is given.
+    FritzBox {
+        url: Url,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// Read the addresses of a MikroTik router's interface through its REST API, optionally without checking its
+    /// (usually self-signed) certificate.
+    Mikrotik {
+        url: Url,
+        username: String,
+        password: String,
+        interface: String,
+        insecure: bool,
+    },
+    /// Use the addresses given on the command line. Can't be set from the config file.
+    Given(Option<Ipv4Addr>, Option<Ipv6Addr>),
+    /// Ask several sources at once, combining their answers according to the [`ConsensusPolicy`].
+    Many(Vec<IpSourceConfig>),
+}
+
+/// How the answers from several IP sources are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConsensusPolicy {
+    /// Use the first source's answer (in the order they're configured), but warn when others disagree with it.
+    #[default]
+    Warn,
+    /// Only use an address if every source agrees on it.
+    All,
+    /// Only use an address if at least this many sources agree on it.
+    AtLeast(usize),
+}
+
+/// DNS services which answer with the address that a query came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhoamiService {
+    /// `myip.opendns.com` on OpenDNS's resolvers.
+    OpenDns,
+    /// `whoami.cloudflare` (a `CH`-class `TXT` record) on Cloudflare's resolvers.
+    Cloudflare,
+    /// `whoami.akamai.net` on Akamai's nameservers. IPv4 only.
+    Akamai,
+}
+
+/// Strategies for fetching existing DNS records from Porkbun.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordLookup {
+    /// Fetch every record for each domain at once, with a single request per domain.
+    #[default]
+    Zone,
+    /// Fetch only the records with each target's exact name and type, with one request per target per type. Better
+    /// suited to domains with many records.
+    Target,
+    /// Don't fetch existing records up front; edit them directly by name and type instead, as long as `multiple_records`
+    /// allows every record with that name to be updated. Otherwise, or if that edit fails (e.g., if the record doesn't
+    /// exist yet and needs to be created), each target's records are looked up on their own.
+    Skip,
+}
 
-        // ...other future settings.
-    }
+/// Which servers to ask when verifying that a changed record has propagated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerifyServers {
+    /// The domain's own nameservers, as set at the registry (normally Porkbun's).
+    #[default]
+    Authoritative,
+    /// Well-known public resolvers (Cloudflare, Google, and Quad9). These may keep giving out the old address until the
+    /// record's previous TTL runs out.
+    Public,
 }
 
-/// Specification for a single domain or subdomain to update.
-#[derive(Debug, Clone)]
-pub struct Target {
-    domain: String,
-    subdomain: Option<String>,
-    ttl: u32,
+/// Policies for targets that have more than one existing record of the same type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MultipleRecords {
+    /// Report an error and leave the target alone.
+    #[default]
+    Error,
+    /// Update only the first of the records.
+    UpdateFirst,
+    /// Update every one of the records to the same address.
+    UpdateAll,
+    /// Keep one record (preferring one that's already correct), delete the rest, and update the one that's left.
+    Dedupe,
+}
+
+/// Policies for the notes attached to records that this program writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotesMode {
+    /// Overwrite the notes with a marker saying when the record was last updated by this program.
+    #[default]
+    Marker,
+    /// Leave existing records' notes untouched. Newly created records still get the marker.
+    Preserve,
+    /// Never send any notes. Existing records keep whatever notes they have, and new records get none.
+    Off,
 }
 
 /// A value which can be true, false, or something in between.
@@ -168,40 +1377,193 @@ pub enum AddrMode {
 }
 
 impl Target {
-    pub fn domain(&self) -> &str {
-        &self.domain[..]
+    pub fn domain(&self) -> &DomainName {
+        &self.domain
     }
 
-    pub fn subdomain(&self) -> Option<&str> {
-        self.subdomain.as_deref()
+    pub fn subdomain(&self) -> Option<&Subdomain> {
+        self.subdomain.as_ref()
     }
 
     pub fn ttl(&self) -> u32 {
-        self.ttl
+        self.ttl.unwrap_or(DEFAULT_TTL)
+    }
+
+    /// Creates a new [`Target`] with the given properties.
+    pub fn new(domain: DomainName, subdomain: Option<Subdomain>, ttl: u32) -> Self {
+        Self {
+            domain,
+            subdomain,
+            ttl: Some(ttl),
+            ipv6_suffix: None,
+            ipv6_prefix_len: None,
+            extra_addresses: Vec::new(),
+            dry_run: false,
+            notes: true,
+            ipv4: true,
+            ipv6: true,
+        }
     }
 
     /// Creates a default [`Target`] out of just a domain name.
-    fn from_domain(domain: String) -> Self {
+    pub fn from_domain(domain: DomainName) -> Self {
         Self {
             domain,
             subdomain: None,
-            ttl: 600,
+            ttl: None,
+            ipv6_suffix: None,
+            ipv6_prefix_len: None,
+            extra_addresses: Vec::new(),
+            dry_run: false,
+            notes: true,
+            ipv4: true,
+            ipv6: true,
+        }
+    }
+
+    /// Addresses to publish alongside this machine's own.
+    pub fn extra_addresses(&self) -> &[ExtraAddress] {
+        &self.extra_addresses
+    }
+
+    /// Whether changes to this target's records should only be logged, not actually made.
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Whether this target's records should be given notes (or have their existing notes touched at all).
+    pub fn writes_notes(&self) -> bool {
+        self.notes
+    }
+
+    /// Whether this target's records of the given address family should be updated, assuming that family is enabled.
+    pub fn updates_family(&self, ipv6: bool) -> bool {
+        match ipv6 {
+            false => self.ipv4,
+            true => self.ipv6,
+        }
+    }
+
+    /// Narrows down the current addresses to the ones that this target's records should be updated with, leaving out
+    /// any families that are disabled for this target.
+    pub fn families(&self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> (Option<Ipv4Addr>, Option<Ipv6Addr>) {
+        (ipv4.filter(|_| self.ipv4), ipv6.filter(|_| self.ipv6))
+    }
+
+    /// Whether this target publishes a whole set of addresses of each type, rather than a single one.
+    pub fn is_round_robin(&self) -> bool {
+        !self.extra_addresses.is_empty()
+    }
+
+    /// Checks whether this target refers to the root of its domain.
+    pub fn is_root(&self) -> bool {
+        self.subdomain.is_none()
+    }
+
+    /// Gets the address that this target's record should hold, given this machine's current address.
+    ///
+    /// This is the same address unless it's an IPv6 address and this target has an `ipv6_suffix`, in which case the
+    /// address's /64 prefix is combined with the suffix.
+    pub fn address_for(&self, addr: IpAddr) -> IpAddr {
+        match addr {
+            IpAddr::V4(_) => addr,
+            IpAddr::V6(v6) => IpAddr::V6(self.ipv6_for(v6)),
         }
     }
 
+    /// Like [`address_for`][Self::address_for], but for IPv6 addresses only.
+    pub fn ipv6_for(&self, addr: Ipv6Addr) -> Ipv6Addr {
+        match self.ipv6_suffix {
+            Some(suffix) => self.with_host_bits(addr, suffix),
+            None => addr,
+        }
+    }
+
+    /// The length of the IPv6 prefix that this target's addresses share with the detected address (64, unless prefix
+    /// tracking is enabled).
+    pub fn ipv6_prefix_len(&self) -> u8 {
+        self.ipv6_prefix_len.unwrap_or(64)
+    }
+
+    /// Whether this target has a fixed IPv6 interface identifier.
+    pub fn has_ipv6_suffix(&self) -> bool {
+        self.ipv6_suffix.is_some()
+    }
+
+    /// Whether IPv6 prefix tracking is enabled for this target.
+    pub fn tracks_ipv6_prefix(&self) -> bool {
+        self.ipv6_prefix_len.is_some()
+    }
+
+    /// Combines the prefix of one address with the host part of another, using this target's prefix length.
+    pub fn with_host_bits(&self, prefix: Ipv6Addr, host: Ipv6Addr) -> Ipv6Addr {
+        let mask = self.ipv6_prefix_mask();
+        Ipv6Addr::from_bits((prefix.to_bits() & mask) | (host.to_bits() & !mask))
+    }
+
+    /// Checks whether two addresses share the same prefix, using this target's prefix length.
+    pub fn same_ipv6_prefix(&self, a: Ipv6Addr, b: Ipv6Addr) -> bool {
+        let mask = self.ipv6_prefix_mask();
+        (a.to_bits() & mask) == (b.to_bits() & mask)
+    }
+
+    fn ipv6_prefix_mask(&self) -> u128 {
+        (!0u128).checked_shl(128 - u32::from(self.ipv6_prefix_len())).unwrap_or(0)
+    }
+
+    /// The fully-qualified name of the records for this target, as Porkbun reports it (i.e., without any `@`).
+    pub fn fqdn(&self) -> String {
+        self.domain.fqdn(self.subdomain())
+    }
+
     /// Checks if the given [record][DNSRecord] matches this [target][Target].
     pub fn matches_record(&self, record: &DNSRecord) -> bool {
-        match self.subdomain() {
-            // '@' as a subdomain refers to the root of the domain; check the whole thing.
-            Some("@") | None => record.name == self.domain,
-            // Could do this by just just allocating "{subdomain}.{domain}" and comparing... but that means allocating!
-            Some(sub) => {
-                record.name.starts_with(sub)
-                    && record.name.ends_with(&self.domain)
-                    && record.name.len() == self.domain.len() + sub.len() + 1
-                    && &record.name[sub.len()..sub.len() + 1] == "."
-            },
+        self.domain.matches(self.subdomain(), &record.name)
+    }
+}
+
+impl StaticRecord {
+    /// The name and TTL of this record.
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    /// The type and content of this record. The content is empty if it comes from a [command][Self::command].
+    pub fn data(&self) -> &RecordData {
+        &self.data
+    }
+
+    /// The program (and its arguments) that prints this record's content, if it isn't given directly.
+    pub fn command(&self) -> Option<&[String]> {
+        self.command.as_deref()
+    }
+
+    /// Checks if the given [record][DNSRecord] has the same name and type as this one. Its content is not compared.
+    pub fn matches_record(&self, record: &DNSRecord) -> bool {
+        record.typ == self.data.typ && self.target.matches_record(record)
+    }
+}
+
+impl SyncDomain {
+    /// Checks whether the given record is protected from being deleted by syncing.
+    ///
+    /// The NS records at the root of the domain are always protected, since those are managed by the registry.
+    pub fn is_protected(&self, record: &DNSRecord) -> bool {
+        if record.typ == RecordType::NS && self.domain.matches(None, &record.name) {
+            return true;
         }
+
+        self.protect.iter().any(|prot| {
+            let type_ok = prot
+                .typ
+                .as_deref()
+                .is_none_or(|typ| typ.eq_ignore_ascii_case(record.typ.as_str()));
+            let name_ok = match &prot.subdomain {
+                None => true,
+                Some(sub) => self.domain.matches(sub.as_ref(), &record.name),
+            };
+            type_ok && name_ok
+        })
     }
 }
 
@@ -216,10 +1578,7 @@ impl AddrMode {
 
     /// Checks whether or not this address mode is required or allowed to fail.
     pub const fn is_required(&self) -> bool {
-        match self {
-            AddrMode::Enabled => true,
-            _ => false,
-        }
+        matches!(self, AddrMode::Enabled)
     }
 }
 
@@ -232,10 +1591,7 @@ impl From<bool> for AddrMode {
     }
 }
 
-/// Formats a [`Target`] as a single domain name that represents how it was specified in the config file.
-///
-/// For example, domains specified with a subdomain of `@` will be printed as `@.example.com`, even though the actual
-/// name that would get sent to Porkbun would just be `example.com`.
+/// Formats a [`Target`] as its fully-qualified name.
 impl Display for Target {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(sub) = self.subdomain() {
@@ -245,14 +1601,73 @@ impl Display for Target {
     }
 }
 
-/// A [`Target`] can be deserialized either as a single string or as a map of options.
-impl<'de> Deserialize<'de> for Target {
+/// Parses an [`ExtraAddress`] as it's written in a target's `extra_addresses`: either an IP address, or
+/// `interface:<name>`.
+impl FromStr for ExtraAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = s.strip_prefix("interface:") {
+            return match name.trim() {
+                "" => Err("interface name may not be empty".to_string()),
+                name => Ok(ExtraAddress::Interface(name.to_string())),
+            };
+        }
+
+        match s.parse() {
+            Ok(addr) => Ok(ExtraAddress::Fixed(addr)),
+            Err(_) => Err(format!("{s:?} is not an IP address or \"interface:<name>\"")),
+        }
+    }
+}
+
+/// Formats a [`StaticRecord`] as its type followed by its name, e.g. `TXT @.example.com`.
+impl Display for StaticRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.data.typ, self.target)
+    }
+}
+
+/// Deserializes a list of [`Target`]s. Each entry can be either a single string or a map of options, and a map with
+/// `subdomains` expands into one target per subdomain.
+fn targets<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Target>, D::Error> {
+    struct TargetEntry(Vec<Target>);
+
+    impl<'de> Deserialize<'de> for TargetEntry {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(TargetVisitor).map(TargetEntry)
+        }
+    }
+
+    let entries = Vec::<TargetEntry>::deserialize(deserializer)?;
+    Ok(entries.into_iter().flat_map(|entry| entry.0).collect())
+}
+
+impl<'de> Deserialize<'de> for StaticRecord {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
         D::Error: de::Error,
     {
-        deserializer.deserialize_any(TargetVisitor)
+        deserializer.deserialize_map(StaticRecordVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpSourceConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(IpSourceVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for ConsensusPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ConsensusPolicyVisitor)
     }
 }
 
@@ -266,66 +1681,286 @@ impl<'de> Deserialize<'de> for AddrMode {
     }
 }
 
+/// Serializes a [`Target`] as a table, the same way it can be written in the config file. Settings which are left at
+/// their defaults are left out.
+impl Serialize for Target {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("domain", &self.domain)?;
+        if let Some(sub) = &self.subdomain {
+            map.serialize_entry("subdomain", sub)?;
+        }
+        map.serialize_entry("ttl", &self.ttl())?;
+        if let Some(suffix) = &self.ipv6_suffix {
+            map.serialize_entry("ipv6_suffix", suffix)?;
+        }
+        if !self.extra_addresses.is_empty() {
+            map.serialize_entry("extra_addresses", &self.extra_addresses)?;
+        }
+        if self.dry_run {
+            map.serialize_entry("dry_run", &true)?;
+        }
+        if !self.notes {
+            map.serialize_entry("notes", &false)?;
+        }
+        if !self.ipv4 {
+            map.serialize_entry("ipv4", &false)?;
+        }
+        if !self.ipv6 {
+            map.serialize_entry("ipv6", &false)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for StaticRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("domain", &self.target.domain)?;
+        if let Some(sub) = &self.target.subdomain {
+            map.serialize_entry("subdomain", sub)?;
+        }
+        map.serialize_entry("ttl", &self.target.ttl())?;
+        map.serialize_entry("type", &self.data.typ)?;
+        match &self.command {
+            Some(command) => map.serialize_entry("command", command)?,
+            None => map.serialize_entry("content", &self.data.content)?,
+        }
+        if let Some(prio) = &self.data.prio {
+            map.serialize_entry("prio", prio)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for ExtraAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ExtraAddress::Fixed(addr) => serializer.collect_str(addr),
+            ExtraAddress::Interface(name) => serializer.collect_str(&format_args!("interface:{name}")),
+        }
+    }
+}
+
+/// Serializes an [`IpSourceConfig`] in its table form, or as a plain string for sources without any settings.
+impl Serialize for IpSourceConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            IpSourceConfig::Porkbun => serializer.serialize_str("porkbun"),
+            IpSourceConfig::Upnp(None) => serializer.serialize_str("upnp"),
+            IpSourceConfig::NatPmp(None) => serializer.serialize_str("natpmp"),
+            IpSourceConfig::Many(sources) => serializer.collect_seq(sources),
+            source => IpSourceTable::from_config(source).serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for ConsensusPolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ConsensusPolicy::Warn => serializer.serialize_str("warn"),
+            ConsensusPolicy::All => serializer.serialize_str("all"),
+            ConsensusPolicy::AtLeast(n) => serializer.serialize_u64(*n as u64),
+        }
+    }
+}
+
+impl Serialize for AddrMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            AddrMode::Enabled => serializer.serialize_bool(true),
+            AddrMode::Disabled => serializer.serialize_bool(false),
+            AddrMode::Try => serializer.serialize_str("try"),
+        }
+    }
+}
+
 struct TargetVisitor;
 
 impl<'de> de::Visitor<'de> for TargetVisitor {
-    type Value = Target;
+    type Value = Vec<Target>;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         formatter.write_str("string or map")
     }
 
     fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
-        Ok(Target::from_domain(v.to_string()))
+        self.visit_string(v.to_string())
     }
 
     fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
-        Ok(Target::from_domain(v))
+        Ok(vec![Target::from_domain(DomainName::parse(&v).map_err(de::Error::custom)?)])
     }
 
     fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        const FIELDS: &[&str] = &[
+            "domain",
+            "subdomain",
+            "subdomains",
+            "ttl",
+            "ipv6_suffix",
+            "extra_addresses",
+            "dry_run",
+            "notes",
+            "ipv4",
+            "ipv6",
+        ];
+
         let mut domain = None;
         let mut subdomain = None;
+        let mut subdomains = None;
         let mut ttl = None;
+        let mut ipv6_suffix = None;
+        let mut extra_addresses = Vec::new();
+        let mut dry_run = false;
+        let mut notes = true;
+        let mut ipv4 = true;
+        let mut ipv6 = true;
 
         while let Some(key) = map.next_key::<Box<str>>()? {
             match &key[..] {
-                "domain" => domain = Some(map.next_value_seed(DomainSegment::DOMAIN)?),
-                "subdomain" => subdomain = Some(map.next_value_seed(DomainSegment::SUBDOMAIN)?),
+                "domain" => domain = Some(map.next_value::<DomainName>()?),
+                "subdomain" => {
+                    subdomain = Some(Subdomain::parse(&map.next_value::<String>()?).map_err(de::Error::custom)?)
+                },
+                "subdomains" => {
+                    let list = map.next_value::<Vec<String>>()?;
+                    let list = list.into_iter().enumerate().map(|(i, sub)| {
+                        Subdomain::parse(&sub).map_err(|err| de::Error::custom(format_args!("subdomains[{i}]: {err}")))
+                    });
+                    subdomains = Some(list.collect::<Result<Vec<_>, _>>()?);
+                },
                 "ttl" => ttl = Some(map.next_value::<u32>()?),
-                other => return Err(de::Error::unknown_field(other, &["domain", "subdomain", "ttl"])),
+                "ipv6_suffix" => {
+                    let str = map.next_value::<String>()?;
+                    let suffix = str.parse::<Ipv6Addr>().map_err(|_| {
+                        de::Error::invalid_value(
+                            de::Unexpected::Str(&str),
+                            &"an IPv6 interface identifier like \"::1\"",
+                        )
+                    })?;
+                    ipv6_suffix = Some(suffix);
+                },
+                "extra_addresses" => {
+                    let list = map.next_value::<Vec<String>>()?;
+                    let list = list.iter().enumerate().map(|(i, str)| {
+                        ExtraAddress::from_str(str)
+                            .map_err(|err| de::Error::custom(format_args!("extra_addresses[{i}]: {err}")))
+                    });
+                    extra_addresses = list.collect::<Result<Vec<_>, _>>()?;
+                },
+                "dry_run" => dry_run = map.next_value::<bool>()?,
+                "notes" => notes = map.next_value::<bool>()?,
+                "ipv4" => ipv4 = map.next_value::<bool>()?,
+                "ipv6" => ipv6 = map.next_value::<bool>()?,
+                other => return Err(de::Error::unknown_field(other, FIELDS)),
             }
         }
 
         let domain = domain.ok_or_else(|| de::Error::missing_field("domain"))?;
-        let subdomain = subdomain.filter(|str| &str[..] != "");
-        let ttl = ttl.unwrap_or(600);
 
-        Ok(Target { domain, subdomain, ttl })
+        let subdomains = match (subdomain, subdomains) {
+            (Some(_), Some(_)) => return Err(de::Error::custom("only one of subdomain and subdomains may be given")),
+            (_, Some(list)) if list.is_empty() => return Err(de::Error::custom("subdomains may not be empty")),
+            (_, Some(list)) => list,
+            (sub, None) => vec![sub.flatten()],
+        };
+
+        let targets = subdomains.into_iter().map(|subdomain| {
+            let target = Target {
+                domain: domain.clone(),
+                subdomain,
+                ttl,
+                ipv6_suffix,
+                ipv6_prefix_len: None,
+                extra_addresses: extra_addresses.clone(),
+                dry_run,
+                notes,
+                ipv4,
+                ipv6,
+            };
+            check_fqdn_len(&target)?;
+            Ok(target)
+        });
+
+        targets.collect()
     }
 }
 
-/// A [`DeserializeSeed`] impl. that deserializes a string while enforcing that it does not contain whitespace. The
-/// seeded version of `Deserialize` is used simply to allow for a better error message.
-struct DomainSegment(&'static str);
+struct StaticRecordVisitor;
 
-impl DomainSegment {
-    pub const DOMAIN: DomainSegment = DomainSegment("domain names");
-    pub const SUBDOMAIN: DomainSegment = DomainSegment("subdomains");
-}
+impl<'de> de::Visitor<'de> for StaticRecordVisitor {
+    type Value = StaticRecord;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map")
+    }
 
-impl<'de> DeserializeSeed<'de> for DomainSegment {
-    type Value = String;
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        const FIELDS: &[&str] = &["domain", "subdomain", "ttl", "type", "content", "command", "prio"];
 
-    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let str = String::deserialize(deserializer)?;
-        match str.chars().find(|c| c.is_whitespace()) {
-            Some(_) => Err(de::Error::custom(format_args!("{} may not contain whitespace", self.0))),
-            None => Ok(str),
+        /// Lets `command` be read with the same rules as the hooks' commands.
+        #[derive(Deserialize)]
+        struct Command(#[serde(deserialize_with = "command")] Option<Vec<String>>);
+
+        let mut domain = None;
+        let mut subdomain = None;
+        let mut ttl = None;
+        let mut typ = None;
+        let mut content = None;
+        let mut command = None;
+        let mut prio = None;
+
+        while let Some(key) = map.next_key::<Box<str>>()? {
+            match &key[..] {
+                "domain" => domain = Some(map.next_value::<DomainName>()?),
+                "subdomain" => subdomain = Subdomain::parse(&map.next_value::<String>()?).map_err(de::Error::custom)?,
+                "ttl" => ttl = Some(map.next_value::<u32>()?),
+                "type" => typ = Some(map.next_value::<RecordType>()?),
+                "content" => content = Some(map.next_value::<String>()?),
+                "command" => command = map.next_value::<Command>()?.0,
+                "prio" => prio = Some(map.next_value::<u32>()?),
+                other => return Err(de::Error::unknown_field(other, FIELDS)),
+            }
+        }
+
+        let domain = domain.ok_or_else(|| de::Error::missing_field("domain"))?;
+        let typ = typ.ok_or_else(|| de::Error::missing_field("type"))?;
+        let content = match (content, &command) {
+            (Some(_), Some(_)) => return Err(de::Error::custom("only one of content and command may be given")),
+            (None, None) => return Err(de::Error::missing_field("content")),
+            (content, _) => content.unwrap_or_default(),
+        };
+
+        // A and AAAA records are what targets are for. Allowing them here would have them fighting over the same
+        // records as the targets.
+        if matches!(typ, RecordType::A | RecordType::AAAA) {
+            return Err(de::Error::custom("A and AAAA records should be configured as targets instead"));
         }
+
+        let target = Target {
+            subdomain,
+            ttl,
+            ..Target::from_domain(domain)
+        };
+        check_fqdn_len(&target)?;
+
+        Ok(StaticRecord {
+            target,
+            data: RecordData { typ, content, prio },
+            command,
+        })
+    }
+}
+
+/// Checks that a target's full name isn't too long for DNS, which can't be done until both halves are known.
+fn check_fqdn_len<E: de::Error>(target: &Target) -> Result<(), E> {
+    let len = target.fqdn().len();
+    match len > MAX_NAME_LEN {
+        true => Err(de::Error::custom(format_args!(
+            "{target} is invalid because it is {len} characters long (the maximum is {MAX_NAME_LEN})"
+        ))),
+        false => Ok(()),
     }
 }
 
@@ -358,3 +1993,347 @@ impl<'de> de::Visitor<'de> for AddrModeVisitor {
         self.visit_str(&v[..])
     }
 }
+
+struct ConsensusPolicyVisitor;
+
+impl<'de> de::Visitor<'de> for ConsensusPolicyVisitor {
+    type Value = ConsensusPolicy;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("\"warn\", \"all\", or a number of sources")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        match v {
+            0 => Err(E::invalid_value(de::Unexpected::Unsigned(v), &"at least 1")),
+            n => Ok(ConsensusPolicy::AtLeast(n as usize)),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Signed(v), &"at least 1")),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        match v {
+            "warn" => Ok(ConsensusPolicy::Warn),
+            "all" => Ok(ConsensusPolicy::All),
+            other => Err(E::invalid_value(de::Unexpected::Str(other), &self)),
+        }
+    }
+}
+
+struct IpSourceVisitor;
+
+/// The table form of `ip_source`, which must have exactly one of its fields set.
+///
+/// It's also how sources are written back out when the config is printed, so anything that isn't set is left out.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct IpSourceTable {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interface: Option<String>,
+    #[serde(default, deserialize_with = "command", skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+    #[serde(default, deserialize_with = "duration", serialize_with = "duration_str")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_age: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stun: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dns: Option<Vec<WhoamiService>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http: Option<HttpUrls>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    upnp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    natpmp: Option<Ipv4Addr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fritzbox: Option<FritzBoxTable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mikrotik: Option<MikrotikTable>,
+    /// Addresses given on the command line, which can't be set from the config file.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    given: Option<Vec<IpAddr>>,
+}
+
+/// The settings for a `mikrotik` source.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct MikrotikTable {
+    url: String,
+    username: String,
+    #[serde(serialize_with = "redacted")]
+    password: String,
+    interface: String,
+    #[serde(default)]
+    insecure: bool,
+}
+
+/// The settings for a `fritzbox` source.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct FritzBoxTable {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(default, serialize_with = "redacted", skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+}
+
+/// The URLs in the table form of an `http` source: either one list for both families, or a list for each.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum HttpUrls {
+    Both(Vec<String>),
+    PerFamily {
+        #[serde(default)]
+        ipv4: Vec<String>,
+        #[serde(default)]
+        ipv6: Vec<String>,
+    },
+}
+
+impl IpSourceTable {
+    /// Turns a source back into the table that it could have been read from.
+    fn from_config(source: &IpSourceConfig) -> Self {
+        let urls = |urls: &[Url]| urls.iter().map(Url::to_string).collect();
+        let empty = Self::default();
+        match source.clone() {
+            IpSourceConfig::Interface(name) => Self { interface: Some(name), ..empty },
+            IpSourceConfig::Command(command) => Self { command: Some(command), ..empty },
+            IpSourceConfig::File { path, max_age } => Self {
+                file: Some(path),
+                max_age,
+                ..empty
+            },
+            IpSourceConfig::Dns(services) => Self { dns: Some(services), ..empty },
+            IpSourceConfig::Http { ipv4, ipv6 } => Self {
+                http: Some(HttpUrls::PerFamily {
+                    ipv4: urls(&ipv4),
+                    ipv6: urls(&ipv6),
+                }),
+                ..empty
+            },
+            IpSourceConfig::Stun(servers) => Self { stun: Some(servers), ..empty },
+            IpSourceConfig::Upnp(url) => Self {
+                upnp: url.map(String::from),
+                ..empty
+            },
+            IpSourceConfig::NatPmp(gateway) => Self { natpmp: gateway, ..empty },
+            IpSourceConfig::FritzBox { url, username, password } => Self {
+                fritzbox: Some(FritzBoxTable {
+                    url: Some(url.into()),
+                    username,
+                    password,
+                }),
+                ..empty
+            },
+            IpSourceConfig::Mikrotik {
+                url,
+                username,
+                password,
+                interface,
+                insecure,
+            } => Self {
+                mikrotik: Some(MikrotikTable {
+                    url: url.into(),
+                    username,
+                    password,
+                    interface,
+                    insecure,
+                }),
+                ..empty
+            },
+            IpSourceConfig::Given(ipv4, ipv6) => Self {
+                given: Some([ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)].into_iter().flatten().collect()),
+                ..empty
+            },
+            IpSourceConfig::Porkbun | IpSourceConfig::Many(_) => empty,
+        }
+    }
+}
+
+impl IpSourceVisitor {
+    fn interface<E: de::Error>(name: &str) -> Result<IpSourceConfig, E> {
+        match name.trim() {
+            "" => Err(E::custom("interface name may not be empty")),
+            name => Ok(IpSourceConfig::Interface(name.to_string())),
+        }
+    }
+
+    fn urls<E: de::Error>(urls: &[impl AsRef<str>]) -> Result<Vec<Url>, E> {
+        (urls.iter())
+            .map(|url| {
+                Url::parse(url.as_ref()).map_err(|err| E::custom(format!("invalid URL {:?}: {err}", url.as_ref())))
+            })
+            .collect()
+    }
+}
+
+impl<'de> de::Visitor<'de> for IpSourceVisitor {
+    type Value = IpSourceConfig;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str(
+            "\"porkbun\", \"dns\", \"http\", \"stun\", \"upnp\", \"natpmp\", \"fritzbox\", \"interface:NAME\", \
+             \"file:PATH\", or a table with `interface`, `command`, `file`, `dns`, `http`, `stun`, `upnp`, `natpmp`, \
+             `fritzbox`, or `mikrotik`; or a list of those",
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        match v.split_once(':') {
+            _ if v == "porkbun" => Ok(IpSourceConfig::Porkbun),
+            _ if v == "dns" => Ok(IpSourceConfig::Dns(vec![WhoamiService::OpenDns, WhoamiService::Cloudflare])),
+            _ if v == "http" => Ok(IpSourceConfig::Http {
+                ipv4: Self::urls(DEFAULT_IPV4_URLS)?,
+                ipv6: Self::urls(DEFAULT_IPV6_URLS)?,
+            }),
+            _ if v == "upnp" => Ok(IpSourceConfig::Upnp(None)),
+            _ if v == "natpmp" => Ok(IpSourceConfig::NatPmp(None)),
+            _ if v == "fritzbox" => Ok(IpSourceConfig::FritzBox {
+                url: Self::urls(&[DEFAULT_FRITZBOX_URL])?.remove(0),
+                username: None,
+                password: None,
+            }),
+            _ if v == "stun" => Ok(IpSourceConfig::Stun(DEFAULT_STUN_SERVERS.iter().map(|s| s.to_string()).collect())),
+            Some(("interface", name)) => Self::interface(name),
+            Some(("file", "")) => Err(E::custom("file path may not be empty")),
+            Some(("file", path)) => Ok(IpSourceConfig::File { path: path.into(), max_age: None }),
+            _ => Err(de::Error::invalid_value(de::Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut sources = Vec::new();
+        while let Some(source) = seq.next_element::<IpSourceConfig>()? {
+            if matches!(source, IpSourceConfig::Many(_)) {
+                return Err(de::Error::custom("lists of IP sources may not be nested"));
+            }
+            sources.push(source);
+        }
+
+        match sources.len() {
+            0 => Err(de::Error::custom("at least one IP source is needed")),
+            1 => Ok(sources.remove(0)),
+            _ => Ok(IpSourceConfig::Many(sources)),
+        }
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let table = IpSourceTable::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        if table.max_age.is_some() && table.file.is_none() {
+            return Err(de::Error::custom("`max_age` can only be used with `file`"));
+        }
+
+        let given = [
+            table.interface.is_some(),
+            table.command.is_some(),
+            table.file.is_some(),
+            table.dns.is_some(),
+            table.http.is_some(),
+            table.stun.is_some(),
+            table.upnp.is_some(),
+            table.natpmp.is_some(),
+            table.fritzbox.is_some(),
+            table.mikrotik.is_some(),
+        ];
+        if given.into_iter().filter(|&given| given).count() != 1 {
+            return Err(de::Error::custom(
+                "expected exactly one of `interface`, `command`, `file`, `dns`, `http`, `stun`, `upnp`, `natpmp`, `fritzbox`, or `mikrotik`",
+            ));
+        }
+
+        if let Some(name) = table.interface {
+            Self::interface(&name)
+        } else if let Some(command) = table.command {
+            Ok(IpSourceConfig::Command(command))
+        } else if let Some(path) = table.file {
+            Ok(IpSourceConfig::File { path, max_age: table.max_age })
+        } else if let Some(services) = table.dns {
+            match services.is_empty() {
+                true => Err(de::Error::custom("at least one DNS service is needed")),
+                false => Ok(IpSourceConfig::Dns(services)),
+            }
+        } else if let Some(urls) = table.http {
+            let (ipv4, ipv6) = match urls {
+                HttpUrls::Both(urls) => (Self::urls(&urls)?, Self::urls(&urls)?),
+                HttpUrls::PerFamily { ipv4, ipv6 } => (Self::urls(&ipv4)?, Self::urls(&ipv6)?),
+            };
+            match ipv4.is_empty() && ipv6.is_empty() {
+                true => Err(de::Error::custom("at least one HTTP service URL is needed")),
+                false => Ok(IpSourceConfig::Http { ipv4, ipv6 }),
+            }
+        } else if let Some(location) = table.upnp {
+            let location = Self::urls(&[location])?.pop();
+            Ok(IpSourceConfig::Upnp(location))
+        } else if let Some(gateway) = table.natpmp {
+            Ok(IpSourceConfig::NatPmp(Some(gateway)))
+        } else if let Some(fritzbox) = table.fritzbox {
+            if fritzbox.username.is_some() && fritzbox.password.is_none() {
+                return Err(de::Error::custom("a Fritz!Box `username` needs a `password`"));
+            }
+            Ok(IpSourceConfig::FritzBox {
+                url: Self::urls(&[fritzbox.url.as_deref().unwrap_or(DEFAULT_FRITZBOX_URL)])?.remove(0),
+                username: fritzbox.username,
+                password: fritzbox.password,
+            })
+        } else if let Some(mikrotik) = table.mikrotik {
+            Ok(IpSourceConfig::Mikrotik {
+                url: Self::urls(&[mikrotik.url])?.remove(0),
+                username: mikrotik.username,
+                password: mikrotik.password,
+                interface: mikrotik.interface,
+                insecure: mikrotik.insecure,
+            })
+        } else {
+            match table.stun.unwrap_or_default() {
+                servers if servers.is_empty() => Err(de::Error::custom("at least one STUN server is needed")),
+                servers => Ok(IpSourceConfig::Stun(servers)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_data_config_path_uses_program_data() {
+        let path = program_data_config_path(Some(OsString::from(r"D:\Data")));
+        assert_eq!(path, PathBuf::from(r"D:\Data").join("porkbun-ddns").join("ddns.toml"));
+    }
+
+    #[test]
+    fn program_data_config_path_falls_back_when_unset() {
+        let expected = PathBuf::from(r"C:\ProgramData").join("porkbun-ddns").join("ddns.toml");
+        assert_eq!(program_data_config_path(None), expected);
+        assert_eq!(program_data_config_path(Some(OsString::new())), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn xdg_config_path_prefers_config_home() {
+        let path = xdg_config_path(Some("/srv/config".into()), Some("/home/me".into()));
+        assert_eq!(path, Some(PathBuf::from("/srv/config/porkbun-ddns/ddns.toml")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn xdg_config_path_falls_back_to_home() {
+        let expected = Some(PathBuf::from("/home/me/.config/porkbun-ddns/ddns.toml"));
+        assert_eq!(xdg_config_path(None, Some("/home/me".into())), expected);
+        // Relative paths are ignored, as the spec says.
+        assert_eq!(xdg_config_path(Some("config".into()), Some("/home/me".into())), expected);
+        assert_eq!(xdg_config_path(None, Some("home/me".into())), None);
+        assert_eq!(xdg_config_path(None, None), None);
+    }
+}
@@ -1,21 +1,111 @@
+use std::fmt::Display;
 use std::io::{self, Write};
+use std::str::FromStr;
 use std::sync::LazyLock;
 
-use chrono::Local;
+use chrono::{Local, SecondsFormat};
 use log::{Level, LevelFilter, Log};
 #[cfg(all(unix, feature = "journald"))]
 use systemd_journal_logger::{JournalLog, connected_to_journal, current_exe_identifier};
 
+use crate::config::LogFormat;
+
 /// A simple logger that writes messages to `stderr`.
 ///
-/// Colour support is automatically provided by the [`anstream`] crate.
+/// Colour support is automatically provided by the [`anstream`] crate (including on Windows consoles). With the
+/// `journald` feature, messages are sent straight to the journal instead whenever it's connected; everywhere else
+/// (e.g., on Windows, or when run outside of systemd), `stderr` is all there is.
 pub struct Logger {
-    filter: LevelFilter,
+    filter: LogFilter,
+    format: LogFormat,
+    /// Whether to show messages from dependencies (e.g., reqwest and hyper) as well as our own.
+    deps: bool,
     timestamps: bool,
     #[cfg(all(unix, feature = "journald"))]
     journald: Option<JournalLog>,
 }
 
+/// The target that the summary at the end of each run is logged under, so that it can still be shown in quiet mode.
+pub const SUMMARY_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::summary");
+
+/// Which log messages get shown, written like `RUST_LOG`: a default level and/or per-module levels, separated by commas.
+///
+/// For example, `info,porkbun_ddns::api=trace` shows everything at `info` and above, plus API traces. A module given
+/// without a level (e.g., just `porkbun_ddns::api`) shows everything from it.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// Module paths with their own levels, most specific first.
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Only warnings and errors, plus the summary at the end of each run.
+    pub fn quiet() -> Self {
+        LogFilter {
+            default: LevelFilter::Warn,
+            modules: vec![(SUMMARY_TARGET.to_string(), LevelFilter::Info)],
+        }
+    }
+
+    /// Makes sure that the default level is at least as verbose as the given one.
+    pub fn at_least(mut self, level: LevelFilter) -> Self {
+        self.default = self.default.max(level);
+        self
+    }
+
+    /// The most verbose level that any module is shown at.
+    pub fn max_level(&self) -> LevelFilter {
+        (self.modules.iter()).map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+
+    /// Finds the level for messages from the given module, using the most specific directive that covers it.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.directive_for(target).map_or(self.default, |(_, level)| *level)
+    }
+
+    /// Finds the most specific directive that covers the given module, if any.
+    fn directive_for(&self, target: &str) -> Option<&(String, LevelFilter)> {
+        let covers = |module: &str| {
+            target
+                .strip_prefix(module)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        };
+
+        self.modules.iter().find(|(module, _)| covers(module))
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_level =
+            |level: &str| LevelFilter::from_str(level.trim()).map_err(|_| format!("invalid log level {level:?}"));
+
+        let mut filter = LogFilter {
+            default: LevelFilter::Info,
+            modules: Vec::new(),
+        };
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => filter.modules.push((module.trim().to_string(), parse_level(level)?)),
+                // A lone word is either the default level or a module that should show everything.
+                None => match parse_level(directive) {
+                    Ok(level) => filter.default = level,
+                    Err(_) => filter.modules.push((directive.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+
+        // Longer (more specific) paths get checked first. Reversing beforehand means that when the same module is given
+        // twice, the last one wins.
+        filter.modules.reverse();
+        filter.modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Ok(filter)
+    }
+}
+
 /// Timestamp format for log output. Format is `Jul 8 2001 14:46:23`.
 static TIMESTAMP_FMT: LazyLock<&'static [chrono::format::Item<'static>]> = LazyLock::new(|| {
     // NB: `LazyLock`'s own docs have a note about how static items don't ever get dropped, so leaking this Vec into a
@@ -67,7 +157,7 @@ mod styles {
 
 impl Logger {
     /// Creates a new logger instance.
-    pub fn new(level: LevelFilter) -> Self {
+    pub fn new(filter: LogFilter, format: LogFormat, deps: bool) -> Self {
         // Default for timestamps is enabled, but they an be disabled by setting an environment variable.
         let mut timestamps = true;
         if crate::get_var("PORKBUN_LOG_NO_TIMESTAMPS").is_ok_and(|v| !v.is_empty()) {
@@ -79,7 +169,9 @@ impl Logger {
         let journald = init_journald().inspect(|_| timestamps = false);
 
         Self {
-            filter: level,
+            filter,
+            format,
+            deps,
             timestamps,
             #[cfg(all(unix, feature = "journald"))]
             journald,
@@ -88,14 +180,20 @@ impl Logger {
 
     /// Initializes this logger.
     pub fn init(self) -> Result<(), log::SetLoggerError> {
-        let level = self.filter;
+        let level = self.filter.max_level();
         log::set_boxed_logger(Box::new(self)).map(|_| log::set_max_level(level))
     }
 
     /// Fallible version of [`Log::log`] to enable the use of `?` within.
     fn try_log(&self, record: &log::Record) -> io::Result<()> {
-        // Only log our own messages; hide implementation details (e.g., reqwest also has logging, hide those)
-        if !record.target().starts_with(env!("CARGO_CRATE_NAME")) {
+        // Only log our own messages; hide implementation details (e.g., reqwest also has logging, hide those), unless
+        // they were specifically asked for.
+        let ours = record.target().starts_with(env!("CARGO_CRATE_NAME"));
+        if !ours && !self.deps && self.filter.directive_for(record.target()).is_none() {
+            return Ok(());
+        }
+
+        if !self.enabled(record.metadata()) {
             return Ok(());
         }
 
@@ -105,13 +203,21 @@ impl Logger {
             return journald.journal_send(record); // Also returns io::Result
         }
 
-        if !self.enabled(record.metadata()) {
-            return Ok(());
-        }
-
         // `anstream`'s versions of `stderr` will automatically handle terminal/VT configuration and NO_COLOR support.
         let mut output = anstream::stderr().lock();
 
+        if self.format == LogFormat::Logfmt {
+            if self.timestamps {
+                write!(output, "time={} ", Local::now().to_rfc3339_opts(SecondsFormat::Secs, false))?;
+            }
+
+            let level = record.level().as_str().to_ascii_lowercase();
+            write!(output, "level={level} target={} ", logfmt_value(record.target()))?;
+            writeln!(output, "msg={}", logfmt_value(record.args()))?;
+            output.flush()?;
+            return Ok(());
+        }
+
         #[rustfmt::skip]
         let (style, tag) = match record.level() {
             Level::Trace => (styles::TRACE, "[trace]"),
@@ -138,7 +244,7 @@ impl Logger {
 
 impl Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= self.filter
+        metadata.level() <= self.filter.level_for(metadata.target())
     }
 
     fn log(&self, record: &log::Record) {
@@ -155,6 +261,29 @@ impl Log for Logger {
     }
 }
 
+/// Formats a value for logfmt output, quoting it if it contains anything that would otherwise break up the line.
+fn logfmt_value(value: impl Display) -> String {
+    let value = value.to_string();
+    if !value.is_empty() && !value.contains(|c: char| c == '"' || c == '=' || c.is_whitespace() || c.is_control()) {
+        return value;
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 #[cfg(all(unix, feature = "journald"))]
 fn init_journald() -> Option<JournalLog> {
     if connected_to_journal() {
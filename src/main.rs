@@ -1,28 +1,149 @@
+/// Formatting helper for log and error messages
+macro_rules! pluralize {
+    ($single:expr, $plural:expr, $count:expr) => {
+        if $count == 1 { $single } else { $plural }
+    };
+}
+
 mod api;
+mod audit;
+mod backup;
+mod commands;
 mod config;
 mod logging;
+mod metrics;
+#[cfg(feature = "mqtt")] mod mqtt;
+mod names;
+mod netif;
+mod notify;
+mod plan;
+mod precheck;
+mod providers;
+mod report;
+mod secrets;
+mod server;
+mod shutdown;
+mod sources;
+mod state;
+mod status;
+mod verify;
+mod zonefile;
 
-use std::collections::{BTreeMap, HashMap};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::process::ExitCode;
+use std::path::{Path, PathBuf};
+use std::process::{ExitCode, Stdio};
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use clap::Parser;
 use eyre::{WrapErr, eyre};
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
 
-use self::api::{DNSRecord, IpAddrExt, PorkbunClient};
-use self::config::{Args, Config, Target};
-use self::logging::Logger;
+use self::api::{DNSRecord, IpAddrExt, PorkbunClient, RecordData, RecordType};
+use self::audit::AuditLog;
+use self::backup::Backups;
+use self::config::{
+    Args,
+    Command,
+    Config,
+    ExtraAddress,
+    MultipleRecords,
+    NotifyEvents,
+    OutputFormat,
+    RecordLookup,
+    StaticRecord,
+    SyncDomain,
+    Target,
+};
+use self::logging::{LogFilter, Logger, SUMMARY_TARGET};
+use self::metrics::Metrics;
+#[cfg(feature = "mqtt")]
+use self::mqtt::Mqtt;
+use self::names::DomainName;
+use self::notify::{Discord, Event, Hook, Notifier, Ntfy, Webhook};
+use self::plan::Change;
+use self::precheck::Precheck;
+use self::providers::{DuckDns, Gandi, Hetzner, Provider};
+use self::report::{Report, TargetAction, TargetResult};
+use self::secrets::Secrets;
+use self::shutdown::Shutdown;
+use self::sources::IpSource;
+use self::state::State;
+use self::status::Status;
+use self::verify::Verifier;
 
-/// Formatting helper for log and error messages
-macro_rules! pluralize {
-    ($single:expr, $plural:expr, $count:expr) => {
-        if $count == 1 { $single } else { $plural }
-    };
-}
+/// How long a static record's program can run for before it's killed.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[tokio::main(flavor = "current_thread")]
 pub async fn main() -> ExitCode {
-    let app = match App::init().await {
+    let mut args = Args::parse();
+    let command = args.command.take();
+
+    // Planning is just a dry run that saves its results.
+    if matches!(command, Some(Command::Plan(_))) {
+        args.dry_run = true;
+    }
+
+    if let Some(Command::Apply(apply_args)) = &command {
+        args.no_detect = apply_args.no_detect;
+    }
+
+    let filter = match (args.quiet, args.verbose) {
+        (true, _) => LogFilter::quiet(),
+        (false, 0) => args.log_level.clone(),
+        (false, 1) => args.log_level.clone().at_least(log::LevelFilter::Debug),
+        (false, _) => args.log_level.clone().at_least(log::LevelFilter::Trace),
+    };
+
+    Logger::new(filter, args.log_format, args.log_deps)
+        .init()
+        .expect("no other logger should have been set yet");
+
+    // There may not even be a config file yet.
+    if let Some(Command::GenerateConfig(gen_args)) = command {
+        return match commands::generate_config(gen_args).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                log::error!("{err:#}");
+                ExitCode::FAILURE
+            },
+        };
+    }
+
+    // Nor does installing a service need anything from the config file itself, just where it is.
+    if let Some(Command::Service(service_args)) = command {
+        return match commands::service(service_args, &args.config).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                log::error!("{err:#}");
+                ExitCode::FAILURE
+            },
+        };
+    }
+
+    // Setting up an `App` can already make network requests (e.g., for `auto_discover`), which checking (or printing)
+    // the config must not do.
+    if let Some(command @ (Command::Check | Command::PrintConfig | Command::History)) = &command {
+        let res = match command {
+            Command::PrintConfig => commands::print_config(args).await,
+            Command::History => commands::history(args).await,
+            _ => commands::check(args).await,
+        };
+        return match res {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                log::error!("{err:#}");
+                ExitCode::FAILURE
+            },
+        };
+    }
+
+    let app = match App::init(args).await {
         Ok(app) => app,
         Err(err) => {
             log::error!("{err:#}");
@@ -30,31 +151,90 @@ pub async fn main() -> ExitCode {
         },
     };
 
+    let res = match command {
+        // Only updates are stopped gracefully; anything else (e.g., waiting for confirmation) can just be interrupted.
+        None => return app.shutdown.guard(update(&app)).await,
+        Some(Command::Prune(args)) => commands::prune(&app, args).await,
+        Some(Command::Ns(args)) => commands::ns(&app, args).await,
+        Some(Command::Acme(args)) => commands::acme(&app, args).await,
+        Some(Command::Import(args)) => commands::import(&app, args).await,
+        Some(Command::Restore(args)) => commands::restore(&app, args).await,
+        Some(Command::Plan(args)) => commands::plan(&app, args).await,
+        Some(Command::Apply(args)) => commands::apply(&app, args).await,
+        Some(Command::TestAuth) => commands::test_auth(&app).await,
+        Some(Command::Status) => commands::status(&app).await,
+        Some(Command::ListRecords(args)) => commands::list_records(&app, args).await,
+        Some(Command::DeleteRecord(args)) => commands::delete_record(&app, args).await,
+        Some(Command::Daemon(args)) => app.shutdown.guard(commands::daemon(&app, args)).await,
+        Some(Command::ExternalDns(args)) => commands::external_dns(&app, args).await,
+        Some(
+            Command::Check | Command::PrintConfig | Command::History | Command::GenerateConfig(_) | Command::Service(_),
+        ) => {
+            unreachable!("handled before initialization")
+        },
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            log::error!("{err:#}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// Runs the main update process: the default command when no subcommand is given.
+async fn update(app: &App) -> ExitCode {
     log::info!("Starting...");
+    app.reset().await;
 
     let (ipv4, ipv6) = match app.get_addresses().await {
         // `get_addresses` will return two `None`s only if both are disabled. Otherwise, at least one is enabled,
         // meaning the only other option is for an error to have occurred or for at least one of them to be valid.
-        Ok((None, None)) => {
+        Ok((None, None)) if app.records.is_empty() && app.sync.is_empty() => {
             log::info!("Both IPv4 and IPv6 are disabled. Nothing to do.");
             return ExitCode::SUCCESS;
         },
         Ok(addrs) => addrs,
         Err(err) => {
-            log::error!(
+            let err = format!(
                 "Failed to determine current IP {addresses}: {err:#}",
                 addresses = pluralize!("address", "addresses", app.mode_count()),
             );
+            log::error!("{err}");
+            app.finish(Report {
+                error: Some(err),
+                ..app.report(None, None, 1)
+            })
+            .await;
             return ExitCode::FAILURE;
         },
     };
 
-    if app.targets.len() == 0 {
+    if app.targets.is_empty() && app.records.is_empty() && app.sync.is_empty() && app.providers.is_empty() {
         log::info!("Zero targets specified. Nothing to do.");
         return ExitCode::SUCCESS;
     }
 
-    match app.run(ipv4, ipv6).await {
+    let mut err_count = app.run(ipv4, ipv6).await;
+    if !app.finish(app.report(ipv4, ipv6, err_count)).await {
+        err_count += 1;
+    }
+
+    match app.output {
+        OutputFormat::Json => match serde_json::to_string_pretty(&app.report(ipv4, ipv6, err_count)) {
+            Ok(json) => println!("{json}"),
+            Err(err) => log::error!("Failed to print results: {err}"),
+        },
+        OutputFormat::Text if app.dry_run => {
+            if let Err(err) = plan::print_diff(&app.changes.lock().unwrap()) {
+                log::error!("Failed to print changes: {err}");
+            }
+        },
+        OutputFormat::Text => {},
+    }
+
+    match err_count {
         0 => {
             log::info!("Done.");
             ExitCode::SUCCESS
@@ -80,17 +260,148 @@ fn get_var(key: &str) -> Result<String, std::env::VarError> {
     std::env::var(key)
 }
 
+/// Loads the Porkbun API key and secret key, from wherever the config says they are.
+async fn load_keys(config: &Config) -> eyre::Result<(String, String)> {
+    log::trace!("Loading API keys");
+    let mut api_key = load_key("PORKBUN_API_KEY", config.api_key_file.as_deref()).await?;
+    let mut secret_key = load_key("PORKBUN_SECRET_KEY", config.secret_key_file.as_deref()).await?;
+
+    // The secrets file is only decrypted if it's actually needed.
+    if (api_key.is_none() || secret_key.is_none())
+        && let Some(path) = &config.secrets_file
+    {
+        let secrets = Secrets::decrypt(path, config.age_identity.as_deref()).await?;
+        api_key = api_key.or(secrets.api_key);
+        secret_key = secret_key.or(secrets.secret_key);
+    }
+
+    let missing = |var: &str| eyre!("Failed to get {var} from environment (and no file was given to read it from)");
+    let api_key = api_key.ok_or_else(|| missing("PORKBUN_API_KEY"))?;
+    let secret_key = secret_key.ok_or_else(|| missing("PORKBUN_SECRET_KEY"))?;
+    Ok((api_key, secret_key))
+}
+
+/// Sets up every other DNS provider given in the config, taking their settings out of it.
+fn init_providers(config: &mut Config) -> eyre::Result<Vec<Box<dyn Provider>>> {
+    let mut providers = Vec::<Box<dyn Provider>>::new();
+    if let Some(duckdns) = config.duckdns.take() {
+        providers.push(Box::new(DuckDns::new(duckdns)));
+    }
+    if let Some(gandi) = config.gandi.take() {
+        providers.push(Box::new(Gandi::new(gandi)?));
+    }
+    if let Some(hetzner) = config.hetzner.take() {
+        providers.push(Box::new(Hetzner::new(hetzner)?));
+    }
+    Ok(providers)
+}
+
+/// Sets up every notifier that is configured in the `[notify]` section (and the `on_change` and `on_failure` hooks),
+/// taking their settings out of the config.
+fn init_notifiers(config: &mut Config) -> eyre::Result<Vec<Box<dyn Notifier>>> {
+    let mut notifiers = Vec::<Box<dyn Notifier>>::new();
+    if let Some(command) = config.on_change.take() {
+        notifiers.push(Box::new(Hook::new("on_change", command, NotifyEvents::Change)));
+    }
+    if let Some(command) = config.on_failure.take() {
+        notifiers.push(Box::new(Hook::new("on_failure", command, NotifyEvents::Failure)));
+    }
+
+    let Some(notify) = config.notify.take() else {
+        return Ok(notifiers);
+    };
+
+    if let Some(webhook) = notify.webhook {
+        notifiers.push(Box::new(Webhook::new(webhook)?));
+    }
+    if let Some(ntfy) = notify.ntfy {
+        notifiers.push(Box::new(Ntfy::new(ntfy)?));
+    }
+    if let Some(discord) = notify.discord {
+        notifiers.push(Box::new(Discord::new(discord)?));
+    }
+    Ok(notifiers)
+}
+
+/// Gets one of the API keys from the environment variable `var`.
+///
+/// If that isn't set, the key is read from a file instead: either the one named by `{var}_FILE`, or the one given in the
+/// config file. This is how secrets are usually passed to Docker and Kubernetes containers. If neither is given, the key
+/// may still be in the config's `secrets_file`, so `None` is returned rather than an error.
+async fn load_key(var: &str, config_path: Option<&Path>) -> eyre::Result<Option<String>> {
+    if let Ok(key) = get_var(var) {
+        return Ok(Some(key));
+    }
+
+    let path = match get_var(&format!("{var}_FILE")) {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => match config_path {
+            Some(path) => path.to_path_buf(),
+            None => return Ok(None),
+        },
+    };
+
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .wrap_err_with(|| format!("Failed to read {var} from {}", path.display()))?;
+
+    // Files almost always end with a newline, which is certainly not part of the key.
+    match text.trim() {
+        "" => Err(eyre!("Failed to read {var} from {}: file is empty", path.display())),
+        key => Ok(Some(key.to_string())),
+    }
+}
+
 /// The main application instance.
 ///
 /// Having this be a separate struct alleviates needing to pass so many parameters around.
 struct App {
-    client: PorkbunClient,
+    client: Rc<PorkbunClient>,
     dry_run: bool,
+    force: bool,
+    /// Whether to keep quiet about runs where nothing happened.
+    quiet: bool,
     ipv4_enabled: bool,
     ipv6_enabled: bool,
     ipv4_required: bool,
     ipv6_required: bool,
+    /// Whether to delete the records that this program created for any address family that is now disabled.
+    cleanup_disabled: bool,
+    allow_private_ips: bool,
+    /// Where to find the current addresses.
+    ip_source: Box<dyn IpSource>,
+    record_lookup: RecordLookup,
+    /// Checks records against Porkbun's nameservers before using the API, if enabled.
+    precheck: Option<Precheck>,
+    multiple_records: MultipleRecords,
     targets: Vec<Target>,
+    records: Vec<StaticRecord>,
+    sync: Vec<SyncDomain>,
+    backups: Backups,
+    state_file: Option<PathBuf>,
+    /// The last addresses pushed to each target, loaded from the state file at startup and updated as we go.
+    state: Mutex<State>,
+    refresh_every: Option<Duration>,
+    /// How many errors a run can hit before the rest of it is abandoned.
+    max_errors: Option<usize>,
+    /// Checks that changed records have propagated, if enabled.
+    verifier: Option<Verifier>,
+    providers: Vec<Box<dyn Provider>>,
+    notifiers: Vec<Box<dyn Notifier>>,
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<Mqtt>,
+    /// Every change made to Porkbun records so far (or that would have been made, in a dry run).
+    changes: Mutex<Vec<Change>>,
+    output: OutputFormat,
+    summary_file: Option<PathBuf>,
+    /// What happened to each target's records, for the report at the end of the run.
+    results: Mutex<Vec<TargetResult>>,
+    /// Counts of what has happened across every run so far, for when running as a daemon.
+    metrics: Mutex<Metrics>,
+    /// The latest known state of every target, for the daemon's HTTP API.
+    status: Mutex<Status>,
+    /// Whether the process has been asked to stop, in which case the current run gets wrapped up early.
+    shutdown: Shutdown,
 }
 
 impl App {
@@ -98,97 +409,255 @@ impl App {
     pub const fn mode_count(&self) -> usize {
         self.ipv4_enabled as usize + self.ipv6_enabled as usize
     }
+
+    /// Backs up all of a domain's records (if enabled) before any of them are changed. Does nothing in a dry run.
+    pub async fn backup(&self, domain: &str) -> eyre::Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+        self.backups.ensure(&self.client, domain).await
+    }
+
+    /// Whether changes to the given target's records should only be logged, either because this whole run is a dry run
+    /// or because the target is set to be.
+    fn is_dry_run(&self, target: &Target) -> bool {
+        self.dry_run || target.dry_run()
+    }
+
+    /// Keeps track of a change that was just made to a record (or that would have been, in a dry run).
+    fn record_change(&self, change: Change) {
+        self.changes.lock().unwrap().push(change);
+    }
+
+    /// Keeps track of what happened to one of a target's records, once it has been dealt with. Must be called after any
+    /// changes to the record have been recorded.
+    fn record_result(&self, target: &Target, addr: IpAddr, error: Option<String>) {
+        let result = TargetResult::new(target, addr, &self.changes.lock().unwrap(), error);
+        self.results.lock().unwrap().push(result);
+    }
+
+    /// Forgets everything that happened during the last run, so that another one can be started.
+    async fn reset(&self) {
+        self.changes.lock().unwrap().clear();
+        self.results.lock().unwrap().clear();
+        self.backups.reset().await;
+        if let Some(audit) = self.client.audit_log() {
+            audit.start_run();
+        }
+    }
+
+    /// Counts a finished run towards the metrics and status, sends notifications about it, and writes a report of it to the summary
+    /// file, if there is one. Returns `false` if writing the summary failed.
+    async fn finish(&self, report: Report) -> bool {
+        self.metrics.lock().unwrap().record(&report);
+        self.status.lock().unwrap().record(&report);
+        self.notify(&Event::from_report(&report)).await;
+        #[cfg(feature = "mqtt")]
+        self.publish(&report).await;
+
+        let Some(path) = &self.summary_file else {
+            return true;
+        };
+
+        match report.save(path).await {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Failed to write summary to {}: {err:#}", path.display());
+                false
+            },
+        }
+    }
+
+    /// Puts together a report of everything that happened during the run.
+    fn report(&self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>, errors: usize) -> Report {
+        Report {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            dry_run: self.dry_run,
+            ipv4,
+            ipv6,
+            targets: self.results.lock().unwrap().clone(),
+            changes: self.changes.lock().unwrap().clone(),
+            errors,
+            error: None,
+        }
+    }
+
+    /// Publishes a run's results to MQTT, if it's configured. Just like notifications, failures are only logged, and
+    /// nothing is published in a dry run.
+    #[cfg(feature = "mqtt")]
+    async fn publish(&self, report: &Report) {
+        let Some(mqtt) = &self.mqtt else {
+            return;
+        };
+
+        if self.dry_run {
+            log::debug!("Skipping publishing to MQTT (dry run).");
+        } else if let Err(err) = mqtt.publish(report).await {
+            log::error!("MQTT: Failed to publish results: {err:#}");
+        }
+    }
+
+    /// Sends every event to every notifier that wants it. Failures are logged, but not counted as errors, since the
+    /// records themselves were still updated. Nothing is sent in a dry run.
+    async fn notify(&self, events: &[Event]) {
+        if self.notifiers.is_empty() || events.is_empty() {
+            return;
+        }
+
+        if self.dry_run {
+            log::debug!(
+                "Skipping {n} {notifications} (dry run).",
+                n = events.len(),
+                notifications = pluralize!("notification", "notifications", events.len()),
+            );
+            return;
+        }
+
+        let tasks = (self.notifiers.iter())
+            .flat_map(|notifier| events.iter().map(move |event| (notifier, event)))
+            .filter(|(notifier, event)| notifier.events().includes(event.event))
+            .map(|(notifier, event)| async move {
+                if let Err(err) = notifier.notify(event).await {
+                    log::error!("{}: Failed to send notification: {err:#}", notifier.name());
+                }
+            });
+
+        futures::future::join_all(tasks).await;
+    }
 }
 
 impl App {
     /// Initializes the application instance.
-    pub async fn init() -> eyre::Result<Self> {
-        let args = Args::parse();
+    pub async fn init(args: Args) -> eyre::Result<Self> {
         let dry_run = args.dry_run;
-        Logger::new(args.log_level)
-            .init()
-            .expect("no other logger should have been set yet");
-        let config = Config::from_args(args).await?;
+        let force = args.force;
+        let quiet = args.quiet;
+        let output = args.output;
+        let summary_file = args.summary_file.clone();
+        let no_detect = args.no_detect;
+        let mut config = Config::from_args(args).await?;
+
+        let (api_key, secret_key) = load_keys(&config).await?;
+        let providers = init_providers(&mut config)?;
+        let notifiers = init_notifiers(&mut config)?;
+        #[cfg(feature = "mqtt")]
+        let mqtt = config.mqtt.take().map(Mqtt::new).transpose()?;
+        let audit = config.audit_log.map(AuditLog::new);
+        let client = Rc::new(PorkbunClient::new(
+            api_key,
+            secret_key,
+            config.notes,
+            config.notes_template,
+            config.notes_timestamp,
+            audit,
+        ));
+
+        let mut targets = config.targets;
+        if config.auto_discover && !no_detect {
+            discover_targets(&client, &mut targets, config.default_ttl)
+                .await
+                .wrap_err("Failed to discover domains on account")?;
+        }
 
-        log::trace!("Loading API keys from environment");
-        let api_key = get_var("PORKBUN_API_KEY").wrap_err("Failed to get PORKBUN_API_KEY from environment")?;
-        let secret_key = get_var("PORKBUN_SECRET_KEY").wrap_err("Failed to get PORKBUN_SECRET_KEY from environment")?;
-        let client = PorkbunClient::new(api_key, secret_key);
+        let state = match &config.state_file {
+            Some(path) => State::load(path).await,
+            None => State::default(),
+        };
+
+        let ip_source = sources::from_config(config.ip_source, config.ip_fallback, config.ip_consensus, &client);
+        let verifier = config.verify.map(|verify| Verifier::new(client.clone(), verify));
 
         log::trace!("Initialization successful.");
         Ok(App {
             client,
             dry_run,
+            force,
+            quiet,
             ipv4_enabled: config.ipv4.is_enabled(),
             ipv6_enabled: config.ipv6.is_enabled(),
             ipv4_required: config.ipv4.is_required(),
             ipv6_required: config.ipv6.is_required(),
-            targets: config.targets,
+            cleanup_disabled: config.cleanup_disabled,
+            allow_private_ips: config.allow_private_ips,
+            ip_source,
+            record_lookup: config.record_lookup,
+            precheck: config.dns_precheck.then(Precheck::default),
+            multiple_records: config.multiple_records,
+            targets,
+            records: config.records,
+            sync: config.sync,
+            backups: Backups::new(config.backup_dir),
+            state_file: config.state_file,
+            state: Mutex::new(state),
+            refresh_every: config.refresh_every,
+            max_errors: config.max_errors,
+            verifier,
+            providers,
+            notifiers,
+            #[cfg(feature = "mqtt")]
+            mqtt,
+            changes: Mutex::new(Vec::new()),
+            output,
+            summary_file,
+            results: Mutex::new(Vec::new()),
+            metrics: Mutex::new(Metrics::default()),
+            status: Mutex::new(Status::new(dry_run)),
+            shutdown: Shutdown::default(),
         })
     }
 
     /// Fetches IPv4 and IPv6 addresses for the current system.
     pub async fn get_addresses(&self) -> eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)> {
-        let num_enabled = self.mode_count();
-        log::debug!(
-            "Pinging Porkbun API for current IP {addresses}...",
-            addresses = pluralize!("address", "addresses", num_enabled),
-        );
-
-        if num_enabled == 0 {
+        if self.mode_count() == 0 {
             return Ok((None, None));
         }
 
-        let mut ipv4 = None;
-        let mut ipv6 = None;
+        let (ipv4, ipv6) = self.source_addresses(&*self.ip_source).await?;
 
-        // Ping the base `/ping` endpoint first: it returns either IPv6 or IPv4.
-        match self.client.ping().await? {
-            IpAddr::V4(addr) => {
-                if self.ipv4_enabled {
-                    log::debug!("Found current IPv4 address: {addr}");
-                    ipv4 = Some(addr);
+        // Publishing an address that can't be reached from outside almost always means that the wrong address was
+        // detected, so it's better to fail loudly than to break every record (unless that's actually what's wanted).
+        let addrs = [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)];
+        for addr in addrs.into_iter().flatten() {
+            if let Some(kind) = addr.private_kind() {
+                if !self.allow_private_ips {
+                    return Err(eyre!(
+                        "Detected address {addr} is {kind} address; refusing to publish it (see allow_private_ips)"
+                    ));
                 }
+                log::debug!("Detected address {addr} is {kind} address, but private addresses are allowed.");
+            }
+        }
 
-                // The base `/ping` endpoint *always* returns IPv6 when possible (AFAIK). If it gives us IPv4,
-                // there's no way for us to get an IPv6. We don't even need to try.
-                if self.ipv6_enabled {
-                    // If IPv6 is set to hard-error mode, or if IPv6 is the only one enabled, this is an error.
-                    if self.ipv6_required || !self.ipv4_enabled {
-                        return Err(eyre!("Tried to get IPv6 address from Porkbun API, but only got IPv4"));
-                    }
+        Ok((ipv4, ipv6))
+    }
 
-                    // Otherwise, we can just log and continue.
-                    log::debug!("Found current IPv6 address: none.");
-                }
-            },
-            IpAddr::V6(addr) => {
-                if self.ipv6_enabled {
-                    log::debug!("Found current IPv6 address: {addr}");
-                    ipv6 = Some(addr);
-                }
+    /// Fetches addresses from the configured [`IpSource`]. A missing address is only an error if that address is
+    /// required, or if it's the only kind enabled.
+    async fn source_addresses(&self, source: &dyn IpSource) -> eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)> {
+        log::debug!("Reading current IP addresses from {}...", source.name());
+        let (ipv4, ipv6) = source.addresses(self.ipv4_enabled, self.ipv6_enabled).await?;
 
-                if self.ipv4_enabled {
-                    log::debug!("Pinging again for IPv4 address...");
-                    match self.client.ping_v4().await {
-                        Ok(addr) => {
-                            log::debug!("Found current IPv4 address: {addr}");
-                            ipv4 = Some(addr);
-                        },
-                        // Failing to fetch an IPv4 address is an error either if (a) IPv4 is required or (b) IPv4 is
-                        // the only one enabled.
-                        Err(e) if self.ipv4_required || !self.ipv6_enabled => {
-                            // I don't actually know what happens when you ping Porkbun from somewhere without an IPv4
-                            // address. Is that even possible yet? Has anywhere actually fully gotten rid of IPv4?
-                            return Err(e.wrap_err("Tried to get IPv4 address from Porkbun API, but only got IPv6"))?;
-                        },
-                        Err(_) => log::debug!("Found current IPv4 address: none."),
-                    }
-                }
-            },
+        if self.ipv4_enabled {
+            match ipv4 {
+                Some(addr) => log::debug!("Found current IPv4 address: {addr}"),
+                None if self.ipv4_required || !self.ipv6_enabled => {
+                    return Err(eyre!("Found no IPv4 address from {}", source.name()));
+                },
+                None => log::debug!("Found current IPv4 address: none."),
+            }
         }
 
-        Ok((ipv4, ipv6))
+        if self.ipv6_enabled {
+            match ipv6 {
+                Some(addr) => log::debug!("Found current IPv6 address: {addr}"),
+                None if self.ipv6_required || !self.ipv4_enabled => {
+                    return Err(eyre!("Found no IPv6 address from {}", source.name()));
+                },
+                None => log::debug!("Found current IPv6 address: none."),
+            }
+        }
+
+        Ok((ipv4.filter(|_| self.ipv4_enabled), ipv6.filter(|_| self.ipv6_enabled)))
     }
 
     /// Run the application.
@@ -199,36 +668,416 @@ impl App {
     pub async fn run(&self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> usize {
         if self.dry_run {
             log::warn!("dry_run is enabled: no create/edit requests will be sent through to Porkbun.");
+        } else {
+            for target in self.targets.iter().filter(|target| target.dry_run()) {
+                log::warn!("{target}: dry_run is enabled for this target: its records will not actually be changed.");
+            }
         }
 
         // Step 1: Fetch existing records for all domains
         // =============================================================================================================
 
-        // First build a unique list of root domain names. Then we can send each one on its own task to get records.
-        let mut current_records = HashMap::<&str, Vec<DNSRecord>>::new();
+        // Targets which already have these exact addresses (as of the last run) don't need to be looked at at all.
+        let targets = self
+            .targets
+            .iter()
+            .filter(|target| {
+                let (ipv4, ipv6) = target.families(ipv4, ipv6);
+                let ipv6 = ipv6.map(|addr| target.ipv6_for(addr));
+                // Round-robin targets might have extra addresses that changed, which the state file can't tell. Targets
+                // that still have records of a disabled family need those cleaned up.
+                let state = self.state.lock().unwrap();
+                let current = !self.force
+                    && !target.is_round_robin()
+                    && !(self.cleanup_types(target)).any(|typ| state.remembers(target, typ == RecordType::AAAA))
+                    && state.is_current(target, ipv4, ipv6, self.refresh_every);
+                drop(state);
+                if current {
+                    log::debug!("{target}: Addresses unchanged since last update. Skipping.");
+                    for addr in [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)].into_iter().flatten() {
+                        self.record_result(target, target.address_for(addr), None);
+                    }
+                }
+                !current
+            })
+            .collect::<Vec<_>>();
+
+        // If every target is already up to date, none of their records need looking up. Static records and synced
+        // domains are still checked on every run, though, since the config (or a record's command) might have changed
+        // even though no address has.
+        if targets.is_empty() && !self.targets.is_empty() {
+            log::info!("Every target is already up to date.");
+        }
+
+        // Targets whose records already hold the right addresses (according to Porkbun's own nameservers) don't need
+        // to be looked up through the API either.
+        let targets = match &self.precheck {
+            Some(precheck) if !self.force && !targets.is_empty() => {
+                self.precheck_targets(precheck, targets, ipv4, ipv6).await
+            },
+            _ => targets,
+        };
+        let (current_records, mut err_count) = match self.record_lookup {
+            RecordLookup::Zone => self.fetch_zone_records(&targets).await,
+            RecordLookup::Target => {
+                let cleanup = |typ| targets.iter().any(|target| self.cleanup_types(target).any(|t| t == typ));
+                let ipv4 = ipv4.is_some() || cleanup(RecordType::A);
+                let ipv6 = ipv6.is_some() || cleanup(RecordType::AAAA);
+                self.fetch_target_records(&targets, ipv4, ipv6).await
+            },
+            RecordLookup::Skip => (HashMap::new(), 0),
+        };
 
-        for target in &self.targets {
-            // Start each one off with an empty (read: non-allocating) vec that can get extended by each task.
-            let domain = target.domain();
-            current_records.entry(domain).or_insert_with(Vec::new);
+        if self.should_stop(err_count) {
+            return self.wrap_up(err_count).await;
         }
 
+        // Step 2: Actually process all of the targets
+        // =============================================================================================================
+
+        let target_tasks = targets.iter().copied().filter_map(|target| {
+            let (ipv4, ipv6) = target.families(ipv4, ipv6);
+            // When lookups are skipped, there are no records to check. Otherwise, only process targets whose records
+            // we actually have.
+            let records = if self.record_lookup == RecordLookup::Skip {
+                None
+            } else {
+                match current_records.get(&self.records_key(target)[..]) {
+                    Some(records) => Some(&records[..]),
+                    None => {
+                        // Target's records might be missing if we previously failed to fetch them. Error would've
+                        // already been logged in that case, so we don't need to report another one.
+                        log::warn!("{target}: Skipped due to missing DNS records.");
+                        for addr in [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)].into_iter().flatten() {
+                            let error = "Existing DNS records could not be fetched".to_string();
+                            self.record_result(target, target.address_for(addr), Some(error));
+                        }
+                        // Skip over this target in the outer `filter_map`.
+                        return None;
+                    },
+                }
+            };
+
+            // Convert an iterator of `Option<IpAddr>` into an iterator of `Option<impl Future>`, which gets filtered
+            // down into an iterator of `impl Future`.
+            let addrs = [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)];
+            let tasks = addrs.into_iter().filter_map(move |addr| {
+                addr.map(async move |addr| -> Result<(), ()> {
+                    let addr = target.address_for(addr);
+                    let start = Instant::now();
+                    let res = match records {
+                        Some(records) => self.handle_target(target, records, addr).await,
+                        None => self.handle_target_blind(target, addr).await,
+                    };
+                    log::debug!("{target}: Finished {} record in {:.2?}.", addr.dns_type(), start.elapsed());
+
+                    if res.is_ok() && !self.is_dry_run(target) {
+                        self.state.lock().unwrap().set(target, addr);
+                    }
+
+                    let error = res.as_ref().err().map(|err| format!("{err:#}"));
+                    let mut result = TargetResult::new(target, addr, &self.changes.lock().unwrap(), error);
+                    if let Some(verifier) = &self.verifier
+                        && !self.is_dry_run(target)
+                        && !self.shutdown.is_requested()
+                        && matches!(result.action, TargetAction::Created | TargetAction::Updated)
+                    {
+                        result.verified = Some(verifier.verify(target, addr).await);
+                    }
+                    self.results.lock().unwrap().push(result);
+
+                    res.map_err(|err| log::error!("{target}: {err:#}")) // log and map to () at the same time
+                })
+            });
+
+            // Return an `Iterator<impl Future>` to the outer `filter_map`, giving `Iter<Iter<impl Future>>`, which then
+            // gets flattened down into one final iterator of futures.
+            Some(tasks)
+        });
+
+        self.join_until_limit(target_tasks.flatten(), &mut err_count).await;
+        if self.should_stop(err_count) {
+            return self.wrap_up(err_count).await;
+        }
+
+        // Records for address families that have since been disabled would otherwise keep pointing at stale addresses.
+        let cleanup_tasks = targets.iter().copied().flat_map(|target| {
+            let records = match self.record_lookup {
+                RecordLookup::Skip => Some(None),
+                // Targets whose records couldn't be fetched were already skipped (with a warning) above.
+                _ => current_records
+                    .get(&self.records_key(target)[..])
+                    .map(|records| Some(&records[..])),
+            };
+
+            let types = self.cleanup_types(target).filter(move |_| records.is_some());
+            types.map(move |typ| async move {
+                let res = self.cleanup_target(target, records.flatten(), typ).await;
+                res.map_err(|err| log::error!("{target}: {err:#}"))
+            })
+        });
+
+        self.join_until_limit(cleanup_tasks, &mut err_count).await;
+        if self.should_stop(err_count) {
+            return self.wrap_up(err_count).await;
+        }
+
+        // Step 3: Process any static records
+        // =============================================================================================================
+
+        // Records whose content is printed by a program need that program run before anything can be compared.
+        let contents = futures::future::join_all(self.records.iter().map(record_content)).await;
+        let records = (self.records.iter().zip(contents))
+            .filter_map(|(record, res)| match res {
+                Ok(data) => Some((record, data)),
+                Err(err) => {
+                    log::error!("{record}: {err:#}");
+                    err_count += 1;
+                    None
+                },
+            })
+            .collect::<Vec<_>>();
+        if self.should_stop(err_count) {
+            return self.wrap_up(err_count).await;
+        }
+
+        let resolved = &records[..];
+        let record_tasks = resolved.iter().filter_map(|(record, data)| {
+            // Static records are always looked up individually unless we already have their whole zone.
+            let existing = if self.record_lookup == RecordLookup::Zone {
+                match current_records.get(record.target().domain().as_str()) {
+                    Some(records) => Some(&records[..]),
+                    None => {
+                        log::warn!("{record}: Skipped due to missing DNS records.");
+                        return None;
+                    },
+                }
+            } else {
+                None
+            };
+
+            Some(async move {
+                let res = self.handle_static_record(record, data, resolved, existing).await;
+                res.map_err(|err| log::error!("{record}: {err:#}"))
+            })
+        });
+
+        let record_results = self.join_until_limit(record_tasks, &mut err_count).await;
+        if self.should_stop(err_count) {
+            return self.wrap_up(err_count).await;
+        }
+
+        // Step 4: Remove anything else from fully-synced domains
+        // =============================================================================================================
+
+        // In a dry run, records that would have been edited still have their old content; make sure they still count
+        // as being claimed by their static records.
+        let claimed = record_results.into_iter().flatten().flatten().collect::<HashSet<_>>();
+        let sync_tasks = self.sync.iter().map(|sync| self.sync_domain(sync, &claimed));
+        err_count += futures::future::join_all(sync_tasks).await.into_iter().sum::<usize>();
+        if self.should_stop(err_count) {
+            return self.wrap_up(err_count).await;
+        }
+
+        // Step 5: Update any other providers
+        // =============================================================================================================
+
+        let provider_tasks = self.providers.iter().map(|provider| {
+            log::debug!("Updating {} records...", provider.name());
+            provider.update(ipv4, ipv6, self.dry_run)
+        });
+
+        err_count += futures::future::join_all(provider_tasks).await.into_iter().sum::<usize>();
+
+        // Step 6: Remember what was pushed for next time
+        // =============================================================================================================
+
+        self.wrap_up(err_count).await
+    }
+
+    /// Wraps up a run (even one that was cut short), saving the state file and logging a summary. Returns the final
+    /// number of errors.
+    async fn wrap_up(&self, mut err_count: usize) -> usize {
+        if !self.dry_run
+            && let Some(path) = &self.state_file
+        {
+            let state = self.state.lock().unwrap().clone();
+            if let Err(err) = state.save(path, &self.targets).await {
+                log::error!("Failed to save state to {}: {err:#}", path.display());
+                err_count += 1;
+            }
+        }
+
+        self.log_summary(err_count);
+        err_count
+    }
+
+    /// Filters out targets whose records already resolve to the current addresses on Porkbun's nameservers, counting
+    /// them as unchanged.
+    async fn precheck_targets<'a>(
+        &self,
+        precheck: &Precheck,
+        targets: Vec<&'a Target>,
+        ipv4: Option<Ipv4Addr>,
+        ipv6: Option<Ipv6Addr>,
+    ) -> Vec<&'a Target> {
+        log::debug!(
+            "Checking {n} {targets} against Porkbun's nameservers...",
+            n = targets.len(),
+            targets = pluralize!("target", "targets", targets.len()),
+        );
+
+        let addrs = |target: &Target| {
+            let (ipv4, ipv6) = target.families(ipv4, ipv6);
+            [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)].into_iter().flatten()
+        };
+
+        let checks = targets.iter().map(async |&target| {
+            for addr in addrs(target) {
+                if !precheck.is_current(target, target.address_for(addr)).await {
+                    return false;
+                }
+            }
+
+            // Records of disabled families might still need cleaning up.
+            for typ in self.cleanup_types(target) {
+                if precheck.has_records(target, typ == RecordType::AAAA).await {
+                    return false;
+                }
+            }
+            true
+        });
+
+        let current = futures::future::join_all(checks).await;
+        (targets.into_iter().zip(current))
+            .filter(|&(target, current)| {
+                if current {
+                    log::debug!("{target}: Records already resolve to the current addresses. Skipping.");
+                    for addr in addrs(target) {
+                        let addr = target.address_for(addr);
+                        if !self.is_dry_run(target) {
+                            self.state.lock().unwrap().set(target, addr);
+                        }
+                        self.record_result(target, addr, None);
+                    }
+                }
+                !current
+            })
+            .map(|(target, _)| target)
+            .collect()
+    }
+
+    /// Checks whether the rest of a run should be abandoned, either because it has hit `max_errors` or because the
+    /// process has been asked to stop.
+    fn should_stop(&self, err_count: usize) -> bool {
+        match self.max_errors {
+            Some(max) if err_count >= max => {
+                log::error!(
+                    "Reached the limit of {max} {errors}. Skipping the rest of the run.",
+                    errors = pluralize!("error", "errors", max),
+                );
+                true
+            },
+            _ if self.shutdown.is_requested() => {
+                log::warn!("Stopping early. Skipping the rest of the run.");
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Runs a batch of tasks all at once, adding any failures to `err_count`. If that reaches `max_errors`, whatever is
+    /// still running gets cancelled, and only the results gathered so far are returned.
+    async fn join_until_limit<T>(
+        &self,
+        tasks: impl IntoIterator<Item = impl Future<Output = Result<T, ()>>>,
+        err_count: &mut usize,
+    ) -> Vec<Result<T, ()>> {
+        let mut pending = tasks.into_iter().collect::<FuturesUnordered<_>>();
+        let mut results = Vec::with_capacity(pending.len());
+        while let Some(res) = pending.next().await {
+            *err_count += res.is_err() as usize;
+            results.push(res);
+            if self.max_errors.is_some_and(|max| *err_count >= max) {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Logs a one-line summary of what happened to every target during the run.
+    fn log_summary(&self, err_count: usize) {
+        let results = self.results.lock().unwrap();
+        let count = |action| results.iter().filter(|res| res.action == action).count();
+        let verb = if self.dry_run { "would be " } else { "" };
+        let (created, updated) = (count(TargetAction::Created), count(TargetAction::Updated));
+        let verified = results.iter().filter(|res| res.verified == Some(true)).count();
+        let checked = results.iter().filter(|res| res.verified.is_some()).count();
+
+        // In quiet mode, a run where nothing happened isn't worth mentioning at all.
+        if self.quiet && created + updated + err_count == 0 {
+            return;
+        }
+
+        let verification = match checked {
+            0 => String::new(),
+            n => format!(" {verified} of {n} {changes} verified.", changes = pluralize!("change", "changes", n)),
+        };
+
+        log::info!(
+            target: SUMMARY_TARGET,
+            "{created} {verb}created, {updated} {verb}updated, {unchanged} unchanged, {err_count} {errors} across {n} {targets}.{verification}",
+            unchanged = count(TargetAction::Unchanged),
+            errors = pluralize!("error", "errors", err_count),
+            n = self.targets.len(),
+            targets = pluralize!("target", "targets", self.targets.len()),
+        );
+    }
+
+    /// Gets the key used to look up the existing records for the given target, which depends on which
+    /// [`RecordLookup`] mode is in use.
+    fn records_key<'a>(&self, target: &'a Target) -> Cow<'a, str> {
+        match self.record_lookup {
+            RecordLookup::Zone => Cow::Borrowed(target.domain()),
+            RecordLookup::Target | RecordLookup::Skip => Cow::Owned(target.to_string()),
+        }
+    }
+
+    /// Fetches every existing record for each unique domain in the given list of targets and in the static records.
+    ///
+    /// Records are returned keyed by domain name, alongside the number of errors encountered. Domains whose records
+    /// could not be fetched are left out of the map.
+    async fn fetch_zone_records(&self, targets: &[&Target]) -> (HashMap<String, Vec<DNSRecord>>, usize) {
+        // First build a unique list of root domain names. Then we can send each one on its own task to get records.
+        let domains = (targets.iter().copied())
+            .chain(self.records.iter().map(StaticRecord::target))
+            .map(Target::domain)
+            .collect::<BTreeSet<_>>();
+
         log::debug!(
             "Querying Porkbun API for {n} {domains} existing DNS records...",
-            n = current_records.len(),
-            domains = pluralize!("domain's", "domains'", current_records.len()),
+            n = domains.len(),
+            domains = pluralize!("domain's", "domains'", domains.len()),
         );
 
-        let record_tasks = current_records.iter_mut().map(async |(domain, records)| -> Result<(), ()> {
-            match self.client.get_existing_records(domain).await {
-                Ok(existing) => {
-                    *records = existing;
+        // Targets only care about address records, but static records can be of any type.
+        let full_zones = (self.records.iter())
+            .map(|record| record.target().domain())
+            .collect::<HashSet<_>>();
 
+        let record_tasks = domains.into_iter().map(async |domain| {
+            let records = match full_zones.contains(domain) {
+                true => self.client.get_existing_records(domain).await,
+                false => self.client.get_address_records(domain).await,
+            };
+
+            match records {
+                Ok(records) => {
                     if log::log_enabled!(log::Level::Debug) {
-                        log_records(log::Level::Debug, domain, records);
+                        log_records(log::Level::Debug, domain, &records);
                     }
 
-                    Ok(())
+                    Ok((domain.to_string(), records))
                 },
                 Err(err) => {
                     log::error!("Failed to fetch DNS records for {domain}: {err:#}");
@@ -237,135 +1086,632 @@ impl App {
             }
         });
 
-        let mut err_count = futures::future::join_all(record_tasks)
-            .await
-            .into_iter()
-            .filter(Result::is_err)
-            .count();
+        let mut err_count = 0;
+        let results = self.join_until_limit(record_tasks, &mut err_count).await;
+        (results.into_iter().flatten().collect(), err_count)
+    }
 
-        // Step 2: Actually process all of the targets
-        // =============================================================================================================
+    /// Fetches only the existing A and/or AAAA records for each given target's specific name.
+    ///
+    /// Records are returned keyed by target (formatted as a string), alongside the number of errors encountered.
+    /// Targets whose records could not be fetched are left out of the map.
+    async fn fetch_target_records(
+        &self,
+        targets: &[&Target],
+        ipv4: bool,
+        ipv6: bool,
+    ) -> (HashMap<String, Vec<DNSRecord>>, usize) {
+        let dns_types = [ipv4.then_some("A"), ipv6.then_some("AAAA")];
 
-        let target_tasks = self.targets.iter().filter_map(|target| {
-            match current_records.get(target.domain()) {
-                Some(records) if records.len() > 0 => {
-                    // Convert an iterator of `Option<IpAddr>` into an iterator of `Option<impl Future>`, which gets
-                    // filtered down into an iterator of `impl Future`.
-                    let addrs = [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)];
-                    let tasks = addrs.into_iter().filter_map(move |addr| {
-                        addr.map(async move |addr| -> Result<(), ()> {
-                            let res = self.handle_target(target, records, addr).await;
-                            res.map_err(|err| log::error!("{target}: {err:#}")) // log and map to () at the same time
-                        })
-                    });
-
-                    // Return an `Iterator<impl Future>` to the outer `filter_map`, giving `Iter<Iter<impl Future>>`,
-                    // which then gets flattened down into one final iterator of futures.
-                    Some(tasks)
-                },
-                _ => {
-                    // Target's records might be missing if we previously failed to fetch them. Error would've already
-                    // been logged in that case, so we don't need to report another one.
-                    log::warn!("{target}: Skipped due to missing DNS records.");
-                    // Skip over this target in the outer `filter_map`.
-                    return None;
-                },
+        log::debug!(
+            "Querying Porkbun API for {n} {targets} existing DNS records...",
+            n = targets.len(),
+            targets = pluralize!("target's", "targets'", targets.len()),
+        );
+
+        let record_tasks = targets.iter().map(async |target| {
+            let mut records = Vec::new();
+            for dns_type in dns_types.into_iter().flatten() {
+                match self
+                    .client
+                    .get_records_by_name_type(target.domain(), target.subdomain(), dns_type)
+                    .await
+                {
+                    Ok(found) => records.extend(found),
+                    Err(err) => {
+                        log::error!("{target}: Failed to fetch existing {dns_type} records: {err:#}");
+                        return Err(());
+                    },
+                }
             }
+
+            log::debug!(
+                "{target}: Found {n} existing {records}.",
+                n = records.len(),
+                records = pluralize!("record", "records", records.len()),
+            );
+
+            Ok((target.to_string(), records))
         });
 
-        err_count += futures::future::join_all(target_tasks.flatten())
-            .await
-            .into_iter()
-            .filter(Result::is_err)
-            .count();
+        let mut err_count = 0;
+        let results = self.join_until_limit(record_tasks, &mut err_count).await;
+        (results.into_iter().flatten().collect(), err_count)
+    }
 
-        err_count
+    /// Updates a target without having looked up its existing records first, by editing its records by name and type.
+    ///
+    /// If the edit fails (most likely because there is no record to edit yet), or if editing blindly isn't safe, this
+    /// falls back to looking up the target's records and handling it normally.
+    async fn handle_target_blind(&self, target: &Target, addr: IpAddr) -> eyre::Result<()> {
+        let dns_type = addr.dns_type();
+
+        // Without looking anything up, there's no way to tell what a dry run would have done. Editing by name and type
+        // also points every one of the target's records at the same address, which is only what's wanted when
+        // `multiple_records` says so (and never for round-robin targets).
+        let blind =
+            !self.is_dry_run(target) && !target.is_round_robin() && self.multiple_records == MultipleRecords::UpdateAll;
+        if blind {
+            self.backup(target.domain()).await?;
+            match self.client.edit_records_by_name_type(target, &addr.into()).await {
+                Ok(()) => {
+                    // Porkbun doesn't say whether the content was actually any different, so this only counts as a
+                    // change if the state file remembers a different address being there before.
+                    let previous = self.state.lock().unwrap().last_pushed(target, addr.is_ipv6());
+                    match previous {
+                        Some(old) if old != addr => {
+                            log::info!("{target}: Edited existing {dns_type} record from {old} to {addr}.");
+                            self.record_change(Change::edit_blind(target, &addr.into()));
+                        },
+                        _ => log::debug!("{target}: Set {dns_type} record to {addr} (it may already have held it)."),
+                    }
+                    return Ok(());
+                },
+                Err(err) => log::debug!("{target}: Could not edit {dns_type} record by name and type: {err:#}"),
+            }
+        }
+
+        log::debug!("{target}: Looking up existing {dns_type} records...");
+        let records = self
+            .client
+            .get_records_by_name_type(target.domain(), target.subdomain(), dns_type)
+            .await
+            .wrap_err("Failed to fetch existing records")?;
+        self.handle_target(target, &records, addr).await
     }
 
-    async fn handle_target<'a>(&self, target: &Target, records: &'a [DNSRecord], addr: IpAddr) -> eyre::Result<()> {
+    async fn handle_target(&self, target: &Target, records: &[DNSRecord], addr: IpAddr) -> eyre::Result<()> {
         let dns_type = addr.dns_type();
 
         // Check if any of the existing records for this target's domain actually match the target precisely:
-        let mut existing = None;
+        let mut existing = Vec::new();
         for record in records {
             if !target.matches_record(record) {
                 continue;
             }
 
-            if record.typ == dns_type {
-                if existing.is_none() {
-                    existing = Some(record);
-                } else {
-                    // We don't really have a way to handle when there are multiple existing records. Do we replace both
-                    // of them? How can we know if that's a good idea if we don't know why there are two? We'll just let
-                    // the user deal with it (for now, at least).
-                    return Err(eyre!(
-                        "Found more than one existing {dns_type} records for {target}, unsure which to update"
-                    ));
+            match (&record.typ, addr) {
+                (RecordType::A, IpAddr::V4(_)) | (RecordType::AAAA, IpAddr::V6(_)) => existing.push(record),
+                (RecordType::CNAME | RecordType::ALIAS, _) => {
+                    // It's not possible to create an A or AAAA record when there is an ALIAS or a CNAME record, since
+                    // those work by passing records through to another host. Porkbun's API ideally should handle this
+                    // and return an error in their API response, but the message they return doesn't actually give a
+                    // reason (it does in their web interface, though). So, we'll keep an eye out for it.
+                    return Err(eyre!("A CNAME or ALIAS record already exists for host {target}")
+                        .wrap_err(format!("Can't create {dns_type} record")));
+                },
+                _ => {},
+            }
+        }
+
+        if target.is_round_robin() {
+            let wanted = self.round_robin_addresses(target, addr)?;
+            return self.handle_round_robin(target, &existing, &wanted).await;
+        }
+
+        match (&existing[..], self.multiple_records) {
+            ([], _) => self.create_target_record(target, addr).await,
+            ([record], _) | ([record, ..], MultipleRecords::UpdateFirst) => {
+                self.update_target_record(target, record, addr).await
+            },
+            (records, MultipleRecords::UpdateAll) => {
+                log::debug!("{target}: Found {} existing {dns_type} records; updating all of them.", records.len());
+                for record in records {
+                    self.update_target_record(target, record, addr).await?;
+                }
+                Ok(())
+            },
+            (records, MultipleRecords::Dedupe) => {
+                // Keep whichever one is already correct, if any, so that it doesn't need to be edited.
+                let keep = (records.iter())
+                    .position(|rec| rec.try_parse_ip().is_ok_and(|rec_addr| rec_addr == addr))
+                    .unwrap_or(0);
+
+                for (i, record) in records.iter().enumerate() {
+                    if i != keep {
+                        self.delete_target_record(target, record, "duplicate").await?;
+                    }
                 }
-            } else if record.typ == "CNAME" || record.typ == "ALIAS" {
-                // It's not possible to create an A or AAAA record when there is an ALIAS or a CNAME record, since those
-                // work by passing records through to another host. Porkbun's API ideally should handle this and return
-                // an error in their API response, but the message they return doesn't actually give a reason (it does
-                // in their web interface, though). So, we'll keep an eye out for it.
-                return Err(eyre!("A CNAME or ALIAS record already exists for host {target}")
-                    .wrap_err(format!("Can't create {dns_type} record")));
+
+                self.update_target_record(target, records[keep], addr).await
+            },
+            (_, MultipleRecords::Error) => {
+                // We don't really have a way to handle when there are multiple existing records by default. Do we
+                // replace both of them? How can we know if that's a good idea if we don't know why there are two? We'll
+                // just let the user decide.
+                Err(eyre!("Found more than one existing {dns_type} records for {target}, unsure which to update"))
+            },
+        }
+    }
+
+    /// The types of a target's address records that should be cleaned up, because their address family is disabled
+    /// (either everywhere, or just for that target).
+    fn cleanup_types(&self, target: &Target) -> impl Iterator<Item = RecordType> + use<> {
+        let types = [
+            (!self.ipv4_enabled || !target.updates_family(false)).then_some(RecordType::A),
+            (!self.ipv6_enabled || !target.updates_family(true)).then_some(RecordType::AAAA),
+        ];
+        let cleanup = self.cleanup_disabled;
+        types.into_iter().flatten().filter(move |_| cleanup)
+    }
+
+    /// Deletes a target's records of the given (disabled) type, as long as they were created by this program. Records
+    /// are fetched first if they haven't been already.
+    async fn cleanup_target(
+        &self,
+        target: &Target,
+        records: Option<&[DNSRecord]>,
+        typ: RecordType,
+    ) -> eyre::Result<()> {
+        let fetched;
+        let records = match records {
+            Some(records) => records,
+            None => {
+                fetched = self
+                    .client
+                    .get_records_by_name_type(target.domain(), target.subdomain(), typ.as_str())
+                    .await
+                    .wrap_err("Failed to fetch existing records")?;
+                &fetched[..]
+            },
+        };
+
+        let stale = records.iter().filter(|rec| rec.typ == typ && target.matches_record(rec));
+        for record in stale {
+            if record.is_managed() {
+                self.delete_target_record(target, record, "stale").await?;
+            } else if self.client.marks_records(target) {
+                log::debug!("{target}: Leaving {typ} record with content {} alone (not ours).", record.content);
+            } else {
+                // Records that this program wrote without the marker look exactly like anyone else's.
+                log::warn!(
+                    "{target}: Leaving {typ} record with content {} alone: without notes to mark it, there's no way to \
+                     tell whether it was created by this program.",
+                    record.content,
+                );
             }
         }
 
-        if let Some(record) = existing {
-            let id = &record.id[..];
+        if !self.is_dry_run(target) {
+            self.state.lock().unwrap().forget(target, typ == RecordType::AAAA);
+        }
+        Ok(())
+    }
 
-            // Check what the IP address is on the existing record
-            let existing_addr = record
-                .try_parse_ip()
-                .wrap_err_with(|| format!("Found matching {dns_type} record, but it was malformed"))?;
+    /// Makes a round-robin target's records of one type hold exactly the given set of addresses: records that already
+    /// hold one of them are kept, records holding anything else are edited to hold whichever are missing, and then any
+    /// records that are still left over are deleted (or any addresses still missing are created).
+    async fn handle_round_robin(
+        &self,
+        target: &Target,
+        existing: &[&DNSRecord],
+        wanted: &[IpAddr],
+    ) -> eyre::Result<()> {
+        let mut missing = wanted.to_vec();
+        let mut surplus = Vec::new();
+        for &record in existing {
+            let found = (record.try_parse_ip().ok()).and_then(|addr| missing.iter().position(|&want| want == addr));
+            match found {
+                Some(i) => {
+                    let addr = missing.remove(i);
+                    self.update_target_record(target, record, addr).await?;
+                },
+                None => surplus.push(record),
+            }
+        }
 
-            // If the address on the record matches our current address, we don't need to update anything.
-            if existing_addr == addr {
-                log::debug!("{target}: Found existing {dns_type} record with content {addr}. Nothing to do.");
-                log::trace!("{target}: Existing {} record has ID {}", record.typ, record.id);
-                Ok(())
+        let mut surplus = surplus.into_iter();
+        for addr in missing {
+            let Some(record) = surplus.next() else {
+                self.create_target_record(target, addr).await?;
+                continue;
+            };
+
+            if !self.is_dry_run(target) {
+                self.backup(target.domain()).await?;
+                self.client
+                    .edit_record(target, record, &addr.into())
+                    .await
+                    .wrap_err("Failed to edit DNS record")?;
+            }
+
+            log::info!("{target}: Edited existing {} record from {} to {addr}.", record.typ, record.content);
+            self.record_change(Change::edit(target, record, &addr.into()));
+            log::trace!("{target}: Edited {} record has ID {}", record.typ, record.id);
+        }
+
+        for record in surplus {
+            self.delete_target_record(target, record, "extra").await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets every address that a round-robin target's records of one type should hold: this machine's own address,
+    /// followed by any extra addresses of the same family.
+    fn round_robin_addresses(&self, target: &Target, addr: IpAddr) -> eyre::Result<Vec<IpAddr>> {
+        let mut wanted = vec![addr];
+        for extra in target.extra_addresses() {
+            let found = match extra {
+                ExtraAddress::Fixed(extra) => vec![*extra],
+                // Interfaces usually have loopback and link-local addresses too, which are no use to anyone else.
+                ExtraAddress::Interface(name) => netif::interface_addrs(name)
+                    .wrap_err_with(|| format!("Failed to read addresses of interface {name}"))?
+                    .into_iter()
+                    .filter(|found| self.allow_private_ips || found.private_kind().is_none())
+                    .collect(),
+            };
+
+            for found in found.into_iter().filter(|found| found.is_ipv6() == addr.is_ipv6()) {
+                if !wanted.contains(&found) {
+                    wanted.push(found);
+                }
+            }
+        }
+
+        Ok(wanted)
+    }
+
+    /// Creates a new A/AAAA record for a target holding the given address.
+    async fn create_target_record(&self, target: &Target, addr: IpAddr) -> eyre::Result<()> {
+        let id = if !self.is_dry_run(target) {
+            self.client
+                .create_record(target, &addr.into())
+                .await
+                .wrap_err("Failed to create DNS record")?
+        } else {
+            "<ID>".to_string()
+        };
+
+        log::info!("{target}: Created new {} record with content {addr}.", addr.dns_type());
+        self.record_change(Change::create(target, &addr.into()));
+        log::trace!("{target}: New record has ID {id}");
+        Ok(())
+    }
+
+    /// Deletes one of a target's A/AAAA records that it doesn't need. `what` describes why, for the log message.
+    async fn delete_target_record(&self, target: &Target, record: &DNSRecord, what: &str) -> eyre::Result<()> {
+        if !self.is_dry_run(target) {
+            self.backup(target.domain()).await?;
+            self.client
+                .delete_record(target.domain(), record)
+                .await
+                .wrap_err_with(|| format!("Failed to delete {what} DNS record"))?;
+        }
+
+        log::info!("{target}: Deleted {what} {} record with content {}.", record.typ, record.content);
+        self.record_change(Change::delete(target.domain(), record));
+        log::trace!("{target}: Deleted record had ID {}", record.id);
+        Ok(())
+    }
+
+    /// Updates a single existing A/AAAA record for a target to hold the given address, if it doesn't already.
+    async fn update_target_record(&self, target: &Target, record: &DNSRecord, addr: IpAddr) -> eyre::Result<()> {
+        let dns_type = addr.dns_type();
+
+        // Check what the IP address is on the existing record
+        let existing_addr = record
+            .try_parse_ip()
+            .wrap_err_with(|| format!("Found matching {dns_type} record, but it was malformed"))?;
+
+        // When tracking the IPv6 prefix, targets without a fixed suffix keep whatever host part their record already
+        // has; only the prefix gets replaced.
+        let addr = match (addr, existing_addr) {
+            (IpAddr::V6(new), IpAddr::V6(old)) if target.tracks_ipv6_prefix() && !target.has_ipv6_suffix() => {
+                IpAddr::V6(target.with_host_bits(new, old))
+            },
+            _ => addr,
+        };
+
+        // If the address on the record matches our current address, we don't need to update anything (unless we've
+        // been told to anyway).
+        if existing_addr == addr && !self.force {
+            log::debug!("{target}: Found existing {dns_type} record with content {addr}. Nothing to do.");
+            log::trace!("{target}: Existing {} record has ID {}", record.typ, record.id);
+            return Ok(());
+        }
+
+        if !self.is_dry_run(target) {
+            self.backup(target.domain()).await?;
+            self.client
+                .edit_record(target, record, &addr.into())
+                .await
+                .wrap_err("Failed to edit DNS record")?;
+        }
+
+        if existing_addr == addr {
+            log::info!("{target}: Re-sent existing {dns_type} record with content {addr} (forced).");
+        } else {
+            log::info!("{target}: Edited existing {dns_type} record from {existing_addr} to {addr}.");
+        }
+        self.record_change(Change::edit(target, record, &addr.into()));
+        log::trace!("{target}: Edited {} record has ID {}", record.typ, record.id);
+        Ok(())
+    }
+
+    /// Creates or updates a single static record.
+    ///
+    /// `existing` should contain at least all the records with the same name as this one, if they have already been
+    /// fetched. If not, they are fetched here.
+    ///
+    /// Returns the ID of the existing record which now holds this static record's content, if there was one.
+    async fn handle_static_record(
+        &self,
+        record: &StaticRecord,
+        data: &RecordData,
+        resolved: &[(&StaticRecord, RecordData)],
+        existing: Option<&[DNSRecord]>,
+    ) -> eyre::Result<Option<String>> {
+        let target = record.target();
+        let (typ, content) = (&data.typ, &data.content);
+
+        let fetched;
+        let existing = match existing {
+            Some(records) => records,
+            None => {
+                fetched = self
+                    .client
+                    .get_records_by_name_type(target.domain(), target.subdomain(), typ.as_str())
+                    .await
+                    .wrap_err("Failed to fetch existing records")?;
+                &fetched[..]
+            },
+        };
+
+        // If a record with this exact content already exists, there's nothing to do other than to make sure its TTL
+        // and priority are right.
+        if let Some(current) = existing
+            .iter()
+            .find(|rec| record.matches_record(rec) && rec.content == *content)
+        {
+            let ttl_ok = current.ttl == Some(target.ttl());
+            let prio_ok = data.prio.is_none_or(|prio| current.prio == Some(prio));
+            if ttl_ok && prio_ok && !self.force {
+                log::debug!("{target}: Found existing {typ} record with content {content:?}. Nothing to do.");
             } else {
                 if !self.dry_run {
+                    self.backup(target.domain()).await?;
                     self.client
-                        .edit_record(target, id, addr)
+                        .edit_record(target, current, data)
                         .await
                         .wrap_err("Failed to edit DNS record")?;
                 }
 
-                log::info!("{target}: Edited existing {dns_type} record from {existing_addr} to {addr}.");
-                log::trace!("{target}: Edited {} record has ID {}", record.typ, record.id);
-                Ok(())
+                let what = match (ttl_ok, prio_ok) {
+                    (true, true) => None,
+                    (false, false) => Some("TTL and priority"),
+                    (false, true) => Some("TTL"),
+                    (true, false) => Some("priority"),
+                };
+                match what {
+                    Some(what) => {
+                        log::info!("{target}: Edited {what} of existing {typ} record with content {content:?}.")
+                    },
+                    None => log::info!("{target}: Re-sent existing {typ} record with content {content:?} (forced)."),
+                }
+                self.record_change(Change::edit(target, current, data));
             }
-        } else {
-            let id;
-            if !self.dry_run {
-                id = self
-                    .client
-                    .create_record(target, addr)
-                    .await
-                    .wrap_err("Failed to create DNS record")?;
-            } else {
-                id = "<ID>".to_string();
+            return Ok(Some(current.id.clone()));
+        }
+
+        // Otherwise, we may be able to update an existing record with this name and type. Some types (like TXT) are
+        // often used for several records with the same name, though, so records whose content belongs to another of
+        // the configured static records are left alone.
+        let replaceable = existing
+            .iter()
+            .filter(|rec| record.matches_record(rec))
+            .filter(|rec| {
+                !(resolved.iter())
+                    .any(|(other, other_data)| other.matches_record(rec) && rec.content == other_data.content)
+            })
+            .collect::<Vec<_>>();
+
+        match replaceable[..] {
+            [] => {
+                let id = if !self.dry_run {
+                    self.client
+                        .create_record(target, data)
+                        .await
+                        .wrap_err("Failed to create DNS record")?
+                } else {
+                    "<ID>".to_string()
+                };
+
+                log::info!("{target}: Created new {typ} record with content {content:?}.");
+                self.record_change(Change::create(target, data));
+                log::trace!("{target}: New record has ID {id}");
+                Ok(None)
+            },
+            [current] => {
+                if !self.dry_run {
+                    self.backup(target.domain()).await?;
+                    self.client
+                        .edit_record(target, current, data)
+                        .await
+                        .wrap_err("Failed to edit DNS record")?;
+                }
+
+                let old = &current.content;
+                log::info!("{target}: Edited existing {typ} record from {old:?} to {content:?}.");
+                self.record_change(Change::edit(target, current, data));
+                log::trace!("{target}: Edited {typ} record has ID {}", current.id);
+                Ok(Some(current.id.clone()))
+            },
+            _ => Err(eyre!("Found more than one existing {typ} records for {target}, unsure which to update")),
+        }
+    }
+
+    /// Deletes every record on a fully-synced domain that isn't described by the config file or explicitly protected.
+    ///
+    /// This runs after all targets and static records have been updated, so it fetches the domain's records again.
+    /// `claimed` holds the IDs of existing records that static records have already claimed. Returns the number of
+    /// errors encountered.
+    async fn sync_domain(&self, sync: &SyncDomain, claimed: &HashSet<String>) -> usize {
+        let domain = &sync.domain[..];
+        let records = match self.client.get_existing_records(domain).await {
+            Ok(records) => records,
+            Err(err) => {
+                log::error!("Failed to fetch DNS records for {domain} to sync: {err:#}");
+                return 1;
+            },
+        };
+
+        let wanted = |rec: &DNSRecord| -> bool {
+            if claimed.contains(&rec.id) || sync.is_protected(rec) {
+                return true;
             }
 
-            log::info!("{target}: Created new {dns_type} record with content {addr}.");
-            log::trace!("{target}: New record has ID {id}");
-            Ok(())
+            let on_domain = |tgt: &Target| tgt.domain().eq_ignore_ascii_case(domain);
+            match rec.typ {
+                RecordType::A | RecordType::AAAA => {
+                    (self.targets.iter()).any(|tgt| on_domain(tgt) && tgt.matches_record(rec))
+                },
+                // Records whose content comes from a program can't be told apart by their content (and their program
+                // might have failed this time around), so anything with their name and type is kept.
+                _ => (self.records.iter()).any(|other| {
+                    on_domain(other.target())
+                        && other.matches_record(rec)
+                        && (other.command().is_some() || other.data().content == rec.content)
+                }),
+            }
+        };
+
+        let unwanted = records.iter().filter(|rec| !wanted(rec)).collect::<Vec<_>>();
+        if !unwanted.is_empty()
+            && let Err(err) = self.backup(domain).await
+        {
+            log::error!("Not syncing {domain}: {err:#}");
+            return 1;
         }
+
+        let mut err_count = 0;
+        for record in unwanted {
+            let (name, typ, content) = (&record.name, &record.typ, &record.content);
+
+            if !self.dry_run
+                && let Err(err) = self.client.delete_record(domain, record).await
+            {
+                log::error!("{name}: Failed to delete unlisted {typ} record: {err:#}");
+                err_count += 1;
+                continue;
+            }
+
+            log::info!("{name}: Deleted unlisted {typ} record with content {content:?}.");
+            self.record_change(Change::delete(domain, record));
+            log::trace!("{name}: Deleted record had ID {}", record.id);
+        }
+
+        err_count
+    }
+}
+
+/// Adds a target for the root of each active domain on the Porkbun account which doesn't already have one, with the
+/// given TTL.
+async fn discover_targets(client: &PorkbunClient, targets: &mut Vec<Target>, ttl: u32) -> eyre::Result<()> {
+    log::debug!("Querying Porkbun API for domains on account...");
+    let domains = client.list_domains().await?;
+    log::debug!(
+        "Found {n} {domains} on account.",
+        n = domains.len(),
+        domains = pluralize!("domain", "domains", domains.len()),
+    );
+
+    for info in domains {
+        // Domains which have expired or are still being transferred in can't have their records updated.
+        if info.status.as_deref().is_some_and(|status| status != "ACTIVE") {
+            log::debug!("Skipping discovered domain {} with status {:?}.", info.domain, info.status.unwrap());
+            continue;
+        }
+
+        let domain = match DomainName::parse(&info.domain) {
+            Ok(domain) => domain,
+            Err(err) => {
+                log::warn!("Skipping discovered domain: {err}.");
+                continue;
+            },
+        };
+
+        if targets.iter().any(|tgt| tgt.is_root() && *tgt.domain() == domain) {
+            continue;
+        }
+
+        log::trace!("Adding discovered target {domain}");
+        targets.push(Target::new(domain, None, ttl));
+    }
+
+    Ok(())
+}
+
+/// Works out the type and content of a static record, running its program if its content comes from one.
+///
+/// The program is run directly (not through a shell), even during dry runs, with the record's full name and type in
+/// `RECORD_NAME` and `RECORD_TYPE`. Everything it prints, minus any surrounding whitespace, becomes the content.
+async fn record_content(record: &StaticRecord) -> eyre::Result<RecordData> {
+    let Some(command) = record.command() else {
+        return Ok(record.data().clone());
+    };
+
+    let program = &command[0];
+    log::debug!("{record}: Running {program} to get record content...");
+    let output = tokio::process::Command::new(program)
+        .args(&command[1..])
+        .env("RECORD_NAME", record.target().fqdn())
+        .env("RECORD_TYPE", record.data().typ.as_str())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, output)
+        .await
+        .map_err(|_| eyre!("{program} did not finish within {} seconds", COMMAND_TIMEOUT.as_secs()))?
+        .wrap_err_with(|| format!("Failed to run {program}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(eyre!("{program} exited with {}: {}", output.status, stderr.trim()));
+    }
+
+    let stdout = String::from_utf8(output.stdout).wrap_err_with(|| format!("{program} printed invalid UTF-8"))?;
+    match stdout.trim() {
+        "" => Err(eyre!("{program} did not print any record content")),
+        content => Ok(RecordData {
+            content: content.to_string(),
+            ..record.data().clone()
+        }),
     }
 }
 
 /// Helper function for logging which records were retrieved for a given domain.
 fn log_records(level: log::Level, domain: &str, records: &[DNSRecord]) {
-    if records.len() == 0 {
+    if records.is_empty() {
         log::log!(level, "Found 0 existing records for {domain}.");
     } else {
         // Count how many records of each specific type we found:
         let mut counts = BTreeMap::new();
-        for rec in &*records {
-            *(counts.entry(&rec.typ[..]).or_insert(0usize)) += 1;
+        for rec in records {
+            *(counts.entry(rec.typ.as_str()).or_insert(0usize)) += 1;
         }
 
         // Don't feel like bringing all of itertools in just to get `.join`...
@@ -390,3 +1736,30 @@ fn log_records(level: log::Level, domain: &str, records: &[DNSRecord]) {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs share nothing about a record's content: its command is run again every time, whether or not any target's
+    /// address has changed, so new output is always picked up.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn record_content_reruns_command_every_time() {
+        let path = std::env::temp_dir().join(format!("porkbun-ddns-record-content-{}", std::process::id()));
+        let record = toml::from_str::<StaticRecord>(&format!(
+            "domain = \"example.com\"\nsubdomain = \"_status\"\ntype = \"TXT\"\ncommand = [\"cat\", {:?}]",
+            path.display().to_string(),
+        ))
+        .unwrap();
+
+        std::fs::write(&path, "first\n").unwrap();
+        let first = record_content(&record).await;
+        std::fs::write(&path, "second\n").unwrap();
+        let second = record_content(&record).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(first.unwrap().content, "first");
+        assert_eq!(second.unwrap().content, "second");
+    }
+}
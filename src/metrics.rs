@@ -0,0 +1,166 @@
+//! Counters and gauges about past runs, exported in Prometheus' text format when running as a daemon.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::report::{Report, TargetAction};
+
+/// How long requests to one of Porkbun's API endpoints have taken so far.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiTiming {
+    pub count: u64,
+    pub failures: u64,
+    pub total: Duration,
+}
+
+impl ApiTiming {
+    pub fn record(&mut self, elapsed: Duration, success: bool) {
+        self.count += 1;
+        self.total += elapsed;
+        if !success {
+            self.failures += 1;
+        }
+    }
+}
+
+/// Everything that has happened across all the runs made so far.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    updates: u64,
+    failures: u64,
+    errors: u64,
+    /// When the last run finished, as a Unix timestamp.
+    last_update: Option<i64>,
+    /// When the last run without any errors finished, as a Unix timestamp.
+    last_success: Option<i64>,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    /// The address each target's record was last set to (or found to already have), by target and record type.
+    published: BTreeMap<(String, &'static str), String>,
+}
+
+impl Metrics {
+    /// Counts a finished run.
+    pub fn record(&mut self, report: &Report) {
+        let now = chrono::Utc::now().timestamp();
+
+        self.updates += 1;
+        self.errors += report.errors as u64;
+        self.last_update = Some(now);
+        match report.errors {
+            0 => self.last_success = Some(now),
+            _ => self.failures += 1,
+        }
+
+        // Keep showing the last known addresses if they couldn't be found this time.
+        self.ipv4 = report.ipv4.or(self.ipv4);
+        self.ipv6 = report.ipv6.or(self.ipv6);
+
+        for result in &report.targets {
+            if result.action != TargetAction::Failed {
+                self.published.insert((result.target.clone(), result.typ), result.new.clone());
+            }
+        }
+    }
+
+    /// Formats every metric in Prometheus' text exposition format.
+    pub fn render(&self, api: &BTreeMap<String, ApiTiming>) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, typ: &str, help: &str, samples: &[(String, String)]| {
+            // Writing to a string can't fail.
+            let _ = writeln!(out, "# HELP porkbun_ddns_{name} {help}");
+            let _ = writeln!(out, "# TYPE porkbun_ddns_{name} {typ}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "porkbun_ddns_{name}{labels} {value}");
+            }
+        };
+
+        metric("updates_total", "counter", "Update runs attempted.", &unlabelled(self.updates));
+        metric(
+            "update_failures_total",
+            "counter",
+            "Update runs that encountered at least one error.",
+            &unlabelled(self.failures),
+        );
+        metric("errors_total", "counter", "Errors encountered across all update runs.", &unlabelled(self.errors));
+        metric(
+            "last_update_timestamp_seconds",
+            "gauge",
+            "When the last update run finished.",
+            &unlabelled(self.last_update.unwrap_or(0)),
+        );
+        metric(
+            "last_success_timestamp_seconds",
+            "gauge",
+            "When the last update run without any errors finished.",
+            &unlabelled(self.last_success.unwrap_or(0)),
+        );
+
+        let api_samples = |f: fn(&ApiTiming) -> String| {
+            (api.iter())
+                .map(|(endpoint, timing)| (labels(&[("endpoint", endpoint)]), f(timing)))
+                .collect::<Vec<_>>()
+        };
+        metric(
+            "api_request_duration_seconds",
+            "summary",
+            "Time taken by requests to Porkbun's API.",
+            &(api.iter())
+                .flat_map(|(endpoint, timing)| {
+                    let labels = labels(&[("endpoint", endpoint)]);
+                    [
+                        (format!("_sum{labels}"), timing.total.as_secs_f64().to_string()),
+                        (format!("_count{labels}"), timing.count.to_string()),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        );
+        metric(
+            "api_request_failures_total",
+            "counter",
+            "Requests to Porkbun's API that failed.",
+            &api_samples(|timing| timing.failures.to_string()),
+        );
+
+        let addrs = [
+            ("ipv4", self.ipv4.map(|a| a.to_string())),
+            ("ipv6", self.ipv6.map(|a| a.to_string())),
+        ];
+        metric(
+            "address_info",
+            "gauge",
+            "This machine's current public addresses.",
+            &(addrs.into_iter())
+                .filter_map(|(family, addr)| Some((labels(&[("family", family), ("address", &addr?)]), "1".into())))
+                .collect::<Vec<_>>(),
+        );
+        metric(
+            "published_address_info",
+            "gauge",
+            "The address that each target's record was last set to.",
+            &(self.published.iter())
+                .map(|((target, typ), addr)| {
+                    (labels(&[("target", target), ("type", typ), ("address", addr)]), "1".into())
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        out
+    }
+}
+
+/// A single sample without any labels.
+fn unlabelled(value: impl ToString) -> [(String, String); 1] {
+    [(String::new(), value.to_string())]
+}
+
+/// Formats a set of labels, escaping their values.
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs = pairs.iter().map(|(name, value)| {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        format!("{name}=\"{value}\"")
+    });
+    format!("{{{}}}", pairs.collect::<Vec<_>>().join(","))
+}
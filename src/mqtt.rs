@@ -0,0 +1,95 @@
+//! Publishing of current addresses and update results to an MQTT broker, for home automation systems and the like.
+
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, QoS, TlsConfiguration, Transport};
+
+use crate::api::HOSTNAME;
+use crate::config::MqttConfig;
+use crate::report::Report;
+
+/// How long to wait for the broker to accept everything before giving up.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Publishes the results of each run to an MQTT broker, as retained messages.
+///
+/// The following topics are published to, under the configured prefix:
+///
+/// - `ipv4` and `ipv6`: this machine's current addresses, as plain text.
+/// - `targets/{target}/{type}`: what happened to each target's A/AAAA record during the last run, as JSON.
+/// - `last_run`: the full [report][Report] of the last run, as JSON.
+#[derive(Debug)]
+pub struct Mqtt {
+    options: MqttOptions,
+    topic: String,
+}
+
+impl Mqtt {
+    pub fn new(config: MqttConfig) -> eyre::Result<Self> {
+        let client_id = (config.client_id).unwrap_or_else(|| format!("{}-{}", env!("CARGO_PKG_NAME"), *HOSTNAME));
+        let port = config.port.unwrap_or(if config.tls { 8883 } else { 1883 });
+
+        let mut options = MqttOptions::new(client_id, config.host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if config.tls {
+            options.set_transport(Transport::tls_with_config(TlsConfiguration::Native));
+        }
+
+        match (config.username, config.password) {
+            (Some(user), pass) => {
+                options.set_credentials(user, pass.unwrap_or_default());
+            },
+            (None, Some(_)) => return Err(eyre!("mqtt.password cannot be given without mqtt.username")),
+            (None, None) => {},
+        }
+
+        Ok(Self {
+            options,
+            topic: config.topic.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Publishes everything from a run's report, waiting for the broker to acknowledge each message.
+    pub async fn publish(&self, report: &Report) -> eyre::Result<()> {
+        let mut messages = Vec::new();
+        if let Some(addr) = report.ipv4 {
+            messages.push((format!("{}/ipv4", self.topic), addr.to_string()));
+        }
+        if let Some(addr) = report.ipv6 {
+            messages.push((format!("{}/ipv6", self.topic), addr.to_string()));
+        }
+        for result in &report.targets {
+            let topic = format!("{}/targets/{}/{}", self.topic, result.target, result.typ);
+            messages.push((topic, serde_json::to_string(result)?));
+        }
+        messages.push((format!("{}/last_run", self.topic), serde_json::to_string(report)?));
+
+        // Everything is queued up front, so the channel just needs to be big enough to hold it all.
+        let count = messages.len();
+        let (client, mut eventloop) = AsyncClient::new(self.options.clone(), count + 1);
+        for (topic, payload) in messages {
+            log::trace!("Publishing to MQTT topic {topic}");
+            client.try_publish(topic, QoS::AtLeastOnce, true, payload)?;
+        }
+
+        let res = tokio::time::timeout(TIMEOUT, async {
+            let mut acked = 0;
+            loop {
+                match eventloop.poll().await.wrap_err("MQTT connection failed")? {
+                    Event::Incoming(Packet::PubAck(_)) => {
+                        acked += 1;
+                        if acked == count {
+                            client.try_disconnect()?;
+                        }
+                    },
+                    Event::Outgoing(Outgoing::Disconnect) => break eyre::Ok(()),
+                    _ => {},
+                }
+            }
+        })
+        .await;
+
+        res.map_err(|_| eyre!("Timed out waiting for the MQTT broker"))?
+    }
+}
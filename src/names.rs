@@ -0,0 +1,190 @@
+//! Validated domain names and subdomains.
+//!
+//! Names are normalized as soon as they're parsed: they're lowercased, internationalized labels are converted to their
+//! punycode (`xn--`) form, and any trailing dot is dropped. Everything else can then compare and format them directly.
+
+use std::fmt::Display;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+/// The longest name that DNS can hold, not counting the trailing dot.
+pub const MAX_NAME_LEN: usize = 253;
+
+/// The longest that any one label (the parts between dots) of a name can be.
+const MAX_LABEL_LEN: usize = 63;
+
+/// A domain registered with Porkbun, like `example.com`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DomainName(String);
+
+/// The part of a name in front of its domain, like `www` or `_acme-challenge.home`.
+///
+/// The root of a domain (written as `@`, or left empty) has no subdomain at all: it's represented by `None` wherever an
+/// `Option<Subdomain>` is used.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Subdomain(String);
+
+impl DomainName {
+    /// Validates and normalizes a domain name.
+    pub fn parse(str: &str) -> Result<Self, String> {
+        let name = normalize(str, false).map_err(|reason| format!("domain {str:?} is invalid because {reason}"))?;
+        Ok(DomainName(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The fully-qualified name of the given subdomain of this domain, as Porkbun reports it (i.e., without any `@`).
+    pub fn fqdn(&self, subdomain: Option<&Subdomain>) -> String {
+        match subdomain {
+            None => self.0.clone(),
+            Some(sub) => format!("{sub}.{self}"),
+        }
+    }
+
+    /// Checks whether a name (e.g., from an existing record) is the given subdomain of this domain.
+    ///
+    /// Names from elsewhere might not be normalized, so this ignores case and any trailing dot; neither of those should
+    /// stop a record from matching (or else a duplicate would get created next to it).
+    pub fn matches(&self, subdomain: Option<&Subdomain>, name: &str) -> bool {
+        let name = name.trim_end_matches('.');
+        let Some(rest) = strip_suffix_ignore_case(name, &self.0) else {
+            return false;
+        };
+
+        match subdomain {
+            None => rest.is_empty(),
+            Some(sub) => rest.strip_suffix('.').is_some_and(|rest| rest.eq_ignore_ascii_case(&sub.0)),
+        }
+    }
+}
+
+impl Subdomain {
+    /// Validates and normalizes a subdomain. Returns `None` for the root of the domain (`@` or an empty string).
+    pub fn parse(str: &str) -> Result<Option<Self>, String> {
+        if str.is_empty() || str == "@" {
+            return Ok(None);
+        }
+
+        let name = normalize(str, true).map_err(|reason| format!("subdomain {str:?} is invalid because {reason}"))?;
+        Ok(Some(Subdomain(name)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Lowercases a name, converts any non-ASCII labels to punycode, and then checks that the result can actually be used
+/// in DNS. Returns the reason that the name is invalid, if it is.
+///
+/// Domains may only contain letters, digits, and hyphens. Subdomains may also have underscores (e.g., `_acme-challenge`)
+/// and `*` wildcard labels.
+fn normalize(str: &str, is_sub: bool) -> Result<String, String> {
+    let str = str.strip_suffix('.').unwrap_or(str);
+    if str.is_empty() {
+        return Err("it is empty".to_string());
+    }
+
+    let mut labels = Vec::new();
+    for label in str.split('.') {
+        let label = match label.is_ascii() {
+            true => label.to_ascii_lowercase(),
+            false => idna::domain_to_ascii(label).map_err(|_| format!("label {label:?} is not a valid name"))?,
+        };
+
+        if label.is_empty() {
+            return Err("it has an empty label (leading or doubled dots)".to_string());
+        } else if label.len() > MAX_LABEL_LEN {
+            return Err(format!("label {label:?} is longer than {MAX_LABEL_LEN} characters"));
+        } else if is_sub && label == "*" {
+            labels.push(label);
+            continue;
+        }
+
+        let allowed = |c: char| c.is_ascii_alphanumeric() || c == '-' || (is_sub && c == '_');
+        if let Some(c) = label.chars().find(|&c| !allowed(c)) {
+            return Err(match c {
+                c if c.is_whitespace() => "it contains whitespace".to_string(),
+                c => format!("it contains {c:?}"),
+            });
+        } else if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("label {label:?} starts or ends with a hyphen"));
+        }
+
+        labels.push(label);
+    }
+
+    let name = labels.join(".");
+    match name.len() > MAX_NAME_LEN {
+        true => Err(format!("it is longer than {MAX_NAME_LEN} characters")),
+        false => Ok(name),
+    }
+}
+
+/// Like [`str::strip_suffix`], but ignoring ASCII case.
+fn strip_suffix_ignore_case<'a>(str: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = str.len().checked_sub(suffix.len())?;
+    match str.is_char_boundary(split) && str[split..].eq_ignore_ascii_case(suffix) {
+        true => Some(&str[..split]),
+        false => None,
+    }
+}
+
+impl Deref for DomainName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Subdomain {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for DomainName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Display for Subdomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for DomainName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DomainName::parse(s)
+    }
+}
+
+impl Serialize for DomainName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl Serialize for Subdomain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for DomainName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let str = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        DomainName::parse(&str).map_err(de::Error::custom)
+    }
+}
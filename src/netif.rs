@@ -0,0 +1,215 @@
+//! Information about this machine's own network interfaces and addresses.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::api::IpAddrExt;
+
+/// An IPv6 address assigned to one of this machine's interfaces.
+#[derive(Debug, Clone)]
+pub struct LocalIpv6 {
+    pub addr: Ipv6Addr,
+    pub interface: String,
+    flags: u32,
+}
+
+// Address flags from `linux/if_addr.h`.
+const IFA_F_TEMPORARY: u32 = 0x01;
+const IFA_F_DADFAILED: u32 = 0x08;
+const IFA_F_DEPRECATED: u32 = 0x20;
+const IFA_F_TENTATIVE: u32 = 0x40;
+
+impl LocalIpv6 {
+    /// Whether this is an RFC 4941 temporary ("privacy") address, which gets rotated regularly.
+    pub fn is_temporary(&self) -> bool {
+        self.flags & IFA_F_TEMPORARY != 0
+    }
+
+    /// Whether this address is in a state where it can actually be used.
+    pub fn is_usable(&self) -> bool {
+        self.flags & (IFA_F_DADFAILED | IFA_F_DEPRECATED | IFA_F_TENTATIVE) == 0
+    }
+
+    /// Whether this address shares the given address's /64 prefix.
+    pub fn same_prefix(&self, other: &Ipv6Addr) -> bool {
+        (self.addr.to_bits() >> 64) == (other.to_bits() >> 64)
+    }
+}
+
+/// Lists every IPv6 address on this machine, along with what kind of address each one is.
+///
+/// This is only supported on Linux, where it reads `/proc/net/if_inet6`.
+#[cfg(target_os = "linux")]
+pub fn local_ipv6_addrs() -> std::io::Result<Vec<LocalIpv6>> {
+    let text = std::fs::read_to_string("/proc/net/if_inet6")?;
+
+    // Each line looks like: `20010db8000000000000000000000001 02 40 00 80 eth0`, with the fields being: address,
+    // interface index, prefix length, scope, flags, and interface name. Everything but the name is in hex.
+    let addrs = text.lines().filter_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let [addr, _index, _prefix_len, _scope, flags, interface] = fields[..] else {
+            return None;
+        };
+
+        Some(LocalIpv6 {
+            addr: Ipv6Addr::from_bits(u128::from_str_radix(addr, 16).ok()?),
+            interface: interface.to_string(),
+            flags: u32::from_str_radix(flags, 16).ok()?,
+        })
+    });
+
+    Ok(addrs.collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn local_ipv6_addrs() -> std::io::Result<Vec<LocalIpv6>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "listing local IPv6 addresses is only supported on Linux",
+    ))
+}
+
+/// Finds the gateway of this machine's default IPv4 route (i.e., the local router).
+///
+/// This is only supported on Linux, where it reads `/proc/net/route`.
+#[cfg(target_os = "linux")]
+pub fn default_gateway() -> std::io::Result<Ipv4Addr> {
+    let text = std::fs::read_to_string("/proc/net/route")?;
+
+    // After a header, each line looks like: `eth0 00000000 010200C0 0003 0 0 0 00000000 0 0 0`, with the fields being:
+    // interface name, destination, gateway, flags, ref count, use count, metric, mask, MTU, window, and IRTT. Addresses
+    // are in hex, in the machine's byte order.
+    let gateway = (text.lines().skip(1))
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [_interface, destination, gateway, flags, _, _, metric, mask, ..] = fields[..] else {
+                return None;
+            };
+
+            // RTF_UP and RTF_GATEWAY.
+            let flags = u16::from_str_radix(flags, 16).ok()?;
+            let default = destination == "00000000" && mask == "00000000" && flags & 0x3 == 0x3;
+            let gateway = u32::from_str_radix(gateway, 16).ok()?;
+            default.then(|| (metric.parse::<u32>().unwrap_or(0), Ipv4Addr::from(gateway.to_ne_bytes())))
+        })
+        .min_by_key(|(metric, _)| *metric);
+
+    gateway
+        .map(|(_, gateway)| gateway)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "there is no default IPv4 route"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn default_gateway() -> std::io::Result<Ipv4Addr> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "finding the default gateway is only supported on Linux",
+    ))
+}
+
+/// Lists every IPv4 and IPv6 address assigned to the interface with the given name.
+#[cfg(unix)]
+pub fn interface_addrs(name: &str) -> std::io::Result<Vec<IpAddr>> {
+    use std::ffi::CStr;
+
+    let mut ifaddrs = std::ptr::null_mut();
+    // SAFETY: `getifaddrs` fills in a linked list that we own until it's passed to `freeifaddrs`.
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut found = false;
+    let mut addrs = Vec::new();
+    let mut cur = ifaddrs;
+    while !cur.is_null() {
+        // SAFETY: every entry in the list is valid until `freeifaddrs` is called, as are their names and addresses.
+        // `ifa_addr` points to a `sockaddr_in` or `sockaddr_in6` whenever its family says so.
+        unsafe {
+            let ifa = &*cur;
+            cur = ifa.ifa_next;
+
+            if CStr::from_ptr(ifa.ifa_name).to_bytes() != name.as_bytes() {
+                continue;
+            }
+
+            found = true;
+            if ifa.ifa_addr.is_null() {
+                continue;
+            }
+
+            match i32::from((*ifa.ifa_addr).sa_family) {
+                libc::AF_INET => {
+                    let sin = &*ifa.ifa_addr.cast::<libc::sockaddr_in>();
+                    addrs.push(IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))));
+                },
+                libc::AF_INET6 => {
+                    let sin6 = &*ifa.ifa_addr.cast::<libc::sockaddr_in6>();
+                    addrs.push(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)));
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // SAFETY: `ifaddrs` came from `getifaddrs`, and nothing from the list is used after this.
+    unsafe { libc::freeifaddrs(ifaddrs) };
+
+    match found {
+        true => Ok(addrs),
+        false => {
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("there is no interface named {name:?}")))
+        },
+    }
+}
+
+#[cfg(not(unix))]
+pub fn interface_addrs(_name: &str) -> std::io::Result<Vec<IpAddr>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "reading interface addresses is only supported on Unix",
+    ))
+}
+
+/// If the given address is a temporary address on this machine, finds a stable address to use in its place.
+///
+/// Outgoing connections usually come from temporary addresses, so that's what Porkbun will see. Those change every day
+/// or so though, which would mean needlessly updating records (and having them point at addresses that will soon stop
+/// working). The replacement is a non-temporary address from the same interface and prefix, e.g. an EUI-64 or
+/// stable-privacy (RFC 7217) address. If there is no such address, or if the given address isn't a local one at all,
+/// the address is returned as-is.
+pub fn prefer_stable_ipv6(addr: Ipv6Addr) -> Ipv6Addr {
+    let locals = match local_ipv6_addrs() {
+        Ok(locals) => locals,
+        Err(err) => {
+            log::trace!("Could not check whether {addr} is a temporary address: {err}");
+            return addr;
+        },
+    };
+
+    let Some(current) = locals.iter().find(|local| local.addr == addr) else {
+        log::trace!("{addr} is not assigned to any local interface; using it as-is.");
+        return addr;
+    };
+
+    if !current.is_temporary() {
+        return addr;
+    }
+
+    let stable = locals.iter().find(|local| {
+        local.interface == current.interface
+            && !local.is_temporary()
+            && local.is_usable()
+            && local.same_prefix(&addr)
+            && local.addr.private_kind().is_none()
+    });
+
+    match stable {
+        Some(stable) => {
+            log::debug!("{addr} is a temporary address; using stable address {} instead.", stable.addr);
+            stable.addr
+        },
+        None => {
+            log::debug!("{addr} is a temporary address, but no stable address was found on {}.", current.interface);
+            addr
+        },
+    }
+}
@@ -0,0 +1,95 @@
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+use serde_json::{Value as JsonValue, json};
+
+use super::{Event, EventKind, Notifier};
+use crate::config::{DiscordConfig, NotifyEvents};
+use crate::providers::http_client;
+
+/// Embed colours for each kind of event.
+const CHANGE_COLOR: u32 = 0x2ECC71;
+const FAILURE_COLOR: u32 = 0xE74C3C;
+
+/// Posts each event to a Discord channel as an embed, through a webhook.
+#[derive(Debug)]
+pub struct Discord {
+    reqwest: reqwest::Client,
+    url: Url,
+    events: NotifyEvents,
+}
+
+impl Discord {
+    pub fn new(config: DiscordConfig) -> eyre::Result<Self> {
+        Ok(Self {
+            reqwest: http_client([]),
+            url: Url::parse(&config.url).wrap_err("Invalid notify.discord.url")?,
+            events: config.events,
+        })
+    }
+
+    /// Builds the webhook payload for an event.
+    fn payload(event: &Event) -> JsonValue {
+        let field = |name: &str, value: &str| json!({ "name": name, "value": value, "inline": true });
+
+        let mut fields = Vec::new();
+        if let Some(target) = &event.target {
+            fields.push(field("Domain", target));
+        }
+        if let Some(typ) = event.typ {
+            fields.push(field("Type", typ));
+        }
+
+        let (color, description) = match event.event {
+            EventKind::Change => {
+                let old = event.old.as_deref().unwrap_or("none");
+                let new = event.new.as_deref().unwrap_or("?");
+                (CHANGE_COLOR, format!("`{old}` → `{new}`"))
+            },
+            EventKind::Failure => (FAILURE_COLOR, event.message()),
+        };
+
+        json!({
+            "username": env!("CARGO_PKG_NAME"),
+            "embeds": [{
+                "title": event.title(),
+                "description": description,
+                "color": color,
+                "fields": fields,
+                "footer": { "text": event.hostname },
+                "timestamp": event.timestamp,
+            }],
+        })
+    }
+}
+
+impl Notifier for Discord {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    fn events(&self) -> NotifyEvents {
+        self.events
+    }
+
+    fn notify<'a>(&'a self, event: &'a Event) -> LocalBoxFuture<'a, eyre::Result<()>> {
+        async move {
+            // The webhook URL has a token in it, so it's left out of logs (and out of any errors that end up there).
+            log::trace!("Sending POST request to Discord webhook");
+            let res = (self.reqwest.post(self.url.clone()))
+                .json(&Self::payload(event))
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)
+                .wrap_err("POST request failed")?;
+
+            let status = res.status();
+            match status.is_success() {
+                true => Ok(()),
+                false => Err(eyre!("Received {status}: {}", res.text().await.unwrap_or_default())),
+            }
+        }
+        .boxed_local()
+    }
+}
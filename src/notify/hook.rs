@@ -0,0 +1,85 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use tokio::process::Command;
+
+use super::{Event, Notifier};
+use crate::config::NotifyEvents;
+
+/// How long a hook can run for before it's killed.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Runs a program for each event, with the details of the event given as environment variables.
+///
+/// The program is run directly, not through a shell. It's given `EVENT` (`change` or `failure`), `TARGET`,
+/// `RECORD_TYPE`, `OLD_IP`, `NEW_IP`, `ERROR`, and `TIMESTAMP`; any that don't apply to the event are left empty.
+#[derive(Debug)]
+pub struct Hook {
+    name: &'static str,
+    command: Vec<String>,
+    events: NotifyEvents,
+}
+
+impl Hook {
+    pub fn new(name: &'static str, command: Vec<String>, events: NotifyEvents) -> Self {
+        Self { name, command, events }
+    }
+}
+
+impl Notifier for Hook {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn events(&self) -> NotifyEvents {
+        self.events
+    }
+
+    fn notify<'a>(&'a self, event: &'a Event) -> LocalBoxFuture<'a, eyre::Result<()>> {
+        async move {
+            let program = &self.command[0];
+            log::debug!("Running {program}");
+
+            let env = [
+                ("EVENT", "event"),
+                ("TARGET", "target"),
+                ("RECORD_TYPE", "type"),
+                ("OLD_IP", "old"),
+                ("NEW_IP", "new"),
+                ("ERROR", "error"),
+                ("TIMESTAMP", "timestamp"),
+            ];
+
+            let output = Command::new(program)
+                .args(&self.command[1..])
+                .envs(env.map(|(var, placeholder)| (var, event.placeholder(placeholder))))
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .output();
+
+            let output = tokio::time::timeout(TIMEOUT, output)
+                .await
+                .map_err(|_| eyre!("{program} did not finish within {} seconds", TIMEOUT.as_secs()))?
+                .wrap_err_with(|| format!("Failed to run {program}"))?;
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if !stdout.trim().is_empty() {
+                log::debug!("Output from {program}: {}", stdout.trim());
+            }
+
+            match output.status.success() {
+                true => Ok(()),
+                false => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    Err(eyre!("{program} exited with {}: {}", output.status, stderr.trim()))
+                },
+            }
+        }
+        .boxed_local()
+    }
+}
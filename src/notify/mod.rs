@@ -0,0 +1,221 @@
+//! Notifications sent out when records change or updates fail.
+
+mod discord;
+mod hook;
+mod ntfy;
+mod webhook;
+
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+
+pub use self::discord::Discord;
+pub use self::hook::Hook;
+pub use self::ntfy::Ntfy;
+pub use self::webhook::Webhook;
+use crate::api::HOSTNAME;
+use crate::config::NotifyEvents;
+use crate::report::{Report, TargetAction};
+
+/// Something that happened during a run that someone might want to know about.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    /// When the run finished, in RFC 3339 format.
+    pub timestamp: String,
+    /// The name of the machine that the event happened on.
+    pub hostname: String,
+    /// The target that the event is about, if it's about any one of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub typ: Option<&'static str>,
+    /// What the record held before it was changed, if it existed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// What the record holds now, or should have been changed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    /// A target's record was created or changed.
+    Change,
+    /// Something went wrong.
+    Failure,
+}
+
+impl NotifyEvents {
+    /// Checks whether a notifier with this setting should be sent the given kind of event.
+    pub fn includes(self, kind: EventKind) -> bool {
+        match self {
+            NotifyEvents::All => true,
+            NotifyEvents::Change => kind == EventKind::Change,
+            NotifyEvents::Failure => kind == EventKind::Failure,
+        }
+    }
+}
+
+impl Event {
+    /// Works out what to notify about after a run: one event per record that was changed or failed to update, plus one
+    /// for any errors that weren't tied to a target.
+    pub fn from_report(report: &Report) -> Vec<Event> {
+        let event = |event, target: Option<&str>, typ, error| Event {
+            event,
+            timestamp: report.timestamp.clone(),
+            hostname: HOSTNAME.clone(),
+            target: target.map(str::to_string),
+            typ,
+            old: None,
+            new: None,
+            error,
+        };
+
+        let mut events = Vec::new();
+        let mut failed = 0;
+        for result in &report.targets {
+            let kind = match result.action {
+                // Trial targets' changes were never actually made, so there's nothing to tell anyone about.
+                TargetAction::Created | TargetAction::Updated if result.dry_run => continue,
+                TargetAction::Created | TargetAction::Updated => EventKind::Change,
+                TargetAction::Failed => EventKind::Failure,
+                TargetAction::Unchanged => continue,
+            };
+
+            failed += (kind == EventKind::Failure) as usize;
+            events.push(Event {
+                old: result.old.clone(),
+                new: Some(result.new.clone()),
+                ..event(kind, Some(&result.target), Some(result.typ), result.error.clone())
+            });
+        }
+
+        if let Some(error) = &report.error {
+            events.push(event(EventKind::Failure, None, None, Some(error.clone())));
+        } else if report.errors > failed {
+            let n = report.errors - failed;
+            let error = format!(
+                "{n} {errors} occurred during the update; see logs for details",
+                errors = pluralize!("other error", "other errors", n),
+            );
+            events.push(event(EventKind::Failure, None, None, Some(error)));
+        }
+
+        events
+    }
+
+    /// A short summary of the event, for notifiers that show a title.
+    pub fn title(&self) -> String {
+        match (self.event, &self.target) {
+            (EventKind::Change, Some(target)) => format!("{target} updated"),
+            (EventKind::Change, None) => format!("Records updated on {}", self.hostname),
+            (EventKind::Failure, Some(target)) => format!("{target} failed to update"),
+            (EventKind::Failure, None) => format!("Update failed on {}", self.hostname),
+        }
+    }
+
+    /// A one-line description of the event, for notifiers that show plain text.
+    pub fn message(&self) -> String {
+        let typ = self.typ.unwrap_or("DNS");
+        let new = self.new.as_deref().unwrap_or("?");
+        match (self.event, &self.old, &self.error) {
+            (EventKind::Change, Some(old), _) => format!("{typ} record changed from {old} to {new}."),
+            (EventKind::Change, None, _) => format!("{typ} record created with {new}."),
+            (EventKind::Failure, _, Some(error)) => error.clone(),
+            (EventKind::Failure, _, None) => "Unknown error.".to_string(),
+        }
+    }
+
+    /// Gets the value of one of the [placeholders][PLACEHOLDERS] that can be used in notification templates.
+    pub fn placeholder(&self, name: &str) -> String {
+        let opt = |value: &Option<String>| value.clone().unwrap_or_default();
+        match name {
+            "event" => match self.event {
+                EventKind::Change => "change".to_string(),
+                EventKind::Failure => "failure".to_string(),
+            },
+            "timestamp" => self.timestamp.clone(),
+            "hostname" => self.hostname.clone(),
+            "target" => opt(&self.target),
+            "type" => self.typ.unwrap_or_default().to_string(),
+            "old" => opt(&self.old),
+            "new" => opt(&self.new),
+            "error" => opt(&self.error),
+            _ => String::new(),
+        }
+    }
+}
+
+/// The placeholders that can be used in notification templates.
+pub const PLACEHOLDERS: &[&str] = &[
+    "event",
+    "timestamp",
+    "hostname",
+    "target",
+    "type",
+    "old",
+    "new",
+    "error",
+];
+
+/// Somewhere that events can be sent to.
+pub trait Notifier {
+    /// A human-readable name for this notifier, used to prefix log messages.
+    fn name(&self) -> &'static str;
+
+    /// Which events this notifier wants to be sent.
+    fn events(&self) -> NotifyEvents;
+
+    /// Sends a single event.
+    fn notify<'a>(&'a self, event: &'a Event) -> LocalBoxFuture<'a, eyre::Result<()>>;
+}
+
+/// Fills in a template's placeholders with an event's details, passing each value through `escape` first.
+///
+/// Only `{name}`s made up of lowercase letters are treated as placeholders, so that templates for JSON bodies can still
+/// use braces.
+fn fill_template(template: &str, event: &Event, escape: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some((start, name)) = next_placeholder(rest) {
+        out += &rest[..start];
+        match PLACEHOLDERS.contains(&name) {
+            true => out += &escape(&event.placeholder(name)),
+            false => out += &rest[start..start + name.len() + 2],
+        }
+        rest = &rest[start + name.len() + 2..];
+    }
+    out + rest
+}
+
+/// Checks that a template only uses placeholders that exist.
+fn check_template(template: &str) -> eyre::Result<()> {
+    let mut rest = template;
+    while let Some((start, name)) = next_placeholder(rest) {
+        if !PLACEHOLDERS.contains(&name) {
+            let expected = PLACEHOLDERS.iter().map(|p| format!("{{{p}}}")).collect::<Vec<_>>();
+            return Err(eyre::eyre!("Unknown placeholder {{{name}}} (expected one of {})", expected.join(", ")));
+        }
+        rest = &rest[start + name.len() + 2..];
+    }
+    Ok(())
+}
+
+/// Finds the next `{name}` in a string, returning where it starts and the name inside the braces.
+fn next_placeholder(str: &str) -> Option<(usize, &str)> {
+    let mut offset = 0;
+    while let Some(start) = str[offset..].find('{').map(|i| offset + i) {
+        let name_len = str[start + 1..]
+            .find(|c: char| !c.is_ascii_lowercase())
+            .unwrap_or(str.len() - start - 1);
+        let name = &str[start + 1..start + 1 + name_len];
+        if !name.is_empty() && str[start + 1 + name_len..].starts_with('}') {
+            return Some((start, name));
+        }
+        offset = start + 1;
+    }
+    None
+}
@@ -0,0 +1,110 @@
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+use reqwest::header::{AUTHORIZATION, HeaderName, HeaderValue};
+
+use super::{Event, EventKind, Notifier};
+use crate::config::{NotifyEvents, NtfyConfig, NtfyPriority};
+use crate::providers::http_client;
+
+/// Publishes each event to an [ntfy](https://ntfy.sh) topic, for push notifications on a phone or desktop.
+#[derive(Debug)]
+pub struct Ntfy {
+    reqwest: reqwest::Client,
+    url: Url,
+    events: NotifyEvents,
+    priority: NtfyPriority,
+    /// A username and password to log in with, if the topic needs one. Tokens are sent as a default header instead.
+    login: Option<(String, String)>,
+}
+
+impl Ntfy {
+    pub fn new(config: NtfyConfig) -> eyre::Result<Self> {
+        let url = Url::parse(&config.url).wrap_err("Invalid notify.ntfy.url")?;
+
+        let login = match (config.username, config.password) {
+            (Some(user), Some(pass)) => Some((user, pass)),
+            (None, None) => None,
+            _ => return Err(eyre!("notify.ntfy.username and notify.ntfy.password must be given together")),
+        };
+
+        let headers = match config.token {
+            Some(_) if login.is_some() => {
+                return Err(eyre!("notify.ntfy.token cannot be used with a username and password"));
+            },
+            Some(token) => {
+                let auth = HeaderValue::from_str(&format!("Bearer {token}"))
+                    .wrap_err("ntfy access token contains invalid characters")?;
+                vec![(AUTHORIZATION, auth)]
+            },
+            None => vec![],
+        };
+
+        Ok(Self {
+            reqwest: http_client(headers),
+            url,
+            events: config.events,
+            priority: config.priority,
+            login,
+        })
+    }
+}
+
+impl Notifier for Ntfy {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    fn events(&self) -> NotifyEvents {
+        self.events
+    }
+
+    fn notify<'a>(&'a self, event: &'a Event) -> LocalBoxFuture<'a, eyre::Result<()>> {
+        async move {
+            let priority = match self.priority {
+                NtfyPriority::Min => "min",
+                NtfyPriority::Low => "low",
+                NtfyPriority::Default => "default",
+                NtfyPriority::High => "high",
+                NtfyPriority::Urgent => "urgent",
+            };
+
+            // ntfy shows tags that are emoji shortcodes as emoji, in front of the title.
+            let tags = match event.event {
+                EventKind::Change => "globe_with_meridians",
+                EventKind::Failure => "warning",
+            };
+
+            // Titles go in a header, which needs to be ASCII; ntfy decodes RFC 2047 encoded-words, so anything else can
+            // be sent that way.
+            let title = HeaderValue::from_str(&event.title())
+                .or_else(|_| HeaderValue::from_str(&rfc2047(&event.title())))
+                .wrap_err("ntfy title contains invalid characters")?;
+
+            log::trace!("Sending POST request to {}", self.url);
+            let mut req = (self.reqwest.post(self.url.clone()))
+                .header(HeaderName::from_static("title"), title)
+                .header(HeaderName::from_static("priority"), priority)
+                .header(HeaderName::from_static("tags"), tags)
+                .body(event.message());
+            if let Some((user, pass)) = &self.login {
+                req = req.basic_auth(user, Some(pass));
+            }
+
+            let res = req.send().await.wrap_err("POST request failed")?;
+            let status = res.status();
+            match status.is_success() {
+                true => Ok(()),
+                false => Err(eyre!("Received {status}: {}", res.text().await.unwrap_or_default())),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Encodes a string as an RFC 2047 "encoded-word", so that it can be put in a header.
+fn rfc2047(str: &str) -> String {
+    let hex = str.bytes().map(|b| format!("={b:02X}")).collect::<String>();
+    format!("=?UTF-8?Q?{hex}?=")
+}
@@ -0,0 +1,93 @@
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+use reqwest::header::{CONTENT_TYPE, HeaderName, HeaderValue};
+
+use super::{Event, Notifier, check_template, fill_template};
+use crate::config::{NotifyEvents, WebhookConfig};
+use crate::providers::http_client;
+
+/// POSTs each event to an arbitrary URL, either as JSON or using a custom body template.
+#[derive(Debug)]
+pub struct Webhook {
+    reqwest: reqwest::Client,
+    url: Url,
+    events: NotifyEvents,
+    body: Option<String>,
+    content_type: String,
+}
+
+impl Webhook {
+    pub fn new(config: WebhookConfig) -> eyre::Result<Self> {
+        let url = Url::parse(&config.url).wrap_err("Invalid notify.webhook.url")?;
+        if let Some(body) = &config.body {
+            check_template(body).wrap_err("Invalid notify.webhook.body")?;
+        }
+
+        let headers = (config.headers.iter())
+            .map(|(name, value)| {
+                let name =
+                    HeaderName::try_from(name).wrap_err_with(|| format!("Invalid webhook header name {name:?}"))?;
+                let value =
+                    HeaderValue::try_from(value).wrap_err_with(|| format!("Invalid value for header {name}"))?;
+                Ok((name, value))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            reqwest: http_client(headers),
+            url,
+            events: config.events,
+            body: config.body,
+            content_type: config.content_type,
+        })
+    }
+
+    /// Works out the body to send for an event.
+    fn body(&self, event: &Event) -> eyre::Result<String> {
+        let Some(template) = &self.body else {
+            return Ok(serde_json::to_string(event)?);
+        };
+
+        // Values are escaped for JSON bodies, so that e.g. quotes in error messages don't break them.
+        Ok(match self.content_type.contains("json") {
+            true => fill_template(template, event, |value| {
+                let quoted = serde_json::Value::from(value).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }),
+            false => fill_template(template, event, str::to_string),
+        })
+    }
+}
+
+impl Notifier for Webhook {
+    fn name(&self) -> &'static str {
+        "Webhook"
+    }
+
+    fn events(&self) -> NotifyEvents {
+        self.events
+    }
+
+    fn notify<'a>(&'a self, event: &'a Event) -> LocalBoxFuture<'a, eyre::Result<()>> {
+        async move {
+            log::trace!("Sending POST request to {}", self.url);
+            let res = self
+                .reqwest
+                .post(self.url.clone())
+                .header(CONTENT_TYPE, &self.content_type)
+                .body(self.body(event)?)
+                .send()
+                .await
+                .wrap_err("POST request failed")?;
+
+            let status = res.status();
+            match status.is_success() {
+                true => Ok(()),
+                false => Err(eyre!("Received {status}: {}", res.text().await.unwrap_or_default())),
+            }
+        }
+        .boxed_local()
+    }
+}
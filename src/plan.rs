@@ -0,0 +1,224 @@
+//! Records of the changes made to DNS records over the course of a run (or that would have been made, in a dry run).
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use anstyle::{AnsiColor, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{DNSRecord, RecordData, RecordType};
+use crate::config::Target;
+use crate::names::{DomainName, Subdomain};
+
+/// A list of changes to be applied later, as written by the `plan` subcommand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    /// When the plan was made, in RFC 3339 format.
+    pub timestamp: String,
+    pub changes: Vec<Change>,
+}
+
+/// A single change to a single DNS record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub action: Action,
+    /// The domain that the record belongs to.
+    pub domain: String,
+    /// The record's fully-qualified name.
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: RecordType,
+    /// The ID of the existing record, for edits and deletions (or of the new record, once it has been created). Records
+    /// edited or deleted by name and type don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The state of the record before the change, for edits and deletions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<RecordState>,
+    /// The state of the record after the change, for creations and edits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<RecordState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Create,
+    Edit,
+    Delete,
+}
+
+/// The parts of a DNS record that can be changed without changing which record it is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordState {
+    pub content: String,
+    pub ttl: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prio: Option<u32>,
+}
+
+impl Change {
+    /// A new record being created for the given target.
+    pub fn create(target: &Target, data: &RecordData) -> Self {
+        Self {
+            action: Action::Create,
+            domain: target.domain().to_string(),
+            name: target.fqdn(),
+            typ: data.typ.clone(),
+            id: None,
+            old: None,
+            new: Some(RecordState::new(target, data)),
+        }
+    }
+
+    /// An existing record being edited to have new content, TTL, and/or priority.
+    pub fn edit(target: &Target, record: &DNSRecord, data: &RecordData) -> Self {
+        Self {
+            action: Action::Edit,
+            domain: target.domain().to_string(),
+            name: record.name.clone(),
+            typ: record.typ.clone(),
+            id: Some(record.id.clone()),
+            old: Some(RecordState::from(record)),
+            new: Some(RecordState::new(target, data)),
+        }
+    }
+
+    /// An existing record being edited by name and type, without having looked up what it was before.
+    pub fn edit_blind(target: &Target, data: &RecordData) -> Self {
+        Self {
+            action: Action::Edit,
+            ..Self::create(target, data)
+        }
+    }
+
+    /// Every existing record with a name and type being deleted, without having looked up what they were.
+    pub fn delete_blind(domain: &DomainName, subdomain: Option<&Subdomain>, typ: &str) -> Self {
+        Self {
+            action: Action::Delete,
+            domain: domain.to_string(),
+            name: domain.fqdn(subdomain),
+            typ: RecordType::from(typ),
+            id: None,
+            old: None,
+            new: None,
+        }
+    }
+
+    /// An existing record being deleted.
+    pub fn delete(domain: &str, record: &DNSRecord) -> Self {
+        Self {
+            action: Action::Delete,
+            domain: domain.to_string(),
+            name: record.name.clone(),
+            typ: record.typ.clone(),
+            id: Some(record.id.clone()),
+            old: Some(RecordState::from(record)),
+            new: None,
+        }
+    }
+}
+
+impl RecordState {
+    /// The state that a record will have once it has been created or edited for the given target.
+    pub fn new(target: &Target, data: &RecordData) -> Self {
+        Self {
+            content: data.content.clone(),
+            ttl: Some(target.ttl()),
+            prio: data.prio,
+        }
+    }
+
+    /// Converts the state back into the data needed to create or edit a record.
+    pub fn to_data(&self, typ: &RecordType) -> RecordData {
+        RecordData {
+            typ: typ.clone(),
+            content: self.content.clone(),
+            prio: self.prio,
+        }
+    }
+}
+
+impl From<&DNSRecord> for RecordState {
+    fn from(record: &DNSRecord) -> Self {
+        Self {
+            content: record.content.clone(),
+            ttl: record.ttl,
+            prio: record.prio,
+        }
+    }
+}
+
+/// Prints a list of changes to `stdout` as a human-readable diff, grouped by record name.
+///
+/// Colours are added automatically when `stdout` is a terminal (again, by [`anstream`]).
+pub fn print_diff(changes: &[Change]) -> io::Result<()> {
+    const CREATE: Style = AnsiColor::Green.on_default();
+    const EDIT: Style = AnsiColor::Yellow.on_default();
+    const DELETE: Style = AnsiColor::Red.on_default();
+    const NAME: Style = Style::new().bold();
+
+    let mut output = anstream::stdout().lock();
+    if changes.is_empty() {
+        writeln!(output, "No changes.")?;
+        return output.flush();
+    }
+
+    let mut by_name = BTreeMap::<&str, Vec<&Change>>::new();
+    for change in changes {
+        by_name.entry(&change.name).or_default().push(change);
+    }
+
+    for (name, changes) in by_name {
+        writeln!(output, "{NAME}{name}{NAME:#}")?;
+        for change in changes {
+            let typ = &change.typ;
+            let content = |state: &RecordState| match typ {
+                RecordType::A | RecordType::AAAA => state.content.clone(),
+                _ => format!("{:?}", state.content),
+            };
+            let ttl = |state: &RecordState| match state.ttl {
+                Some(ttl) => ttl.to_string(),
+                None => "default".to_string(),
+            };
+
+            match (change.action, &change.old, &change.new) {
+                (Action::Create, _, Some(new)) => {
+                    write!(output, "{CREATE}  + {typ:<5} {}{CREATE:#}", content(new))?;
+                    write!(output, " (TTL {}", ttl(new))?;
+                    if let Some(prio) = new.prio {
+                        write!(output, ", prio {prio}")?;
+                    }
+                    writeln!(output, ")")?;
+                },
+                (Action::Edit, Some(old), Some(new)) => {
+                    // Only show what's actually changing.
+                    let mut parts = Vec::new();
+                    if old.content != new.content {
+                        parts.push(format!("{} → {}", content(old), content(new)));
+                    } else {
+                        parts.push(content(new));
+                    }
+                    if old.ttl != new.ttl {
+                        parts.push(format!("TTL {} → {}", ttl(old), ttl(new)));
+                    }
+                    if new.prio.is_some() && old.prio != new.prio {
+                        let prio = |prio: Option<u32>| prio.map_or("none".to_string(), |p| p.to_string());
+                        parts.push(format!("prio {} → {}", prio(old.prio), prio(new.prio)));
+                    }
+                    writeln!(output, "{EDIT}  ~ {typ:<5} {}{EDIT:#}", parts.join(", "))?;
+                },
+                (Action::Edit, None, Some(new)) => {
+                    writeln!(output, "{EDIT}  ~ {typ:<5} ? → {}{EDIT:#}", content(new))?;
+                },
+                (Action::Delete, Some(old), _) => {
+                    writeln!(output, "{DELETE}  - {typ:<5} {}{DELETE:#}", content(old))?;
+                },
+                // Changes are only ever built with the constructors above, so these don't show up.
+                _ => {},
+            }
+        }
+    }
+
+    output.flush()
+}
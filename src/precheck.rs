@@ -0,0 +1,160 @@
+//! Checks targets' records over DNS before involving Porkbun's API at all.
+
+use std::net::{IpAddr, SocketAddr};
+
+use eyre::{WrapErr, eyre};
+use futures::lock::Mutex;
+
+use crate::config::Target;
+use crate::sources::query_addresses;
+
+/// Porkbun's authoritative nameservers, which every domain using Porkbun's DNS is delegated to.
+pub const NAMESERVERS: [&str; 4] = [
+    "curitiba.ns.porkbun.com",
+    "fortaleza.ns.porkbun.com",
+    "maceio.ns.porkbun.com",
+    "salvador.ns.porkbun.com",
+];
+
+/// Asks Porkbun's nameservers what a target's records currently hold.
+///
+/// Nameservers answer straight from Porkbun's own data, so there's no caching to worry about; if they say a record
+/// already holds the right address, there's no need to look it up (or edit it) through the API.
+#[derive(Debug, Default)]
+pub struct Precheck {
+    /// The nameservers' addresses, looked up the first time they're needed.
+    servers: Mutex<Option<Vec<SocketAddr>>>,
+}
+
+impl Precheck {
+    /// Checks whether a target's record of the given address's type holds exactly that address and nothing else.
+    ///
+    /// Anything that gets in the way of a clear answer (an unreachable nameserver, a missing record, a domain that isn't
+    /// using Porkbun's DNS) counts as not current, so that the target gets handled through the API as usual.
+    pub async fn is_current(&self, target: &Target, addr: IpAddr) -> bool {
+        let servers = match self.servers().await {
+            Ok(servers) => servers,
+            Err(err) => {
+                log::debug!("{target}: Skipping DNS pre-check: {err:#}");
+                return false;
+            },
+        };
+
+        // All of the nameservers serve the same data, so the first one to answer is enough.
+        let fqdn = target.fqdn();
+        for server in servers {
+            match query_addresses(server, &fqdn, addr.is_ipv6(), false).await {
+                Ok(found) => {
+                    log::trace!("{target}: {server} answered with {found:?}.");
+                    return found == [addr];
+                },
+                Err(err) => log::trace!("{target}: {server} did not answer: {err:#}"),
+            }
+        }
+
+        false
+    }
+
+    /// Checks whether a target has any records of the given family at all.
+    ///
+    /// A nameserver that answers without any addresses is no different from one that doesn't answer, so unlike
+    /// [`is_current`][Self::is_current], not being able to get an answer counts as there being no records. Only failing
+    /// to find the nameservers at all counts as there possibly being some.
+    pub async fn has_records(&self, target: &Target, ipv6: bool) -> bool {
+        let Ok(servers) = self.servers().await else {
+            return true;
+        };
+
+        let fqdn = target.fqdn();
+        for server in servers {
+            if let Ok(found) = query_addresses(server, &fqdn, ipv6, false).await {
+                log::trace!("{target}: {server} answered with {found:?}.");
+                return !found.is_empty();
+            }
+        }
+
+        false
+    }
+
+    /// Gets the addresses of Porkbun's nameservers, looking them up if this is the first time they're needed.
+    async fn servers(&self) -> eyre::Result<Vec<SocketAddr>> {
+        // Held for the whole lookup, so that every target doesn't look up the same nameservers at once.
+        let mut cache = self.servers.lock().await;
+        if let Some(servers) = &*cache {
+            return Ok(servers.clone());
+        }
+
+        let mut servers = Vec::with_capacity(NAMESERVERS.len());
+        for name in NAMESERVERS {
+            let res = tokio::net::lookup_host((name, 53)).await;
+            match res.wrap_err_with(|| format!("Failed to look up nameserver {name}")) {
+                // Not every machine can reach IPv6 servers, so only IPv4 ones are used.
+                Ok(mut addrs) => servers.extend(addrs.find(SocketAddr::is_ipv4)),
+                Err(err) => log::debug!("{err:#}"),
+            }
+        }
+
+        if servers.is_empty() {
+            return Err(eyre!("Could not find any of Porkbun's nameservers"));
+        }
+
+        *cache = Some(servers.clone());
+        Ok(servers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use tokio::net::UdpSocket;
+
+    use super::*;
+    use crate::names::DomainName;
+
+    /// Starts a nameserver on localhost that answers every query with the given A records (or with none at all).
+    async fn nameserver(answers: Vec<Ipv4Addr>) -> SocketAddr {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                let query = &buf[..len];
+                let mut res = Vec::new();
+                res.extend(&query[..2]);
+                // A response with no error, echoing the one question.
+                res.extend([0x81, 0x80, 0, 1]);
+                res.extend((answers.len() as u16).to_be_bytes());
+                res.extend([0, 0, 0, 0]);
+                res.extend(&query[12..]);
+                for answer in &answers {
+                    // A pointer back to the question's name, then type A, class IN, a TTL of 600, and the address.
+                    res.extend([0xC0, 12, 0, 1, 0, 1, 0, 0, 0x02, 0x58, 0, 4]);
+                    res.extend(answer.octets());
+                }
+                let _ = socket.send_to(&res, from).await;
+            }
+        });
+        addr
+    }
+
+    fn precheck(server: SocketAddr) -> Precheck {
+        Precheck {
+            servers: Mutex::new(Some(vec![server])),
+        }
+    }
+
+    #[tokio::test]
+    async fn has_records_with_addresses() {
+        let precheck = precheck(nameserver(vec![Ipv4Addr::new(203, 0, 113, 10)]).await);
+        let target = Target::from_domain(DomainName::parse("example.com").unwrap());
+        assert!(precheck.has_records(&target, false).await);
+    }
+
+    #[tokio::test]
+    async fn has_no_records_with_empty_answer() {
+        let precheck = precheck(nameserver(Vec::new()).await);
+        let target = Target::from_domain(DomainName::parse("example.com").unwrap());
+        assert!(!precheck.has_records(&target, false).await);
+    }
+}
@@ -0,0 +1,102 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+
+use super::{Provider, http_client};
+use crate::config::DuckDnsConfig;
+
+const UPDATE_URL: &str = "https://www.duckdns.org/update";
+
+/// Updates `duckdns.org` hostnames.
+///
+/// DuckDNS has a single update endpoint that takes every domain at once, and it has no way to retrieve a domain's
+/// current addresses. So, unlike Porkbun, every run sends an update.
+#[derive(Debug)]
+pub struct DuckDns {
+    reqwest: reqwest::Client,
+    token: String,
+    domains: Vec<String>,
+}
+
+impl DuckDns {
+    pub fn new(config: DuckDnsConfig) -> Self {
+        Self {
+            reqwest: http_client([]),
+            token: config.token,
+            domains: config.domains,
+        }
+    }
+
+    async fn send_update(&self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> eyre::Result<()> {
+        let domains = self.domains.join(",");
+        let mut query = vec![("domains", domains), ("token", self.token.clone())];
+
+        // NB: if `ip` is left off, DuckDNS will fill in whichever IPv4 address it sees the request coming from. There's
+        // no way to tell it to leave the A record alone, so IPv6-only setups will still have their IPv4 touched.
+        if let Some(addr) = ipv4 {
+            query.push(("ip", addr.to_string()));
+        }
+        if let Some(addr) = ipv6 {
+            query.push(("ipv6", addr.to_string()));
+        }
+
+        log::trace!("Sending GET request to {UPDATE_URL} for domains {}", query[0].1);
+
+        let res = self
+            .reqwest
+            .get(UPDATE_URL)
+            .query(&query)
+            .send()
+            .await
+            // The URL has the token in its query string, so it's kept out of any errors that end up in the logs.
+            .map_err(reqwest::Error::without_url)
+            .wrap_err("GET request failed")?
+            .text()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .wrap_err("Failed to read GET response body")?;
+
+        // DuckDNS's response is always plaintext: "OK" or "KO", with no further explanation.
+        match res.trim() {
+            "OK" => Ok(()),
+            "KO" => Err(eyre!("DuckDNS rejected the update (check the token and domain names)")),
+            other => Err(eyre!("Unexpected response from DuckDNS: {other:?}")),
+        }
+    }
+}
+
+impl Provider for DuckDns {
+    fn name(&self) -> &'static str {
+        "DuckDNS"
+    }
+
+    fn update<'a>(
+        &'a self,
+        ipv4: Option<Ipv4Addr>,
+        ipv6: Option<Ipv6Addr>,
+        dry_run: bool,
+    ) -> LocalBoxFuture<'a, usize> {
+        async move {
+            if self.domains.is_empty() {
+                return 0;
+            }
+
+            let domains = self.domains.join(", ");
+            if dry_run {
+                log::info!("DuckDNS: Would update {domains}.");
+                return 0;
+            }
+
+            if let Err(err) = self.send_update(ipv4, ipv6).await {
+                log::error!("DuckDNS: Failed to update {domains}: {err:#}");
+                return 1;
+            }
+
+            log::info!("DuckDNS: Updated {domains}.");
+            0
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,135 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::StatusCode;
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+
+use super::{Provider, http_client};
+use crate::api::IpAddrExt;
+use crate::config::{GandiConfig, Target};
+use crate::names::Subdomain;
+
+const BASE_URL: &str = "https://api.gandi.net/v5/livedns";
+
+/// Updates records through Gandi's LiveDNS API.
+#[derive(Debug)]
+pub struct Gandi {
+    reqwest: reqwest::Client,
+    targets: Vec<Target>,
+}
+
+/// A LiveDNS "resource record set": every value for a single name and type.
+#[derive(Debug, Serialize, Deserialize)]
+struct RRSet {
+    rrset_values: Vec<String>,
+    rrset_ttl: u32,
+}
+
+impl Gandi {
+    pub fn new(config: GandiConfig) -> eyre::Result<Self> {
+        let auth = HeaderValue::from_str(&format!("Apikey {}", config.api_key))
+            .wrap_err("Gandi API key contains invalid characters")?;
+        Ok(Self {
+            reqwest: http_client([(reqwest::header::AUTHORIZATION, auth)]),
+            targets: config.targets,
+        })
+    }
+
+    /// Gets the URL for the record set of the given target's name and type.
+    fn rrset_url(target: &Target, addr: IpAddr) -> String {
+        // LiveDNS uses '@' for the root of the domain, just like our own config does.
+        let name = target.subdomain().map_or("@", Subdomain::as_str);
+        format!("{BASE_URL}/domains/{}/records/{name}/{}", target.domain(), addr.dns_type())
+    }
+
+    /// Fetches the current record set for the given target, returning `None` if it does not exist yet.
+    async fn get_rrset(&self, url: &str) -> eyre::Result<Option<RRSet>> {
+        log::trace!("Sending GET request to {url}");
+        let res = self.reqwest.get(url).send().await.wrap_err("GET request failed")?;
+        match res.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let rrset = res.json().await.wrap_err("Response was not the expected type")?;
+                Ok(Some(rrset))
+            },
+            status => Err(eyre!("Received {status} from Gandi API: {}", res.text().await.unwrap_or_default())),
+        }
+    }
+
+    /// Replaces the record set at the given URL, creating it if it does not yet exist.
+    async fn put_rrset(&self, url: &str, rrset: &RRSet) -> eyre::Result<()> {
+        log::trace!("Sending PUT request to {url} with payload {rrset:?}");
+        let res = self.reqwest.put(url).json(rrset).send().await.wrap_err("PUT request failed")?;
+        match res.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(eyre!("Received {status} from Gandi API: {}", res.text().await.unwrap_or_default())),
+        }
+    }
+
+    async fn handle_target(&self, target: &Target, addr: IpAddr, dry_run: bool) -> eyre::Result<()> {
+        let addr = target.address_for(addr);
+        let dns_type = addr.dns_type();
+        let url = Self::rrset_url(target, addr);
+
+        let existing = self.get_rrset(&url).await.wrap_err("Failed to fetch existing record")?;
+        let desired = RRSet {
+            rrset_values: vec![addr.to_string()],
+            rrset_ttl: target.ttl(),
+        };
+
+        match existing {
+            Some(rrset) if rrset.rrset_values == desired.rrset_values && rrset.rrset_ttl == desired.rrset_ttl => {
+                log::debug!("Gandi: {target}: Found existing {dns_type} record with content {addr}. Nothing to do.");
+            },
+            Some(rrset) => {
+                if !dry_run {
+                    self.put_rrset(&url, &desired).await.wrap_err("Failed to edit DNS record")?;
+                }
+                let old = rrset.rrset_values.join(", ");
+                log::info!("Gandi: {target}: Edited existing {dns_type} record from {old} to {addr}.");
+            },
+            None => {
+                if !dry_run {
+                    self.put_rrset(&url, &desired).await.wrap_err("Failed to create DNS record")?;
+                }
+                log::info!("Gandi: {target}: Created new {dns_type} record with content {addr}.");
+            },
+        }
+
+        Ok(())
+    }
+}
+
+impl Provider for Gandi {
+    fn name(&self) -> &'static str {
+        "Gandi"
+    }
+
+    fn update<'a>(
+        &'a self,
+        ipv4: Option<Ipv4Addr>,
+        ipv6: Option<Ipv6Addr>,
+        dry_run: bool,
+    ) -> LocalBoxFuture<'a, usize> {
+        async move {
+            let tasks = self.targets.iter().flat_map(|target| {
+                let (ipv4, ipv6) = target.families(ipv4, ipv6);
+                let addrs = [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)];
+                addrs.into_iter().flatten().map(move |addr| async move {
+                    let res = self.handle_target(target, addr, dry_run || target.dry_run()).await;
+                    res.map_err(|err| log::error!("Gandi: {target}: {err:#}"))
+                })
+            });
+
+            futures::future::join_all(tasks)
+                .await
+                .into_iter()
+                .filter(Result::is_err)
+                .count()
+        }
+        .boxed_local()
+    }
+}
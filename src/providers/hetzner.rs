@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::{Provider, http_client};
+use crate::api::IpAddrExt;
+use crate::config::{HetznerConfig, Target};
+use crate::names::Subdomain;
+
+const BASE_URL: &str = "https://dns.hetzner.com/api/v1";
+
+/// Updates records through the Hetzner DNS Console API.
+#[derive(Debug)]
+pub struct Hetzner {
+    reqwest: reqwest::Client,
+    targets: Vec<Target>,
+}
+
+/// A single zone (domain) from Hetzner's API.
+#[derive(Debug, Deserialize)]
+struct Zone {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZonesResponse {
+    zones: Vec<Zone>,
+}
+
+/// A single DNS record from Hetzner's API.
+#[derive(Debug, Deserialize)]
+struct Record {
+    id: String,
+    #[serde(rename = "type")]
+    typ: String,
+    /// Name of the record, relative to the zone (`@` for the zone's root).
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordsResponse {
+    // Zones without any records return `null` instead of an empty list.
+    #[serde(default)]
+    records: Option<Vec<Record>>,
+}
+
+/// The payload for both creating and editing records.
+#[derive(Debug, Serialize)]
+struct RecordPayload<'a> {
+    zone_id: &'a str,
+    #[serde(rename = "type")]
+    typ: &'a str,
+    name: &'a str,
+    value: String,
+    ttl: u32,
+}
+
+impl Hetzner {
+    pub fn new(config: HetznerConfig) -> eyre::Result<Self> {
+        let token = HeaderValue::from_str(&config.token).wrap_err("Hetzner API token contains invalid characters")?;
+        Ok(Self {
+            reqwest: http_client([(HeaderName::from_static("auth-api-token"), token)]),
+            targets: config.targets,
+        })
+    }
+
+    /// Sends a request to Hetzner's API and parses the JSON response.
+    async fn request<R: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> eyre::Result<R> {
+        let res = req.send().await.wrap_err("Request failed")?;
+        let status = res.status();
+        if status.is_success() {
+            res.json().await.wrap_err("Response was not the expected type")
+        } else {
+            Err(eyre!("Received {status} from Hetzner API: {}", res.text().await.unwrap_or_default()))
+        }
+    }
+
+    /// Looks up the zone ID for a domain and fetches all of its records.
+    async fn get_zone(&self, domain: &str) -> eyre::Result<(String, Vec<Record>)> {
+        log::trace!("Fetching Hetzner zone for {domain}");
+        let zones: ZonesResponse = self
+            .request(self.reqwest.get(format!("{BASE_URL}/zones")).query(&[("name", domain)]))
+            .await?;
+
+        let zone_id = match zones.zones.into_iter().next() {
+            Some(zone) => zone.id,
+            None => return Err(eyre!("No zone named {domain} exists on this account")),
+        };
+
+        log::trace!("Fetching Hetzner records for {domain} (zone ID {zone_id})");
+        let records: RecordsResponse = self
+            .request(self.reqwest.get(format!("{BASE_URL}/records")).query(&[("zone_id", &zone_id)]))
+            .await?;
+
+        Ok((zone_id, records.records.unwrap_or_default()))
+    }
+
+    async fn handle_target(
+        &self,
+        target: &Target,
+        zone_id: &str,
+        records: &[Record],
+        addr: IpAddr,
+        dry_run: bool,
+    ) -> eyre::Result<()> {
+        let addr = target.address_for(addr);
+        let dns_type = addr.dns_type();
+        let name = target.subdomain().map_or("@", Subdomain::as_str);
+
+        let mut existing = records.iter().filter(|rec| rec.name == name && rec.typ == dns_type);
+        let record = existing.next();
+        if existing.next().is_some() {
+            return Err(eyre!("Found more than one existing {dns_type} records for {target}, unsure which to update"));
+        }
+
+        let payload = RecordPayload {
+            zone_id,
+            typ: dns_type,
+            name,
+            value: addr.to_string(),
+            ttl: target.ttl(),
+        };
+
+        match record {
+            Some(record) if record.value.parse::<IpAddr>().is_ok_and(|a| a == addr) => {
+                log::debug!("Hetzner: {target}: Found existing {dns_type} record with content {addr}. Nothing to do.");
+            },
+            Some(record) => {
+                if !dry_run {
+                    let url = format!("{BASE_URL}/records/{}", record.id);
+                    log::trace!("Sending PUT request to {url} with payload {payload:?}");
+                    self.request::<serde_json::Value>(self.reqwest.put(url).json(&payload))
+                        .await
+                        .wrap_err("Failed to edit DNS record")?;
+                }
+                let old = &record.value;
+                log::info!("Hetzner: {target}: Edited existing {dns_type} record from {old} to {addr}.");
+            },
+            None => {
+                if !dry_run {
+                    let url = format!("{BASE_URL}/records");
+                    log::trace!("Sending POST request to {url} with payload {payload:?}");
+                    self.request::<serde_json::Value>(self.reqwest.post(url).json(&payload))
+                        .await
+                        .wrap_err("Failed to create DNS record")?;
+                }
+                log::info!("Hetzner: {target}: Created new {dns_type} record with content {addr}.");
+            },
+        }
+
+        Ok(())
+    }
+}
+
+impl Provider for Hetzner {
+    fn name(&self) -> &'static str {
+        "Hetzner"
+    }
+
+    fn update<'a>(
+        &'a self,
+        ipv4: Option<Ipv4Addr>,
+        ipv6: Option<Ipv6Addr>,
+        dry_run: bool,
+    ) -> LocalBoxFuture<'a, usize> {
+        async move {
+            // Hetzner needs a zone ID before it can do anything with records, so fetch each domain's zone once.
+            let mut zones = HashMap::<&str, Option<(String, Vec<Record>)>>::new();
+            for target in &self.targets {
+                zones.entry(target.domain().as_str()).or_default();
+            }
+
+            let zone_tasks = zones.iter_mut().map(async |(domain, zone)| -> Result<(), ()> {
+                match self.get_zone(domain).await {
+                    Ok(fetched) => {
+                        *zone = Some(fetched);
+                        Ok(())
+                    },
+                    Err(err) => {
+                        log::error!("Hetzner: Failed to fetch DNS records for {domain}: {err:#}");
+                        Err(())
+                    },
+                }
+            });
+
+            let mut err_count = futures::future::join_all(zone_tasks)
+                .await
+                .into_iter()
+                .filter(Result::is_err)
+                .count();
+
+            let tasks = self.targets.iter().flat_map(|target| {
+                let (ipv4, ipv6) = target.families(ipv4, ipv6);
+                let addrs = [ipv4.map(IpAddr::V4), ipv6.map(IpAddr::V6)];
+                let zone = zones.get(target.domain().as_str()).and_then(Option::as_ref);
+                if zone.is_none() {
+                    log::warn!("Hetzner: {target}: Skipped due to missing DNS records.");
+                }
+
+                zone.into_iter().flat_map(move |(zone_id, records)| {
+                    addrs.into_iter().flatten().map(move |addr| async move {
+                        let res = self
+                            .handle_target(target, zone_id, records, addr, dry_run || target.dry_run())
+                            .await;
+                        res.map_err(|err| log::error!("Hetzner: {target}: {err:#}"))
+                    })
+                })
+            });
+
+            err_count += futures::future::join_all(tasks)
+                .await
+                .into_iter()
+                .filter(Result::is_err)
+                .count();
+
+            err_count
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,47 @@
+//! DNS providers other than Porkbun whose records can be kept in sync alongside Porkbun's.
+
+mod duckdns;
+mod gandi;
+mod hetzner;
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use futures::future::LocalBoxFuture;
+use reqwest::header::{HeaderMap, HeaderValue};
+
+pub use self::duckdns::DuckDns;
+pub use self::gandi::Gandi;
+pub use self::hetzner::Hetzner;
+
+/// A secondary DNS provider which can have its records updated to point at this system's current IP addresses.
+///
+/// Unlike [`PorkbunClient`][crate::api::PorkbunClient], which is the main focus of this program, providers are only
+/// responsible for pushing whatever addresses were detected; they are configured entirely by their own section of the
+/// config file.
+pub trait Provider {
+    /// A human-readable name for this provider, used to prefix log messages.
+    fn name(&self) -> &'static str;
+
+    /// Updates all of this provider's configured records with the given addresses.
+    ///
+    /// Just like [`App::run`][crate::App::run], providers are responsible for logging their own errors. The total
+    /// number of errors encountered is returned.
+    ///
+    /// When `dry_run` is set, providers should check their existing records where possible, but must not make any
+    /// changes.
+    fn update<'a>(&'a self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>, dry_run: bool)
+    -> LocalBoxFuture<'a, usize>;
+}
+
+/// Builds a `reqwest` client with the same user-agent and default headers that the Porkbun client uses.
+pub fn http_client(
+    extra_headers: impl IntoIterator<Item = (reqwest::header::HeaderName, HeaderValue)>,
+) -> reqwest::Client {
+    let ua_str = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    let mut headers = HeaderMap::from_iter([(
+        reqwest::header::USER_AGENT,
+        HeaderValue::from_str(&ua_str).expect("UA str should be valid"),
+    )]);
+    headers.extend(extra_headers);
+    reqwest::ClientBuilder::new().default_headers(headers).build().unwrap()
+}
@@ -0,0 +1,113 @@
+//! A machine-readable summary of what happened during a run.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use eyre::WrapErr;
+use serde::Serialize;
+use tokio::fs;
+
+use crate::api::IpAddrExt;
+use crate::config::Target;
+use crate::plan::{Action, Change};
+
+/// Everything that happened during a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// When the run finished, in RFC 3339 format.
+    pub timestamp: String,
+    pub dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv4: Option<Ipv4Addr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<Ipv6Addr>,
+    /// What happened to each target's A and/or AAAA record.
+    pub targets: Vec<TargetResult>,
+    /// Every change made to Porkbun records, including static records and synced domains.
+    pub changes: Vec<Change>,
+    /// The total number of errors encountered, including ones that aren't tied to any one target.
+    pub errors: usize,
+    /// Why the run was stopped before any records were updated, if it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Report {
+    /// Writes the report out to a file as JSON.
+    pub async fn save(&self, path: &Path) -> eyre::Result<()> {
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir).await.wrap_err("Failed to create summary directory")?;
+        }
+
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json).await.wrap_err("Failed to write summary file")
+    }
+}
+
+/// What happened to a single record of a single target.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetResult {
+    pub target: String,
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub action: TargetAction,
+    /// What the record held before, if it was edited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// What the record holds now (or should hold, if updating it failed).
+    pub new: String,
+    /// The ID of the record that was edited, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether the new content could be resolved afterwards, if the record changed and verification is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    /// Whether the target is set to `dry_run`, so that any change was only pretend (even if the run as a whole wasn't).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetAction {
+    Created,
+    Updated,
+    Unchanged,
+    Failed,
+}
+
+impl TargetResult {
+    /// Works out what happened to a target's record for the given address, based on the changes made during the run.
+    pub fn new(target: &Target, addr: IpAddr, changes: &[Change], error: Option<String>) -> Self {
+        let typ = addr.dns_type();
+        let fqdn = target.fqdn();
+        let change = (changes.iter())
+            .filter(|change| change.typ == typ && change.name.eq_ignore_ascii_case(&fqdn))
+            .find(|change| change.action != Action::Delete);
+
+        let action = match (&error, change.map(|change| change.action)) {
+            (Some(_), _) => TargetAction::Failed,
+            (None, Some(Action::Create)) => TargetAction::Created,
+            (None, Some(_)) => TargetAction::Updated,
+            (None, None) => TargetAction::Unchanged,
+        };
+
+        Self {
+            target: target.to_string(),
+            typ,
+            action,
+            old: change.and_then(|change| change.old.as_ref()).map(|old| old.content.clone()),
+            new: change
+                .and_then(|change| change.new.as_ref())
+                .map_or(addr.to_string(), |new| new.content.clone()),
+            id: change.and_then(|change| change.id.clone()),
+            error,
+            verified: None,
+            dry_run: target.dry_run(),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Encrypted files holding the Porkbun API keys, so that they can live alongside the config file.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use eyre::{WrapErr, eyre};
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::config::ConfigFormat;
+
+/// The contents of a decrypted secrets file.
+#[derive(Debug, Deserialize)]
+pub struct Secrets {
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub secret_key: Option<String>,
+}
+
+impl Secrets {
+    /// Decrypts and parses a secrets file.
+    ///
+    /// Files ending in `.age` are decrypted with `age` using the given identity file, and are then parsed according to
+    /// the rest of their name (e.g., `secrets.toml.age` is read as TOML). Anything else is decrypted with `sops`, which
+    /// works out the format (and key) on its own. Either way, the program needs to be installed.
+    pub async fn decrypt(path: &Path, age_identity: Option<&Path>) -> eyre::Result<Self> {
+        log::trace!("Decrypting secrets from {}", path.display());
+
+        let is_age = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("age"));
+        let (mut command, format) = if is_age {
+            let identity =
+                age_identity.ok_or_else(|| eyre!("age_identity must be set to decrypt {}", path.display()))?;
+            let mut command = Command::new("age");
+            command.arg("--decrypt").arg("--identity").arg(identity).arg(path);
+            (command, ConfigFormat::from_path(Path::new(path.file_stem().unwrap_or_default())))
+        } else {
+            let mut command = Command::new("sops");
+            command.args(["--decrypt", "--output-type", "json"]).arg(path);
+            (command, ConfigFormat::Json)
+        };
+
+        let program = if is_age { "age" } else { "sops" };
+        let output = command
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .await
+            .wrap_err_with(|| format!("Failed to run {program} to decrypt secrets file"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(eyre!("Failed to decrypt secrets file with {program}: {}", stderr.trim()));
+        }
+
+        let text = String::from_utf8(output.stdout).wrap_err("Decrypted secrets file is not valid UTF-8")?;
+        format.parse(&text).wrap_err("Failed to parse decrypted secrets file")
+    }
+}
@@ -0,0 +1,146 @@
+//! A small HTTP server, for keeping an eye on the updater while it runs as a daemon and for the external-dns webhook.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use eyre::{WrapErr, eyre};
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CONTENT_TYPE, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+use crate::App;
+
+/// The largest request body that will be read, in bytes.
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+const JSON: &str = "application/json";
+
+/// A single page showing everything from the status API, for viewing in a browser.
+#[cfg(feature = "dashboard")]
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Starts listening on the given address. Done separately from [`serve`] so that problems binding the address can be
+/// reported before anything else starts.
+pub async fn bind(addr: SocketAddr) -> eyre::Result<TcpListener> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err_with(|| format!("Failed to listen on {addr}"))?;
+    log::info!("Listening on http://{addr}");
+    Ok(listener)
+}
+
+/// Answers requests on the given listener forever, using `handler` to work out the response to each one.
+///
+/// Connections are all handled on this one task (rather than spawned), since handlers need to borrow the [`App`].
+pub async fn serve<F, Fut>(listener: TcpListener, handler: F)
+where
+    F: Fn(Request<Incoming>) -> Fut + Copy,
+    Fut: Future<Output = Response<Full<Bytes>>>,
+{
+    let service = service_fn(move |req: Request<Incoming>| {
+        log::trace!("Received HTTP request: {} {}", req.method(), req.uri());
+        let res = handler(req);
+        async move { Ok::<_, Infallible>(res.await) }
+    });
+
+    let mut connections = FuturesUnordered::new();
+    loop {
+        tokio::select! {
+            res = listener.accept() => match res {
+                Ok((stream, _)) => {
+                    let conn = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+                    connections.push(conn);
+                },
+                Err(err) => log::warn!("Failed to accept HTTP connection: {err}"),
+            },
+            Some(res) = connections.next(), if !connections.is_empty() => {
+                if let Err(err) = res {
+                    log::debug!("HTTP connection failed: {err}");
+                }
+            },
+        }
+    }
+}
+
+/// The response from `/healthz`.
+#[derive(Debug, Serialize)]
+struct Health {
+    healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Works out the response to a single request made to the daemon.
+pub async fn handle(app: &App, req: Request<Incoming>) -> Response<Full<Bytes>> {
+    match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            let metrics = app.metrics.lock().unwrap().render(&app.client.timings());
+            respond(StatusCode::OK, "text/plain; version=0.0.4; charset=utf-8", metrics)
+        },
+        #[cfg(feature = "dashboard")]
+        (&Method::GET, "/") => respond(StatusCode::OK, "text/html; charset=utf-8", DASHBOARD),
+        (&Method::GET, "/healthz") => match app.status.lock().unwrap().health() {
+            Ok(()) => json(StatusCode::OK, JSON, &Health { healthy: true, reason: None }),
+            Err(reason) => json(StatusCode::SERVICE_UNAVAILABLE, JSON, &Health {
+                healthy: false,
+                reason: Some(reason),
+            }),
+        },
+        (&Method::GET, "/status") => json(StatusCode::OK, JSON, &app.status.lock().unwrap().summary()),
+        (&Method::GET, "/targets") => json(StatusCode::OK, JSON, &app.status.lock().unwrap().targets()),
+        (&Method::GET, "/last-run") => match app.status.lock().unwrap().last_run() {
+            Some(report) => json(StatusCode::OK, JSON, report),
+            None => respond(StatusCode::NOT_FOUND, "text/plain", "No updates have finished yet\n"),
+        },
+        #[cfg(feature = "dashboard")]
+        (_, "/") => method_not_allowed(),
+        (_, "/metrics" | "/healthz" | "/status" | "/targets" | "/last-run") => method_not_allowed(),
+        _ => not_found(),
+    }
+}
+
+/// Builds a response with the given body.
+pub fn respond(status: StatusCode, content_type: &'static str, body: impl Into<Bytes>) -> Response<Full<Bytes>> {
+    let mut res = Response::new(Full::new(body.into()));
+    *res.status_mut() = status;
+    res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    res
+}
+
+/// Builds a response with a JSON body.
+pub fn json(status: StatusCode, content_type: &'static str, body: &impl Serialize) -> Response<Full<Bytes>> {
+    match serde_json::to_vec(body) {
+        Ok(body) => respond(status, content_type, body),
+        Err(err) => error(&eyre!(err).wrap_err("Failed to serialize response")),
+    }
+}
+
+/// Builds a plain-text response for an error that occurred while handling a request.
+pub fn error(err: &eyre::Report) -> Response<Full<Bytes>> {
+    respond(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", format!("{err:#}\n"))
+}
+
+pub fn not_found() -> Response<Full<Bytes>> {
+    respond(StatusCode::NOT_FOUND, "text/plain", "Not found\n")
+}
+
+pub fn method_not_allowed() -> Response<Full<Bytes>> {
+    respond(StatusCode::METHOD_NOT_ALLOWED, "text/plain", "Method not allowed\n")
+}
+
+/// Reads a request's whole body, up to [`MAX_BODY_SIZE`].
+pub async fn read_body(req: Request<Incoming>) -> eyre::Result<Bytes> {
+    let body = Limited::new(req.into_body(), MAX_BODY_SIZE);
+    match body.collect().await {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(err) => Err(eyre!("Failed to read request body: {err}")),
+    }
+}
@@ -0,0 +1,83 @@
+//! Stopping gracefully when asked to (e.g., by systemd or Ctrl+C), instead of being killed in the middle of an update.
+
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+/// Keeps track of whether the process has been asked to stop.
+///
+/// Nothing gets cancelled outright: requests that are already in flight are left to finish, so that no record is left
+/// half-changed, and the run then wraps up as usual (saving the state file, sending notifications, and so on). Anything
+/// that hasn't been started yet is skipped.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Shutdown {
+    /// Checks whether the process has been asked to stop.
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Waits until the process is asked to stop.
+    pub async fn requested(&self) {
+        let notified = self.notify.notified();
+        if !self.is_requested() {
+            notified.await;
+        }
+    }
+
+    /// Drives a task to completion, asking it to stop (through [`is_requested`][Self::is_requested]) the first time a
+    /// termination signal arrives. If a second one arrives before the task is done, the process exits immediately.
+    pub async fn guard<T>(&self, task: impl Future<Output = T>) -> T {
+        let mut task = pin!(task);
+        tokio::select! {
+            out = &mut task => return out,
+            signal = signal() => {
+                log::warn!("Received {signal}: finishing up before exiting. Send it again to exit immediately.");
+                self.requested.store(true, Ordering::Relaxed);
+                self.notify.notify_waiters();
+            },
+        }
+
+        tokio::select! {
+            out = task => out,
+            signal = signal() => {
+                log::error!("Received {signal} again: exiting immediately.");
+                std::process::exit(1);
+            },
+        }
+    }
+}
+
+/// Waits for SIGTERM or SIGINT, returning the name of whichever arrived first.
+#[cfg(unix)]
+async fn signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let (mut term, mut int) = match (signal(SignalKind::terminate()), signal(SignalKind::interrupt())) {
+        (Ok(term), Ok(int)) => (term, int),
+        (Err(err), _) | (_, Err(err)) => {
+            log::warn!("Failed to listen for termination signals: {err}");
+            return std::future::pending().await;
+        },
+    };
+
+    tokio::select! {
+        _ = term.recv() => "SIGTERM",
+        _ = int.recv() => "SIGINT",
+    }
+}
+
+/// Waits for Ctrl+C, which is the only termination signal there is to listen for outside of Unix.
+#[cfg(not(unix))]
+async fn signal() -> &'static str {
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        log::warn!("Failed to listen for Ctrl+C: {err}");
+        return std::future::pending().await;
+    }
+    "Ctrl+C"
+}
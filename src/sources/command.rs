@@ -0,0 +1,90 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::Stdio;
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use tokio::process::Command as Process;
+
+use super::IpSource;
+
+/// How long the program can run for before it's killed.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Runs a program and reads addresses from what it prints.
+///
+/// The program is run directly, not through a shell. Its output can hold an IPv4 address, an IPv6 address, or one of
+/// each, separated by whitespace. It's given `WANT_IPV4` and `WANT_IPV6` (`1` or `0`), so that it can skip looking up
+/// addresses that aren't needed.
+#[derive(Debug)]
+pub struct Command {
+    command: Vec<String>,
+}
+
+impl Command {
+    pub fn new(command: Vec<String>) -> Self {
+        Self { command }
+    }
+}
+
+impl IpSource for Command {
+    fn name(&self) -> String {
+        format!("command {}", self.command[0])
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move {
+            let program = &self.command[0];
+            let output = Process::new(program)
+                .args(&self.command[1..])
+                .env("WANT_IPV4", if ipv4 { "1" } else { "0" })
+                .env("WANT_IPV6", if ipv6 { "1" } else { "0" })
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .output();
+
+            let output = tokio::time::timeout(TIMEOUT, output)
+                .await
+                .map_err(|_| eyre!("{program} did not finish within {} seconds", TIMEOUT.as_secs()))?
+                .wrap_err_with(|| format!("Failed to run {program}"))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(eyre!("{program} exited with {}: {}", output.status, stderr.trim()));
+            }
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            parse_addrs(&stdout).wrap_err_with(|| format!("Invalid output from {program}"))
+        }
+        .boxed_local()
+    }
+}
+
+/// Reads up to one IPv4 and one IPv6 address out of some whitespace-separated text.
+pub fn parse_addrs(text: &str) -> eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)> {
+    let mut ipv4 = None;
+    let mut ipv6 = None;
+    for word in text.split_whitespace() {
+        match word.parse::<IpAddr>() {
+            Ok(IpAddr::V4(addr)) if ipv4.is_none() => ipv4 = Some(addr),
+            Ok(IpAddr::V6(addr)) if ipv6.is_none() => ipv6 = Some(addr),
+            Ok(addr) => return Err(eyre!("found more than one {} address ({addr})", addr_kind(addr))),
+            Err(_) => return Err(eyre!("{word:?} is not an IP address")),
+        }
+    }
+    Ok((ipv4, ipv6))
+}
+
+fn addr_kind(addr: IpAddr) -> &'static str {
+    match addr {
+        IpAddr::V4(_) => "IPv4",
+        IpAddr::V6(_) => "IPv6",
+    }
+}
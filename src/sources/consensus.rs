@@ -0,0 +1,112 @@
+use std::fmt::Display;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use eyre::eyre;
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+
+use super::IpSource;
+use crate::config::ConsensusPolicy;
+
+/// Asks several sources at once, and combines their answers according to a [`ConsensusPolicy`].
+///
+/// This protects against a single misbehaving source (e.g., a compromised "what's my IP" service, or a router that has
+/// fallen back to a backup connection) publishing the wrong address.
+pub struct Consensus {
+    sources: Vec<Box<dyn IpSource>>,
+    policy: ConsensusPolicy,
+}
+
+impl Consensus {
+    pub fn new(sources: Vec<Box<dyn IpSource>>, policy: ConsensusPolicy) -> Self {
+        Self { sources, policy }
+    }
+
+    /// Picks one family's address out of what each source found, which are in the same order as the sources.
+    fn decide<A: Copy + Eq + Display>(&self, family: &str, found: &[Option<A>]) -> Option<A> {
+        // Tally up the votes for each address, keeping them in the order they were first seen.
+        let mut votes = Vec::<(A, Vec<String>)>::new();
+        for (source, addr) in self.sources.iter().zip(found) {
+            let Some(addr) = *addr else { continue };
+            match votes.iter_mut().find(|(a, _)| *a == addr) {
+                Some((_, names)) => names.push(source.name()),
+                None => votes.push((addr, vec![source.name()])),
+            }
+        }
+
+        let describe = || {
+            (votes.iter())
+                .map(|(addr, names)| format!("{addr} ({})", names.join(", ")))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+
+        let needed = match self.policy {
+            ConsensusPolicy::Warn => {
+                let (addr, _) = votes.first()?;
+                if votes.len() > 1 {
+                    log::warn!("IP sources disagree on the current {family} address: {}; using {addr}", describe());
+                }
+                return Some(*addr);
+            },
+            ConsensusPolicy::All => self.sources.len(),
+            ConsensusPolicy::AtLeast(n) => n,
+        };
+
+        // `max_by_key` would pick the last of several tied addresses, but the earliest one should win.
+        let (addr, names) = votes.iter().rev().max_by_key(|(_, names)| names.len())?;
+        if names.len() < needed {
+            log::warn!("Not enough IP sources agree on the current {family} address ({needed} needed): {}", describe());
+            return None;
+        }
+        Some(*addr)
+    }
+}
+
+impl IpSource for Consensus {
+    fn name(&self) -> String {
+        let names = self.sources.iter().map(|source| source.name()).collect::<Vec<_>>();
+        names.join(", ")
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move {
+            let results = futures::future::join_all(self.sources.iter().map(|s| s.addresses(ipv4, ipv6))).await;
+
+            let mut found = Vec::with_capacity(results.len());
+            let mut last_err = None;
+            for (source, res) in self.sources.iter().zip(results) {
+                match res {
+                    Ok((v4, v6)) => {
+                        let show = |addr: Option<String>| addr.unwrap_or_else(|| "none".to_string());
+                        log::debug!(
+                            "{}: IPv4 {}, IPv6 {}",
+                            source.name(),
+                            show(v4.map(|a| a.to_string())),
+                            show(v6.map(|a| a.to_string())),
+                        );
+                        found.push((v4, v6));
+                    },
+                    Err(err) => {
+                        log::debug!("{}: {err:#}", source.name());
+                        last_err = Some(err.wrap_err(format!("Failed to get addresses from {}", source.name())));
+                        found.push((None, None));
+                    },
+                }
+            }
+
+            if found.iter().all(|(v4, v6)| v4.is_none() && v6.is_none()) {
+                return Err(last_err.unwrap_or_else(|| eyre!("None of the IP sources found any addresses")));
+            }
+
+            let v4 = found.iter().map(|(v4, _)| *v4).collect::<Vec<_>>();
+            let v6 = found.iter().map(|(_, v6)| *v6).collect::<Vec<_>>();
+            Ok((self.decide("IPv4", &v4), self.decide("IPv6", &v6)))
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,251 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use tokio::net::UdpSocket;
+
+use super::{IpSource, each_family, random_id};
+use crate::config::WhoamiService;
+
+/// How long to wait for each response before asking again.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many times to ask each service before moving on to the next one.
+const ATTEMPTS: usize = 3;
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const CLASS_CH: u16 = 3;
+
+/// Asks DNS services that answer special queries with the address that the query came from.
+///
+/// Services are tried in order until one of them answers. Queries are sent straight to each service's own servers
+/// (not through the system's resolver, which would answer with its own address), over IPv4 for the IPv4 address and
+/// over IPv6 for the IPv6 address.
+#[derive(Debug)]
+pub struct Dns {
+    services: Vec<WhoamiService>,
+}
+
+/// A query to send to a specific server.
+struct Query<'a> {
+    server: SocketAddr,
+    name: &'a str,
+    typ: u16,
+    class: u16,
+    /// Whether to ask the server to recurse. Only resolvers do that; authoritative nameservers answer for their own
+    /// zones either way.
+    recursive: bool,
+}
+
+impl Dns {
+    pub fn new(services: Vec<WhoamiService>) -> Self {
+        Self { services }
+    }
+
+    /// Finds our address of one family by asking each service in turn.
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        let mut last_err = None;
+        for &service in &self.services {
+            let Some(query) = query_for(service, ipv6) else {
+                continue;
+            };
+
+            match send(&query).await {
+                Ok(addrs) => return Ok(addrs[0]),
+                Err(err) => {
+                    log::debug!("DNS: {service:?}: {err:#}");
+                    last_err = Some(err.wrap_err(format!("Failed to get address from {service:?}")));
+                },
+            }
+        }
+
+        let family = if ipv6 { "IPv6" } else { "IPv4" };
+        Err(last_err.unwrap_or_else(|| eyre!("None of the configured DNS services support {family}")))
+    }
+}
+
+impl IpSource for Dns {
+    fn name(&self) -> String {
+        "DNS".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("DNS", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
+
+/// Asks a specific server for the A (or AAAA) records of any name. Returns every address in the answer, of which there
+/// is always at least one.
+pub async fn query_addresses(server: SocketAddr, name: &str, ipv6: bool, recursive: bool) -> eyre::Result<Vec<IpAddr>> {
+    let typ = if ipv6 { TYPE_AAAA } else { TYPE_A };
+    let query = Query {
+        server,
+        name,
+        typ,
+        class: CLASS_IN,
+        recursive,
+    };
+    send(&query).await
+}
+
+/// Works out what to ask a service for, and where, to find our address of one family.
+fn query_for(service: WhoamiService, ipv6: bool) -> Option<Query<'static>> {
+    let query = match (service, ipv6) {
+        (WhoamiService::OpenDns, false) => Query {
+            server: SocketAddr::from((Ipv4Addr::new(208, 67, 222, 222), 53)),
+            name: "myip.opendns.com",
+            typ: TYPE_A,
+            class: CLASS_IN,
+            recursive: false,
+        },
+        (WhoamiService::OpenDns, true) => Query {
+            server: SocketAddr::from((Ipv6Addr::new(0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35), 53)),
+            name: "myip.opendns.com",
+            typ: TYPE_AAAA,
+            class: CLASS_IN,
+            recursive: false,
+        },
+        (WhoamiService::Cloudflare, false) => Query {
+            server: SocketAddr::from((Ipv4Addr::new(1, 1, 1, 1), 53)),
+            name: "whoami.cloudflare",
+            typ: TYPE_TXT,
+            class: CLASS_CH,
+            recursive: false,
+        },
+        (WhoamiService::Cloudflare, true) => Query {
+            server: SocketAddr::from((Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111), 53)),
+            name: "whoami.cloudflare",
+            typ: TYPE_TXT,
+            class: CLASS_CH,
+            recursive: false,
+        },
+        (WhoamiService::Akamai, false) => Query {
+            server: SocketAddr::from((Ipv4Addr::new(193, 108, 88, 1), 53)),
+            name: "whoami.akamai.net",
+            typ: TYPE_A,
+            class: CLASS_IN,
+            recursive: false,
+        },
+        (WhoamiService::Akamai, true) => return None,
+    };
+    Some(query)
+}
+
+/// Sends a query and returns the addresses in its answer.
+async fn send(query: &Query<'_>) -> eyre::Result<Vec<IpAddr>> {
+    let local = match query.server {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(local).await.wrap_err("Failed to open socket")?;
+    socket
+        .connect(query.server)
+        .await
+        .wrap_err_with(|| format!("Failed to connect to {}", query.server))?;
+
+    let id = random_id::<2>();
+    let mut request = Vec::with_capacity(12 + query.name.len() + 6);
+    request.extend(id);
+    // Flags (a standard query, with or without recursion), one question, and no other records.
+    request.extend([query.recursive as u8, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in query.name.split('.') {
+        request.push(label.len() as u8);
+        request.extend(label.as_bytes());
+    }
+    request.push(0);
+    request.extend(query.typ.to_be_bytes());
+    request.extend(query.class.to_be_bytes());
+
+    let mut buf = [0u8; 1024];
+    for _ in 0..ATTEMPTS {
+        socket.send(&request).await.wrap_err("Failed to send query")?;
+        match tokio::time::timeout(TIMEOUT, socket.recv(&mut buf)).await {
+            Ok(res) => {
+                let len = res.wrap_err("Failed to receive response")?;
+                match parse_response(&buf[..len], id, query.typ)? {
+                    Some(addrs) => return Ok(addrs),
+                    // Something other than the response to our query; ask again.
+                    None => continue,
+                }
+            },
+            Err(_) => continue,
+        }
+    }
+
+    Err(eyre!("No response after {ATTEMPTS} attempts"))
+}
+
+/// Reads the addresses out of every matching answer in a response. Returns `None` if the message isn't a response to
+/// our query.
+fn parse_response(msg: &[u8], id: [u8; 2], typ: u16) -> eyre::Result<Option<Vec<IpAddr>>> {
+    let malformed = || eyre!("Malformed response");
+
+    // Checking the QR bit, since that's what says this is a response.
+    if msg.len() < 12 || msg[..2] != id || msg[2] & 0x80 == 0 {
+        return Ok(None);
+    }
+
+    let rcode = msg[3] & 0x0F;
+    if rcode != 0 {
+        return Err(eyre!("Server responded with error code {rcode}"));
+    }
+
+    let questions = u16::from_be_bytes([msg[4], msg[5]]);
+    let answers = u16::from_be_bytes([msg[6], msg[7]]);
+
+    let mut addrs = Vec::new();
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos).ok_or_else(malformed)? + 4;
+    }
+
+    for _ in 0..answers {
+        pos = skip_name(msg, pos).ok_or_else(malformed)?;
+        let header = msg.get(pos..pos + 10).ok_or_else(malformed)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let len = usize::from(u16::from_be_bytes([header[8], header[9]]));
+        let data = msg.get(pos + 10..pos + 10 + len).ok_or_else(malformed)?;
+        pos += 10 + len;
+
+        if rtype != typ {
+            continue;
+        }
+
+        let addr = match rtype {
+            TYPE_A => <[u8; 4]>::try_from(data).map(|octets| IpAddr::V4(octets.into())).ok(),
+            TYPE_AAAA => <[u8; 16]>::try_from(data).map(|octets| IpAddr::V6(octets.into())).ok(),
+            // A single character-string, prefixed with its length.
+            _ => (data.get(1..))
+                .and_then(|text| std::str::from_utf8(text).ok())
+                .and_then(|text| text.parse().ok()),
+        };
+        addrs.push(addr.ok_or_else(|| eyre!("Response has a malformed address"))?);
+    }
+
+    match addrs.is_empty() {
+        true => Err(eyre!("Response has no answer")),
+        false => Ok(Some(addrs)),
+    }
+}
+
+/// Finds where the (possibly compressed) domain name starting at `pos` ends.
+fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            // A pointer to a name elsewhere in the message, which always ends the name.
+            len if len & 0xC0 == 0xC0 => return Some(pos + 2),
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+
+use super::IpSource;
+
+/// Asks each source in turn, moving on to the next whenever one fails (or finds nothing at all), so that an outage of
+/// one way of detecting addresses doesn't block updates that could still go through.
+pub struct Fallback {
+    sources: Vec<Box<dyn IpSource>>,
+}
+
+impl Fallback {
+    pub fn new(sources: Vec<Box<dyn IpSource>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl IpSource for Fallback {
+    fn name(&self) -> String {
+        let names = self.sources.iter().map(|source| source.name()).collect::<Vec<_>>();
+        match &names[..] {
+            [first, rest @ ..] if !rest.is_empty() => format!("{first} (falling back to {})", rest.join(", ")),
+            _ => names.join(", "),
+        }
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move {
+            let last = self.sources.len().saturating_sub(1);
+            for (i, source) in self.sources.iter().enumerate() {
+                if i > 0 {
+                    log::info!("Falling back to {}...", source.name());
+                }
+
+                // Whatever the last source says (even if it's an error) is the final answer.
+                let res = source.addresses(ipv4, ipv6).await;
+                match res {
+                    _ if i == last => return res,
+                    Ok((None, None)) => log::warn!("Found no addresses from {}", source.name()),
+                    Ok(found) => return Ok(found),
+                    Err(err) => log::warn!("Failed to get addresses from {}: {err:#}", source.name()),
+                }
+            }
+
+            Ok((None, None))
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,64 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use tokio::fs;
+
+use super::IpSource;
+use super::command::parse_addrs;
+
+/// Reads addresses from a file, e.g. one written by a pppd or dhcpcd hook whenever the connection comes up.
+///
+/// The file is read the same way as a [`Command`][super::Command]'s output. If it hasn't been modified within
+/// `max_age`, it's assumed to be out of date (e.g., because whatever writes it stopped running), and is refused.
+#[derive(Debug)]
+pub struct File {
+    path: PathBuf,
+    max_age: Option<Duration>,
+}
+
+impl File {
+    pub fn new(path: PathBuf, max_age: Option<Duration>) -> Self {
+        Self { path, max_age }
+    }
+}
+
+impl IpSource for File {
+    fn name(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        _ipv4: bool,
+        _ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move {
+            let path = self.path.display();
+            if let Some(max_age) = self.max_age {
+                let modified = (fs::metadata(&self.path).await)
+                    .and_then(|meta| meta.modified())
+                    .wrap_err_with(|| format!("Failed to check when {path} was last modified"))?;
+
+                // A modification time in the future counts as brand new.
+                let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+                if age > max_age {
+                    return Err(eyre!(
+                        "{path} was last modified {}s ago, which is longer than max_age ({}s)",
+                        age.as_secs(),
+                        max_age.as_secs(),
+                    ));
+                }
+            }
+
+            let text = fs::read_to_string(&self.path)
+                .await
+                .wrap_err_with(|| format!("Failed to read {path}"))?;
+            parse_addrs(&text).wrap_err_with(|| format!("Invalid contents in {path}"))
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,134 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+
+use super::soap::{Credentials, soap_request, xml_tag};
+use super::{IpSource, each_family, warn_if_double_nat};
+use crate::netif;
+
+/// Where a Fritz!Box's TR-064 interface is found when no other address is configured.
+pub const DEFAULT_URL: &str = "http://fritz.box:49000";
+
+/// How long to wait for each request to the router.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The TR-064 services which report the router's internet connection, with their control URLs. DSL connections
+/// usually use the PPP one, and cable/fibre ones the IP one, but it's simplest to just try both.
+const WAN_SERVICES: &[(&str, &str)] = &[
+    ("urn:dslforum-org:service:WANIPConnection:1", "/upnp/control/wanipconnection1"),
+    ("urn:dslforum-org:service:WANPPPConnection:1", "/upnp/control/wanpppconn1"),
+];
+
+/// Asks an AVM Fritz!Box for its WAN IPv4 address and its delegated IPv6 prefix, using [TR-064][tr-064].
+///
+/// Since the router only knows the prefix it was delegated, the IPv6 address is one of this machine's own addresses in
+/// that prefix if there is one; otherwise, it's the prefix itself (which is only really useful with `ipv6_prefix_len`).
+///
+/// [tr-064]: https://avm.de/service/schnittstellen/
+#[derive(Debug)]
+pub struct FritzBox {
+    url: Url,
+    credentials: Option<Credentials>,
+    reqwest: reqwest::Client,
+}
+
+impl FritzBox {
+    pub fn new(url: Url, username: Option<String>, password: Option<String>) -> Self {
+        let ua_str = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let reqwest = reqwest::ClientBuilder::new()
+            .user_agent(ua_str)
+            .timeout(TIMEOUT)
+            .build()
+            .unwrap();
+
+        // Newer firmware has no anonymous users, but older firmware only asks for a password.
+        let credentials = password.map(|password| Credentials {
+            username: username.unwrap_or_default(),
+            password,
+        });
+        Self { url, credentials, reqwest }
+    }
+
+    /// Calls an action on each of the WAN services in turn until one of them works.
+    async fn call(&self, action: &str) -> eyre::Result<String> {
+        let mut last_err = None;
+        for (service_type, control_url) in WAN_SERVICES {
+            let url = self.url.join(control_url).wrap_err("Invalid Fritz!Box URL")?;
+            match soap_request(&self.reqwest, url, service_type, action, self.credentials.as_ref()).await {
+                Ok(response) => return Ok(response),
+                Err(err) => {
+                    log::debug!("Fritz!Box: {service_type}: {err:#}");
+                    last_err = Some(err);
+                },
+            }
+        }
+        Err(last_err.expect("there should be at least one WAN service"))
+    }
+
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        match ipv6 {
+            false => self.ipv4().await.map(IpAddr::V4),
+            true => self.ipv6().await.map(IpAddr::V6),
+        }
+        .wrap_err_with(|| format!("Failed to get address from Fritz!Box at {}", self.url))
+    }
+
+    async fn ipv4(&self) -> eyre::Result<Ipv4Addr> {
+        let response = self.call("GetExternalIPAddress").await?;
+        let addr = match xml_tag(&response, "NewExternalIPAddress") {
+            None | Some("") => return Err(eyre!("Router has no external IPv4 address (is it connected?)")),
+            Some(addr) => addr
+                .parse()
+                .wrap_err_with(|| format!("Router responded with invalid address {addr:?}"))?,
+        };
+
+        warn_if_double_nat(&self.url, addr);
+        Ok(addr)
+    }
+
+    async fn ipv6(&self) -> eyre::Result<Ipv6Addr> {
+        let response = self.call("X_AVM_DE_GetIPv6Prefix").await?;
+        let prefix = match xml_tag(&response, "NewIPv6Prefix") {
+            None | Some("") => return Err(eyre!("Router has no IPv6 prefix (is it connected?)")),
+            Some(prefix) => (prefix.parse::<Ipv6Addr>())
+                .wrap_err_with(|| format!("Router responded with invalid prefix {prefix:?}"))?,
+        };
+        let len = xml_tag(&response, "NewPrefixLength")
+            .and_then(|len| len.parse::<u32>().ok())
+            .filter(|len| (1..=128).contains(len))
+            .ok_or_else(|| eyre!("Router responded with an invalid prefix length"))?;
+
+        let mask = (!0u128).checked_shl(128 - len).unwrap_or(0);
+        let in_prefix = |addr: &Ipv6Addr| addr.to_bits() & mask == prefix.to_bits() & mask;
+        let local = (netif::local_ipv6_addrs().unwrap_or_default().into_iter())
+            .filter(|local| in_prefix(&local.addr) && local.is_usable())
+            .min_by_key(|local| local.is_temporary())
+            .map(|local| local.addr);
+
+        match local {
+            Some(addr) => Ok(addr),
+            None => {
+                log::debug!("Fritz!Box: no local address is in {prefix}/{len}; using the prefix itself");
+                Ok(Ipv6Addr::from_bits(prefix.to_bits() & mask))
+            },
+        }
+    }
+}
+
+impl IpSource for FritzBox {
+    fn name(&self) -> String {
+        "the Fritz!Box".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("Fritz!Box", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
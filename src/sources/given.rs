@@ -0,0 +1,33 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+
+use super::IpSource;
+
+/// Addresses given on the command line, for when whatever is running this program already knows them.
+#[derive(Debug)]
+pub struct Given {
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+}
+
+impl Given {
+    pub fn new(ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> Self {
+        Self { ipv4, ipv6 }
+    }
+}
+
+impl IpSource for Given {
+    fn name(&self) -> String {
+        "the command line".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        _ipv4: bool,
+        _ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move { Ok((self.ipv4, self.ipv6)) }.boxed_local()
+    }
+}
@@ -0,0 +1,103 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+
+use super::{IpSource, each_family};
+
+/// The services that are asked for an IPv4 address when none are configured.
+pub const DEFAULT_IPV4_URLS: &[&str] = &["https://api.ipify.org", "https://ipv4.icanhazip.com"];
+
+/// The services that are asked for an IPv6 address when none are configured.
+pub const DEFAULT_IPV6_URLS: &[&str] = &["https://api6.ipify.org", "https://ipv6.icanhazip.com"];
+
+/// How long to wait for each service before moving on to the next one.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Asks plain "what's my IP" web services, which respond with nothing but the address that the request came from.
+///
+/// Each family has its own list of URLs, which are tried in order until one of them answers. Requests for each family
+/// are only ever sent over that protocol, so a dual-stack service can appear in both lists.
+#[derive(Debug)]
+pub struct Http {
+    ipv4: Vec<Url>,
+    ipv6: Vec<Url>,
+    ipv4_client: reqwest::Client,
+    ipv6_client: reqwest::Client,
+}
+
+impl Http {
+    pub fn new(ipv4: Vec<Url>, ipv6: Vec<Url>) -> Self {
+        // Binding to the unspecified address of a family forces connections to be made over that family.
+        let client = |local: IpAddr| {
+            let ua_str = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            reqwest::ClientBuilder::new()
+                .user_agent(ua_str)
+                .local_address(local)
+                .timeout(TIMEOUT)
+                .build()
+                .unwrap()
+        };
+
+        Self {
+            ipv4,
+            ipv6,
+            ipv4_client: client(Ipv4Addr::UNSPECIFIED.into()),
+            ipv6_client: client(Ipv6Addr::UNSPECIFIED.into()),
+        }
+    }
+
+    /// Finds our address of one family by asking each service in turn.
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        let (urls, client, family) = match ipv6 {
+            false => (&self.ipv4, &self.ipv4_client, "IPv4"),
+            true => (&self.ipv6, &self.ipv6_client, "IPv6"),
+        };
+
+        let mut last_err = None;
+        for url in urls {
+            match query(client, url, ipv6).await {
+                Ok(addr) => return Ok(addr),
+                Err(err) => {
+                    log::debug!("HTTP: {url}: {err:#}");
+                    last_err = Some(err.wrap_err(format!("Failed to get address from {url}")));
+                },
+            }
+        }
+        Err(last_err.unwrap_or_else(|| eyre!("No HTTP services are configured for {family}")))
+    }
+}
+
+impl IpSource for Http {
+    fn name(&self) -> String {
+        "HTTP services".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("HTTP", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
+
+/// Sends a single request, and reads the address out of its response.
+async fn query(client: &reqwest::Client, url: &Url, ipv6: bool) -> eyre::Result<IpAddr> {
+    let body = (client.get(url.clone()).send().await)
+        .and_then(|res| res.error_for_status())
+        .wrap_err("GET request failed")?
+        .text()
+        .await
+        .wrap_err("Failed to read GET response body")?;
+
+    let addr = body.trim();
+    match addr.parse::<IpAddr>() {
+        Ok(addr) if addr.is_ipv6() == ipv6 => Ok(addr),
+        Ok(addr) => Err(eyre!("Service responded with {addr}, which is the wrong type of address")),
+        Err(_) => Err(eyre!("Service responded with {addr:?} instead of an address")),
+    }
+}
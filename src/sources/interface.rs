@@ -0,0 +1,63 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use eyre::WrapErr;
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+
+use super::IpSource;
+use crate::api::IpAddrExt;
+use crate::netif;
+
+/// Reads addresses straight off of one of this machine's network interfaces.
+///
+/// This is useful on machines with more than one connection to the internet, where Porkbun would only see whichever
+/// address outgoing requests happen to use.
+#[derive(Debug)]
+pub struct Interface {
+    name: String,
+}
+
+impl Interface {
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+}
+
+impl IpSource for Interface {
+    fn name(&self) -> String {
+        format!("interface {}", self.name)
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move {
+            let addrs = netif::interface_addrs(&self.name)
+                .wrap_err_with(|| format!("Failed to read addresses of interface {}", self.name))?;
+
+            let v4 = (addrs.iter())
+                .filter_map(|addr| match addr {
+                    IpAddr::V4(addr) if !addr.is_loopback() && !addr.is_link_local() => Some(*addr),
+                    _ => None,
+                })
+                .min_by_key(|addr| addr.private_kind().is_some());
+
+            // On Linux, we can also tell which IPv6 addresses are temporary (and so will stop working soon) or aren't
+            // ready to be used yet.
+            let locals = netif::local_ipv6_addrs().unwrap_or_default();
+            let flags = |addr: &Ipv6Addr| locals.iter().find(|local| local.addr == *addr);
+            let v6 = (addrs.iter())
+                .filter_map(|addr| match addr {
+                    IpAddr::V6(addr) if !addr.is_loopback() && !addr.is_unicast_link_local() => Some(*addr),
+                    _ => None,
+                })
+                .filter(|addr| flags(addr).is_none_or(|local| local.is_usable()))
+                .min_by_key(|addr| (addr.private_kind().is_some(), flags(addr).is_some_and(|l| l.is_temporary())));
+
+            Ok((v4.filter(|_| ipv4), v6.filter(|_| ipv6)))
+        }
+        .boxed_local()
+    }
+}
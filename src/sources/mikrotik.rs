@@ -0,0 +1,128 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+use serde::Deserialize;
+
+use super::{IpSource, each_family, warn_if_double_nat};
+use crate::api::IpAddrExt;
+
+/// How long to wait for each request to the router.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reads the addresses of a MikroTik router's WAN interface through the RouterOS (v7+) [REST API][rest].
+///
+/// [rest]: https://help.mikrotik.com/docs/spaces/ROS/pages/47579162/REST+API
+#[derive(Debug)]
+pub struct Mikrotik {
+    url: Url,
+    username: String,
+    password: String,
+    interface: String,
+    reqwest: reqwest::Client,
+}
+
+/// One entry from `/ip/address` or `/ipv6/address`. RouterOS sends every value as a string, including booleans.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct AddressEntry {
+    /// The address with its prefix length, e.g. `203.0.113.5/24`.
+    address: String,
+    #[serde(default)]
+    disabled: Option<String>,
+    #[serde(default)]
+    invalid: Option<String>,
+    /// Only on IPv6 addresses.
+    #[serde(default)]
+    link_local: Option<String>,
+}
+
+impl AddressEntry {
+    fn is_active(&self) -> bool {
+        [&self.disabled, &self.invalid, &self.link_local]
+            .iter()
+            .all(|flag| flag.as_deref() != Some("true"))
+    }
+
+    fn addr(&self) -> Option<IpAddr> {
+        let addr = self.address.split_once('/').map_or(&self.address[..], |(addr, _)| addr);
+        addr.parse().ok()
+    }
+}
+
+impl Mikrotik {
+    pub fn new(url: Url, username: String, password: String, interface: String, insecure: bool) -> Self {
+        let ua_str = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let reqwest = reqwest::ClientBuilder::new()
+            .user_agent(ua_str)
+            .timeout(TIMEOUT)
+            .danger_accept_invalid_certs(insecure)
+            .build()
+            .unwrap();
+
+        Self {
+            url,
+            username,
+            password,
+            interface,
+            reqwest,
+        }
+    }
+
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        let (path, family) = match ipv6 {
+            false => ("rest/ip/address", "IPv4"),
+            true => ("rest/ipv6/address", "IPv6"),
+        };
+        let url = self.url.join(path).wrap_err("Invalid MikroTik URL")?;
+
+        log::trace!("Sending GET request to {url} for interface {}", self.interface);
+        let res = (self.reqwest.get(url))
+            .basic_auth(&self.username, Some(&self.password))
+            .query(&[("interface", &self.interface)])
+            .send()
+            .await
+            .wrap_err("GET request to router failed")?;
+
+        let status = res.status();
+        if !status.is_success() {
+            // Errors look like `{"error":401,"message":"Unauthorized"}`, sometimes with more `detail`.
+            let body = res.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| Some(body.get("detail").or(body.get("message"))?.as_str()?.to_string()))
+                .unwrap_or_else(|| status.to_string());
+            return Err(eyre!("Router responded with an error: {message}"));
+        }
+
+        let entries = (res.json::<Vec<AddressEntry>>().await).wrap_err("Failed to parse router's response")?;
+        let addr = (entries.iter())
+            .filter(|entry| entry.is_active())
+            .filter_map(AddressEntry::addr)
+            .filter(|addr| addr.is_ipv6() == ipv6)
+            .min_by_key(|addr| addr.private_kind().is_some())
+            .ok_or_else(|| eyre!("Interface {} has no {family} address", self.interface))?;
+
+        if let IpAddr::V4(addr) = addr {
+            warn_if_double_nat(&self.url, addr);
+        }
+        Ok(addr)
+    }
+}
+
+impl IpSource for Mikrotik {
+    fn name(&self) -> String {
+        format!("the MikroTik router's {} interface", self.interface)
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("MikroTik", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
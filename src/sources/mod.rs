@@ -0,0 +1,157 @@
+//! Ways of finding this machine's current addresses.
+
+mod command;
+mod consensus;
+mod dns;
+mod fallback;
+mod file;
+mod fritzbox;
+mod given;
+mod http;
+mod interface;
+mod mikrotik;
+mod natpmp;
+mod porkbun;
+mod soap;
+mod stun;
+mod upnp;
+
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::rc::Rc;
+
+use futures::future::LocalBoxFuture;
+
+pub use self::command::Command;
+pub use self::consensus::Consensus;
+pub use self::dns::{Dns, query_addresses};
+pub use self::fallback::Fallback;
+pub use self::file::File;
+pub use self::fritzbox::{DEFAULT_URL as DEFAULT_FRITZBOX_URL, FritzBox};
+pub use self::given::Given;
+pub use self::http::{DEFAULT_IPV4_URLS, DEFAULT_IPV6_URLS, Http};
+pub use self::interface::Interface;
+pub use self::mikrotik::Mikrotik;
+pub use self::natpmp::NatPmp;
+pub use self::porkbun::Porkbun;
+pub use self::stun::{DEFAULT_SERVERS as DEFAULT_STUN_SERVERS, Stun};
+pub use self::upnp::Upnp;
+use crate::api::{IpAddrExt, PorkbunClient};
+use crate::config::{ConsensusPolicy, IpSourceConfig};
+
+/// Somewhere that this machine's current public addresses can be found. Porkbun's `/ping` endpoints are the default.
+pub trait IpSource {
+    /// A human-readable description of this source, for use in log and error messages.
+    fn name(&self) -> String;
+
+    /// Finds the current addresses. Only the families that are asked for need to be looked up; it's up to the caller
+    /// to decide whether a missing address is an error.
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>>;
+}
+
+/// Sets up the configured source, followed by any fallbacks (which are tried in order if it fails). When several sources
+/// are configured at once, their answers are combined according to `policy`.
+pub fn from_config(
+    config: IpSourceConfig,
+    fallback: Option<IpSourceConfig>,
+    policy: ConsensusPolicy,
+    client: &Rc<PorkbunClient>,
+) -> Box<dyn IpSource> {
+    let primary = build(config, policy, client);
+    let fallbacks = match fallback {
+        None => return primary,
+        Some(IpSourceConfig::Many(configs)) => configs,
+        Some(config) => vec![config],
+    };
+
+    let mut sources = vec![primary];
+    sources.extend(fallbacks.into_iter().map(|config| build(config, policy, client)));
+    Box::new(Fallback::new(sources))
+}
+
+fn build(config: IpSourceConfig, policy: ConsensusPolicy, client: &Rc<PorkbunClient>) -> Box<dyn IpSource> {
+    match config {
+        IpSourceConfig::Porkbun => Box::new(Porkbun::new(client.clone())),
+        IpSourceConfig::Interface(name) => Box::new(Interface::new(name)),
+        IpSourceConfig::Command(command) => Box::new(Command::new(command)),
+        IpSourceConfig::File { path, max_age } => Box::new(File::new(path, max_age)),
+        IpSourceConfig::Dns(services) => Box::new(Dns::new(services)),
+        IpSourceConfig::Http { ipv4, ipv6 } => Box::new(Http::new(ipv4, ipv6)),
+        IpSourceConfig::Stun(servers) => Box::new(Stun::new(servers)),
+        IpSourceConfig::Upnp(location) => Box::new(Upnp::new(location)),
+        IpSourceConfig::NatPmp(gateway) => Box::new(NatPmp::new(gateway)),
+        IpSourceConfig::Mikrotik {
+            url,
+            username,
+            password,
+            interface,
+            insecure,
+        } => Box::new(Mikrotik::new(url, username, password, interface, insecure)),
+        IpSourceConfig::FritzBox { url, username, password } => Box::new(FritzBox::new(url, username, password)),
+        IpSourceConfig::Given(ipv4, ipv6) => Box::new(Given::new(ipv4, ipv6)),
+        IpSourceConfig::Many(configs) => {
+            let sources = configs.into_iter().map(|config| build(config, policy, client)).collect();
+            Box::new(Consensus::new(sources, policy))
+        },
+    }
+}
+
+/// Looks up each of the wanted address families separately (and at the same time) with `lookup`, which is given `true`
+/// for IPv6.
+///
+/// A missing address is only an error if nothing at all could be found; otherwise, it's up to the caller to decide
+/// whether it's needed.
+async fn each_family<F, Fut>(
+    name: &str,
+    ipv4: bool,
+    ipv6: bool,
+    lookup: F,
+) -> eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>
+where
+    F: Fn(bool) -> Fut,
+    Fut: Future<Output = eyre::Result<IpAddr>>,
+{
+    let v4 = async { if ipv4 { Some(lookup(false).await) } else { None } };
+    let v6 = async { if ipv6 { Some(lookup(true).await) } else { None } };
+    let (v4, v6) = match futures::join!(v4, v6) {
+        (Some(Err(err)), None | Some(Err(_))) | (None, Some(Err(err))) => return Err(err),
+        found => found,
+    };
+
+    let found = |res: Option<eyre::Result<IpAddr>>| res?.inspect_err(|err| log::debug!("{name}: {err:#}")).ok();
+    let v4 = found(v4).and_then(|addr| match addr {
+        IpAddr::V4(addr) => Some(addr),
+        IpAddr::V6(_) => None,
+    });
+    let v6 = found(v6).and_then(|addr| match addr {
+        IpAddr::V6(addr) => Some(addr),
+        IpAddr::V4(_) => None,
+    });
+    Ok((v4, v6))
+}
+
+/// Makes some random bytes (e.g., for matching up responses with the requests they're for).
+pub fn random_id<const N: usize>() -> [u8; N] {
+    let mut id = [0u8; N];
+    for chunk in id.chunks_mut(4) {
+        let random = RandomState::new().build_hasher().finish();
+        chunk.copy_from_slice(&random.to_be_bytes()[..chunk.len()]);
+    }
+    id
+}
+
+/// Warns when a router reports a private external address, since that means it's behind another NAT and any address
+/// it reports won't be reachable from the internet.
+fn warn_if_double_nat(router: impl Display, addr: Ipv4Addr) {
+    if let Some(kind) = addr.private_kind() {
+        log::warn!(
+            "The router at {router} reports {kind} external address of {addr}; it is probably behind another NAT"
+        );
+    }
+}
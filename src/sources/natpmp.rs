@@ -0,0 +1,189 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use tokio::net::UdpSocket;
+
+use super::{IpSource, each_family, random_id, warn_if_double_nat};
+use crate::netif;
+
+/// The port that NAT-PMP and PCP servers listen on.
+const PORT: u16 = 5351;
+
+/// How long to wait for each response before asking again.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many times to ask before giving up.
+const ATTEMPTS: usize = 3;
+
+/// How long (in seconds) the throwaway PCP mapping should last, in case deleting it fails.
+const PCP_LIFETIME: u32 = 30;
+
+const UNSUPPORTED_VERSION: u16 = 1;
+
+/// Asks the local router for its external address with [NAT-PMP][nat-pmp], or with [PCP][pcp] if the router only
+/// supports that.
+///
+/// PCP has no way of asking for the external address on its own, so a short-lived UDP port mapping is requested
+/// instead (and deleted again right away), since the response to that includes the external address. Like UPnP, this
+/// never leaves the local network, and only deals with IPv4.
+///
+/// [nat-pmp]: https://datatracker.ietf.org/doc/html/rfc6886
+/// [pcp]: https://datatracker.ietf.org/doc/html/rfc6887
+#[derive(Debug)]
+pub struct NatPmp {
+    /// The router to ask, if it isn't the default gateway.
+    gateway: Option<Ipv4Addr>,
+}
+
+impl NatPmp {
+    pub fn new(gateway: Option<Ipv4Addr>) -> Self {
+        Self { gateway }
+    }
+
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        if ipv6 {
+            return Err(eyre!("NAT-PMP and PCP can only find IPv4 addresses"));
+        }
+
+        let gateway = match self.gateway {
+            Some(gateway) => gateway,
+            None => netif::default_gateway().wrap_err("Failed to find the default gateway")?,
+        };
+
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .wrap_err("Failed to open socket")?;
+        socket
+            .connect((gateway, PORT))
+            .await
+            .wrap_err_with(|| format!("Failed to connect to {gateway}"))?;
+
+        let addr = match nat_pmp(&socket).await {
+            Ok(res) => res,
+            Err(Unsupported) => {
+                log::debug!("NAT-PMP: {gateway} doesn't support NAT-PMP; trying PCP");
+                pcp(&socket).await
+            },
+        }
+        .wrap_err_with(|| format!("Failed to get address from {gateway}"))?;
+
+        warn_if_double_nat(gateway, addr);
+        Ok(IpAddr::V4(addr))
+    }
+}
+
+impl IpSource for NatPmp {
+    fn name(&self) -> String {
+        "the router (NAT-PMP)".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("NAT-PMP", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
+
+/// The router only speaks a different version of the protocol.
+struct Unsupported;
+
+/// Sends NAT-PMP's "external address" request.
+async fn nat_pmp(socket: &UdpSocket) -> Result<eyre::Result<Ipv4Addr>, Unsupported> {
+    let res = exchange(socket, &[0, 0], |msg| {
+        // Version, opcode (128 + ours), result code, seconds since the mapping table was reset, then the address.
+        if msg.len() >= 2 && msg[0] != 0 {
+            return Some(Err(Unsupported));
+        } else if msg.len() < 12 || msg[1] != 128 {
+            return None;
+        }
+
+        let result = u16::from_be_bytes([msg[2], msg[3]]);
+        let addr = Ipv4Addr::new(msg[8], msg[9], msg[10], msg[11]);
+        match result {
+            0 => Some(Ok(Ok(addr))),
+            UNSUPPORTED_VERSION => Some(Err(Unsupported)),
+            3 => Some(Ok(Err(eyre!("Router has no external address (is it connected?)")))),
+            code => Some(Ok(Err(eyre!("Router responded with result code {code}")))),
+        }
+    });
+
+    match res.await {
+        Ok(Ok(res)) => Ok(res),
+        Ok(Err(Unsupported)) => Err(Unsupported),
+        Err(err) => Ok(Err(err)),
+    }
+}
+
+/// Requests (and then deletes) a PCP mapping for our socket's port, to find out which external address it's on.
+async fn pcp(socket: &UdpSocket) -> eyre::Result<Ipv4Addr> {
+    let SocketAddr::V4(local) = socket.local_addr().wrap_err("Failed to read socket address")? else {
+        unreachable!("socket is bound to an IPv4 address");
+    };
+
+    let nonce = random_id::<12>();
+    let request = |lifetime: u32| {
+        let mut request = Vec::with_capacity(60);
+        // Version, opcode (MAP), two reserved bytes, lifetime, then our own address.
+        request.extend([2, 1, 0, 0]);
+        request.extend(lifetime.to_be_bytes());
+        request.extend(local.ip().to_ipv6_mapped().octets());
+        // Mapping nonce, protocol (UDP), three reserved bytes, internal port, suggested external port and address.
+        request.extend(nonce);
+        request.extend([17, 0, 0, 0]);
+        request.extend(local.port().to_be_bytes());
+        request.extend(0u16.to_be_bytes());
+        request.extend(Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+        request
+    };
+
+    let res = exchange(socket, &request(PCP_LIFETIME), |msg| {
+        // Version, opcode (128 + ours), a reserved byte, and the result code, then the rest of the header; the
+        // assigned external address is at the end of the MAP data, which starts with our nonce.
+        if msg.len() < 60 || msg[0] != 2 || msg[1] != 0x81 || msg[24..36] != nonce {
+            return None;
+        }
+
+        let addr = Ipv6Addr::from(<[u8; 16]>::try_from(&msg[44..60]).unwrap());
+        match msg[3] {
+            0 => Some(
+                addr.to_ipv4_mapped()
+                    .ok_or_else(|| eyre!("Router assigned a non-IPv4 address ({addr})")),
+            ),
+            7 => Some(Err(eyre!("Router has no external address (is it connected?)"))),
+            code => Some(Err(eyre!("Router responded with PCP result code {code}"))),
+        }
+    })
+    .await
+    .and_then(|res| res);
+
+    // Clean up after ourselves; the mapping will expire on its own soon if this doesn't work.
+    if res.is_ok()
+        && let Err(err) = socket.send(&request(0)).await
+    {
+        log::debug!("PCP: failed to delete mapping: {err}");
+    }
+
+    res
+}
+
+/// Sends a request until `parse` finds a response to it.
+async fn exchange<T>(socket: &UdpSocket, request: &[u8], parse: impl Fn(&[u8]) -> Option<T>) -> eyre::Result<T> {
+    let mut buf = [0u8; 1100];
+    for _ in 0..ATTEMPTS {
+        socket.send(request).await.wrap_err("Failed to send request")?;
+        let deadline = tokio::time::Instant::now() + TIMEOUT;
+        while let Ok(res) = tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+            let len = res.wrap_err("Failed to receive response")?;
+            if let Some(res) = parse(&buf[..len]) {
+                return Ok(res);
+            }
+        }
+    }
+
+    Err(eyre!("No response after {ATTEMPTS} attempts"))
+}
@@ -0,0 +1,77 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::rc::Rc;
+
+use eyre::eyre;
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+
+use super::IpSource;
+use crate::api::PorkbunClient;
+use crate::netif;
+
+/// Asks Porkbun's `/ping` endpoints which address our requests come from. This is the default source.
+#[derive(Debug)]
+pub struct Porkbun {
+    client: Rc<PorkbunClient>,
+}
+
+impl Porkbun {
+    pub fn new(client: Rc<PorkbunClient>) -> Self {
+        Self { client }
+    }
+}
+
+impl IpSource for Porkbun {
+    fn name(&self) -> String {
+        "Porkbun's API".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        async move {
+            // The base `/ping` endpoint returns either IPv6 or IPv4, so it's only needed for IPv6. IPv4 comes from the
+            // IPv4-only endpoint instead. When both families are wanted, ask both at once.
+            let (dual, only_v4) =
+                futures::join!(async { if ipv6 { Some(self.client.ping().await) } else { None } }, async {
+                    if ipv4 { Some(self.client.ping_v4().await) } else { None }
+                },);
+
+            let (dual_v4, v6) = match dual {
+                None => (None, None),
+                Some(Ok(IpAddr::V6(addr))) => (None, Some(netif::prefer_stable_ipv6(addr))),
+                // The base `/ping` endpoint *always* returns IPv6 when possible (AFAIK). If it gives us IPv4, there's no
+                // way for us to get an IPv6.
+                Some(Ok(IpAddr::V4(_))) if !ipv4 => {
+                    return Err(eyre!("Tried to get IPv6 address from Porkbun API, but only got IPv4"));
+                },
+                Some(Ok(IpAddr::V4(addr))) => {
+                    log::debug!("Porkbun API only returned an IPv4 address.");
+                    (Some(addr), None)
+                },
+                Some(Err(err)) if !ipv4 => return Err(err),
+                Some(Err(err)) => {
+                    log::debug!("Failed to get IPv6 address from Porkbun API: {err:#}");
+                    (None, None)
+                },
+            };
+
+            let v4 = match only_v4 {
+                None => None,
+                Some(Ok(addr)) => Some(addr),
+                // I don't actually know what happens when you ping Porkbun from somewhere without an IPv4 address. Is
+                // that even possible yet? Has anywhere actually fully gotten rid of IPv4?
+                Some(Err(err)) if dual_v4.is_none() && v6.is_none() => return Err(err),
+                Some(Err(err)) => {
+                    log::debug!("Failed to get IPv4 address from Porkbun's IPv4-only endpoint: {err:#}");
+                    dual_v4
+                },
+            };
+
+            Ok((v4, v6))
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,124 @@
+//! Just enough SOAP (and XML) to ask routers about their connections.
+
+use eyre::{WrapErr, eyre};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderValue, WWW_AUTHENTICATE};
+use reqwest::{StatusCode, Url};
+
+use super::random_id;
+
+/// A username and password for routers that need them, sent with HTTP digest authentication.
+#[derive(Debug)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Calls an argument-less SOAP action, returning the body of the response.
+pub async fn soap_request(
+    client: &reqwest::Client,
+    url: Url,
+    service_type: &str,
+    action: &str,
+    credentials: Option<&Credentials>,
+) -> eyre::Result<String> {
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service_type}\"/></s:Body>\
+         </s:Envelope>"
+    );
+
+    let request = (client.post(url.clone()))
+        .header(CONTENT_TYPE, HeaderValue::from_static("text/xml; charset=\"utf-8\""))
+        .header("SOAPAction", format!("\"{service_type}#{action}\""))
+        .body(body);
+
+    // The first request is always sent without credentials, since the server has to hand out a nonce first.
+    let retry = request.try_clone().expect("request body should not be a stream");
+    let mut res = request.send().await.wrap_err_with(|| format!("{action} request failed"))?;
+    if res.status() == StatusCode::UNAUTHORIZED
+        && let Some(credentials) = credentials
+    {
+        let challenge = (res.headers().get(WWW_AUTHENTICATE))
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| eyre!("{action} needs authentication, but the server didn't say how"))?;
+        let authorization = digest_authorization(challenge, credentials, "POST", url.path())?;
+        res = (retry.header(AUTHORIZATION, authorization).send().await)
+            .wrap_err_with(|| format!("{action} request failed"))?;
+    }
+
+    let status = res.status();
+    let text = res.text().await.wrap_err_with(|| format!("Failed to read {action} response"))?;
+    match status {
+        _ if status.is_success() => Ok(text),
+        StatusCode::UNAUTHORIZED if credentials.is_none() => {
+            Err(eyre!("{action} needs a username and password, but none are configured"))
+        },
+        StatusCode::UNAUTHORIZED => Err(eyre!("{action} was refused; check the username and password")),
+        _ => {
+            let reason = xml_tag(&text, "errorDescription").unwrap_or("no description");
+            Err(eyre!("{action} failed with status {status} ({reason})"))
+        },
+    }
+}
+
+/// Answers an HTTP digest authentication challenge ([RFC 7616][rfc]), which is what TR-064 uses. Only MD5 (with or
+/// without `qop=auth`) is supported, since that's all that routers use.
+///
+/// [rfc]: https://datatracker.ietf.org/doc/html/rfc7616
+fn digest_authorization(challenge: &str, credentials: &Credentials, method: &str, uri: &str) -> eyre::Result<String> {
+    let params = challenge
+        .strip_prefix("Digest ")
+        .ok_or_else(|| eyre!("Server asked for unsupported authentication: {challenge}"))?;
+
+    // Parameters are comma-separated `key=value` or `key="value"` pairs. Quoted values may themselves contain commas
+    // (e.g., `qop="auth,auth-int"`), so they can't just be split on commas.
+    let mut fields = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+        fields.push((key.trim(), value));
+        rest = after.trim_start_matches([',', ' ']);
+    }
+    let field = |name: &str| fields.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, v)| *v);
+
+    let realm = field("realm").unwrap_or_default();
+    let nonce = field("nonce").ok_or_else(|| eyre!("Authentication challenge has no nonce"))?;
+    if let Some(algorithm) = field("algorithm")
+        && !algorithm.eq_ignore_ascii_case("MD5")
+    {
+        return Err(eyre!("Server asked for unsupported digest algorithm {algorithm}"));
+    }
+
+    let md5 = |text: String| format!("{:x}", md5::compute(text));
+    let Credentials { username, password } = credentials;
+    let ha1 = md5(format!("{username}:{realm}:{password}"));
+    let ha2 = md5(format!("{method}:{uri}"));
+
+    let mut header = format!("Digest username=\"{username}\", realm=\"{realm}\", nonce=\"{nonce}\", uri=\"{uri}\"");
+    if field("qop").is_some_and(|qop| qop.split(',').any(|qop| qop.trim() == "auth")) {
+        let cnonce = random_id::<8>().iter().map(|b| format!("{b:02x}")).collect::<String>();
+        let response = md5(format!("{ha1}:{nonce}:00000001:{cnonce}:auth:{ha2}"));
+        header += &format!(", qop=auth, nc=00000001, cnonce=\"{cnonce}\", response=\"{response}\"");
+    } else {
+        let response = md5(format!("{ha1}:{nonce}:{ha2}"));
+        header += &format!(", response=\"{response}\"");
+    }
+    if let Some(opaque) = field("opaque") {
+        header += &format!(", opaque=\"{opaque}\"");
+    }
+
+    Ok(header)
+}
+
+/// Finds the text inside the first `<name>` element in some XML. Only good enough for the simple documents that
+/// routers send back.
+pub fn xml_tag<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{name}>"))? + name.len() + 2;
+    let end = xml[start..].find(&format!("</{name}>"))?;
+    Some(xml[start..start + end].trim())
+}
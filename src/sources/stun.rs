@@ -0,0 +1,171 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use tokio::net::{UdpSocket, lookup_host};
+
+use super::{IpSource, each_family, random_id};
+
+/// The servers that are asked when none are configured.
+pub const DEFAULT_SERVERS: &[&str] = &["stun.cloudflare.com:3478", "stun.l.google.com:19302"];
+
+/// How long to wait for each response before asking again.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many times to ask each server before moving on to the next one.
+const ATTEMPTS: usize = 3;
+
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const MAPPED_ADDRESS: u16 = 0x0001;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// Asks [STUN][rfc] servers which address our UDP packets come from.
+///
+/// Servers are tried in order until one of them answers. IPv4 and IPv6 are looked up separately, by talking to each
+/// server over that protocol.
+///
+/// [rfc]: https://datatracker.ietf.org/doc/html/rfc8489
+#[derive(Debug)]
+pub struct Stun {
+    servers: Vec<String>,
+}
+
+impl Stun {
+    pub fn new(servers: Vec<String>) -> Self {
+        Self { servers }
+    }
+
+    /// Finds our address of one family by asking each server in turn.
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        let mut last_err = None;
+        for server in &self.servers {
+            match query(server, ipv6).await {
+                Ok(addr) => return Ok(addr),
+                Err(err) => {
+                    log::debug!("STUN: {server}: {err:#}");
+                    last_err = Some(err.wrap_err(format!("Failed to get address from {server}")));
+                },
+            }
+        }
+        Err(last_err.unwrap_or_else(|| eyre!("No STUN servers are configured")))
+    }
+}
+
+impl IpSource for Stun {
+    fn name(&self) -> String {
+        "STUN".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("STUN", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
+
+/// Sends a binding request to a single server over IPv4 or IPv6, and returns the address it saw.
+async fn query(server: &str, ipv6: bool) -> eyre::Result<IpAddr> {
+    let family = if ipv6 { "IPv6" } else { "IPv4" };
+    let remote = (lookup_host(server).await)
+        .wrap_err("Failed to look up server")?
+        .find(|addr| addr.is_ipv6() == ipv6)
+        .ok_or_else(|| eyre!("Server has no {family} address"))?;
+
+    let local = match ipv6 {
+        true => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+        false => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(local).await.wrap_err("Failed to open socket")?;
+    socket
+        .connect(remote)
+        .await
+        .wrap_err_with(|| format!("Failed to connect over {family}"))?;
+
+    let txid = random_id::<12>();
+    let mut request = Vec::with_capacity(20);
+    request.extend(BINDING_REQUEST.to_be_bytes());
+    request.extend(0u16.to_be_bytes());
+    request.extend(MAGIC_COOKIE.to_be_bytes());
+    request.extend(txid);
+
+    let mut buf = [0u8; 1024];
+    for _ in 0..ATTEMPTS {
+        socket.send(&request).await.wrap_err("Failed to send request")?;
+        match tokio::time::timeout(TIMEOUT, socket.recv(&mut buf)).await {
+            Ok(res) => {
+                let len = res.wrap_err("Failed to receive response")?;
+                match parse_response(&buf[..len], &txid)? {
+                    Some(addr) => return Ok(addr),
+                    // Something other than the response to our request; ask again.
+                    None => continue,
+                }
+            },
+            Err(_) => continue,
+        }
+    }
+
+    Err(eyre!("No response after {ATTEMPTS} attempts"))
+}
+
+/// Reads the mapped address out of a binding response. Returns `None` if the message isn't a response to our request.
+fn parse_response(msg: &[u8], txid: &[u8; 12]) -> eyre::Result<Option<IpAddr>> {
+    if msg.len() < 20 || msg[4..8] != MAGIC_COOKIE.to_be_bytes() || msg[8..20] != txid[..] {
+        return Ok(None);
+    }
+
+    let typ = u16::from_be_bytes([msg[0], msg[1]]);
+    if typ != BINDING_SUCCESS {
+        return Err(eyre!("Server responded with message type {typ:#06x} instead of a binding success"));
+    }
+
+    let mut mapped = None;
+    let mut attrs = &msg[20..];
+    while attrs.len() >= 4 {
+        let typ = u16::from_be_bytes([attrs[0], attrs[1]]);
+        let len = usize::from(u16::from_be_bytes([attrs[2], attrs[3]]));
+        let Some(value) = attrs.get(4..4 + len) else {
+            break;
+        };
+
+        match typ {
+            XOR_MAPPED_ADDRESS => return parse_address(value, Some(txid)).map(Some),
+            MAPPED_ADDRESS => mapped = Some(parse_address(value, None)?),
+            _ => {},
+        }
+
+        // Attributes are padded out to a multiple of four bytes.
+        let padded = (4 + len).next_multiple_of(4);
+        attrs = attrs.get(padded..).unwrap_or_default();
+    }
+
+    mapped.map(Some).ok_or_else(|| eyre!("Response has no mapped address"))
+}
+
+/// Reads a (XOR-)MAPPED-ADDRESS attribute's value. The transaction ID is only needed for XOR-MAPPED-ADDRESS.
+fn parse_address(value: &[u8], xor: Option<&[u8; 12]>) -> eyre::Result<IpAddr> {
+    let mut mask = [0u8; 16];
+    if let Some(txid) = xor {
+        mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(txid);
+    }
+
+    // Reserved byte, family, two bytes of port, then the address itself.
+    let unmask = |bytes: &[u8]| bytes.iter().zip(mask).map(|(b, m)| b ^ m).collect::<Vec<_>>();
+    match (value.get(1), value.get(4..)) {
+        (Some(1), Some(addr)) if addr.len() == 4 => {
+            let octets: [u8; 4] = unmask(addr).try_into().unwrap();
+            Ok(IpAddr::V4(Ipv4Addr::from(octets)))
+        },
+        (Some(2), Some(addr)) if addr.len() == 16 => {
+            let octets: [u8; 16] = unmask(addr).try_into().unwrap();
+            Ok(IpAddr::V6(Ipv6Addr::from(octets)))
+        },
+        _ => Err(eyre!("Response has a malformed mapped address")),
+    }
+}
@@ -0,0 +1,160 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::FutureExt;
+use futures::future::LocalBoxFuture;
+use reqwest::Url;
+use tokio::net::UdpSocket;
+
+use super::soap::{soap_request, xml_tag};
+use super::{IpSource, each_family, warn_if_double_nat};
+
+/// Where SSDP searches are sent.
+const SSDP_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), 1900);
+
+/// How long to wait for routers to answer a search.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long to wait for each HTTP request to the router.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The services which can report the router's external address. Routers with a PPPoE connection often only have the
+/// second one.
+const WAN_SERVICES: &[&str] = &["WANIPConnection", "WANPPPConnection"];
+
+/// Asks the local router for its external address with [UPnP IGD][igd]'s `GetExternalIPAddress` action.
+///
+/// This never leaves the local network, and since it's the address the router itself has, it shows when the router is
+/// behind another NAT (which would make any published address useless). IGD only deals with IPv4.
+///
+/// [igd]: https://openconnectivity.org/developer/specifications/upnp-resources/upnp/internet-gateway-device-igd-v-2-0/
+#[derive(Debug)]
+pub struct Upnp {
+    /// The router's device description, if it shouldn't be discovered with SSDP.
+    location: Option<Url>,
+    reqwest: reqwest::Client,
+}
+
+impl Upnp {
+    pub fn new(location: Option<Url>) -> Self {
+        let ua_str = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let reqwest = reqwest::ClientBuilder::new()
+            .user_agent(ua_str)
+            .timeout(TIMEOUT)
+            .build()
+            .unwrap();
+        Self { location, reqwest }
+    }
+
+    async fn lookup(&self, ipv6: bool) -> eyre::Result<IpAddr> {
+        if ipv6 {
+            return Err(eyre!("UPnP IGD can only find IPv4 addresses"));
+        }
+
+        let locations = match &self.location {
+            Some(location) => vec![location.clone()],
+            None => discover().await.wrap_err("Failed to find a router")?,
+        };
+
+        let mut last_err = None;
+        for location in &locations {
+            match self.external_address(location).await {
+                Ok(addr) => {
+                    warn_if_double_nat(location, addr);
+                    return Ok(IpAddr::V4(addr));
+                },
+                Err(err) => {
+                    log::debug!("UPnP: {location}: {err:#}");
+                    last_err = Some(err.wrap_err(format!("Failed to get address from router at {location}")));
+                },
+            }
+        }
+        Err(last_err.unwrap_or_else(|| eyre!("No UPnP routers responded")))
+    }
+
+    /// Asks the router described at `location` for its external address.
+    async fn external_address(&self, location: &Url) -> eyre::Result<Ipv4Addr> {
+        let description = (self.reqwest.get(location.clone()).send().await)
+            .and_then(|res| res.error_for_status())
+            .wrap_err("Failed to fetch device description")?
+            .text()
+            .await
+            .wrap_err("Failed to read device description")?;
+
+        let (service_type, control_url) = (description.split("<service>").skip(1))
+            .filter_map(|service| Some((xml_tag(service, "serviceType")?, xml_tag(service, "controlURL")?)))
+            .find(|(service_type, _)| WAN_SERVICES.iter().any(|name| service_type.contains(name)))
+            .ok_or_else(|| eyre!("Router has no WANIPConnection or WANPPPConnection service"))?;
+
+        // Control URLs are relative to the description's URLBase, or to the description itself if there isn't one.
+        let base = match xml_tag(&description, "URLBase") {
+            Some(base) => Url::parse(base).wrap_err("Router has an invalid URLBase")?,
+            None => location.clone(),
+        };
+        let control_url = base.join(control_url).wrap_err("Router has an invalid controlURL")?;
+
+        let response = soap_request(&self.reqwest, control_url, service_type, "GetExternalIPAddress", None).await?;
+        let addr = xml_tag(&response, "NewExternalIPAddress").ok_or_else(|| eyre!("Response has no address"))?;
+        match addr.trim() {
+            "" => Err(eyre!("Router has no external address (is it connected?)")),
+            addr => addr
+                .parse()
+                .wrap_err_with(|| format!("Router responded with invalid address {addr:?}")),
+        }
+    }
+}
+
+impl IpSource for Upnp {
+    fn name(&self) -> String {
+        "the router (UPnP)".to_string()
+    }
+
+    fn addresses<'a>(
+        &'a self,
+        ipv4: bool,
+        ipv6: bool,
+    ) -> LocalBoxFuture<'a, eyre::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)>> {
+        each_family("UPnP", ipv4, ipv6, |ipv6| self.lookup(ipv6)).boxed_local()
+    }
+}
+
+/// Searches the local network for internet gateway devices with SSDP, and returns the URLs of their descriptions.
+async fn discover() -> eyre::Result<Vec<Url>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .wrap_err("Failed to open socket")?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDR}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\n\
+         ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n"
+    );
+    socket
+        .send_to(search.as_bytes(), SSDP_ADDR)
+        .await
+        .wrap_err("Failed to send search")?;
+
+    let mut locations = Vec::new();
+    let mut buf = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + SEARCH_TIMEOUT;
+    while let Ok(res) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = res.wrap_err("Failed to receive search response")?;
+        let location = (String::from_utf8_lossy(&buf[..len]).lines())
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+            .and_then(|(_, value)| Url::parse(value.trim()).ok());
+
+        match location {
+            Some(location) if !locations.contains(&location) => {
+                log::debug!("UPnP: found router at {from} with description at {location}");
+                locations.push(location);
+            },
+            Some(_) => {},
+            None => log::debug!("UPnP: ignoring search response from {from} without a location"),
+        }
+    }
+
+    match locations.is_empty() {
+        true => Err(eyre!("No UPnP routers responded within {} seconds", SEARCH_TIMEOUT.as_secs())),
+        false => Ok(locations),
+    }
+}
@@ -0,0 +1,184 @@
+//! A small cache of what was last sent to Porkbun for each target, kept between runs.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::config::Target;
+
+/// How many address changes are remembered for each target, after which the oldest ones are forgotten.
+const MAX_HISTORY: usize = 50;
+
+/// The contents of the state file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Last-pushed addresses, keyed by target (formatted as a string).
+    #[serde(default)]
+    targets: BTreeMap<String, TargetState>,
+}
+
+/// What was last successfully sent to Porkbun for a single target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct TargetState {
+    ttl: u32,
+    /// When the target's records were last checked against Porkbun, as a Unix timestamp.
+    #[serde(default)]
+    verified: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv4: Option<Ipv4Addr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv6: Option<Ipv6Addr>,
+    /// Every time a new address was pushed to the target, oldest first (up to [`MAX_HISTORY`] of them).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<AddressChange>,
+}
+
+/// A new address being pushed to a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressChange {
+    /// When the address was pushed, as a Unix timestamp.
+    pub timestamp: i64,
+    pub addr: IpAddr,
+}
+
+impl State {
+    /// Loads the state from a file.
+    ///
+    /// A missing state file is perfectly normal (e.g., on the first run), and a broken one just means every target gets
+    /// checked again; neither of those is an error.
+    pub async fn load(path: &Path) -> Self {
+        let text = match fs::read_to_string(path).await {
+            Ok(text) => text,
+            Err(err) => {
+                log::debug!("Could not read state file {}: {err}", path.display());
+                return State::default();
+            },
+        };
+
+        match serde_json::from_str(&text) {
+            Ok(state) => state,
+            Err(err) => {
+                log::warn!("Ignoring invalid state file {}: {err}", path.display());
+                State::default()
+            },
+        }
+    }
+
+    /// Writes the state out to a file, keeping only the entries for the given targets.
+    pub async fn save(&self, path: &Path, targets: &[Target]) -> eyre::Result<()> {
+        let mut state = self.clone();
+        state.targets.retain(|key, _| targets.iter().any(|tgt| tgt.to_string() == *key));
+
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir).await.wrap_err("Failed to create state directory")?;
+        }
+
+        let json = serde_json::to_string_pretty(&state)?;
+        fs::write(path, json).await.wrap_err("Failed to write state file")
+    }
+
+    /// Checks whether every one of the given addresses was already pushed to this target (with its current TTL).
+    ///
+    /// If `max_age` is given, targets that haven't been checked against Porkbun within that long are never current.
+    pub fn is_current(
+        &self,
+        target: &Target,
+        ipv4: Option<Ipv4Addr>,
+        ipv6: Option<Ipv6Addr>,
+        max_age: Option<Duration>,
+    ) -> bool {
+        let Some(cached) = self.targets.get(&target.to_string()) else {
+            return false;
+        };
+
+        let age = Utc::now().timestamp().saturating_sub(cached.verified);
+        let fresh = max_age.is_none_or(|max| u64::try_from(age).is_ok_and(|age| age < max.as_secs()));
+
+        fresh
+            && cached.ttl == target.ttl()
+            && ipv4.is_none_or(|addr| cached.ipv4 == Some(addr))
+            && ipv6.is_none_or(|addr| {
+                // When tracking prefixes, only the prefix needs to match; the rest of the address was kept as-is.
+                cached.ipv6.is_some_and(|cached| match target.tracks_ipv6_prefix() {
+                    true => target.same_ipv6_prefix(cached, addr),
+                    false => cached == addr,
+                })
+            })
+    }
+
+    /// Remembers that a target's record was just checked and now holds the given address.
+    pub fn set(&mut self, target: &Target, addr: IpAddr) {
+        let entry = self.targets.entry(target.to_string()).or_default();
+
+        // Anything cached with an old TTL is out of date, except for the history of what was pushed.
+        if entry.ttl != target.ttl() {
+            *entry = TargetState {
+                ttl: target.ttl(),
+                history: std::mem::take(&mut entry.history),
+                ..Default::default()
+            };
+        }
+
+        let now = Utc::now().timestamp();
+        entry.verified = now;
+
+        // Only the last address pushed of each family counts, since the cached one may have been forgotten since then.
+        let last = entry
+            .history
+            .iter()
+            .rev()
+            .find(|change| change.addr.is_ipv6() == addr.is_ipv6());
+        if last.is_none_or(|change| change.addr != addr) {
+            entry.history.push(AddressChange { timestamp: now, addr });
+            let excess = entry.history.len().saturating_sub(MAX_HISTORY);
+            entry.history.drain(..excess);
+        }
+
+        match addr {
+            IpAddr::V4(addr) => entry.ipv4 = Some(addr),
+            IpAddr::V6(addr) => entry.ipv6 = Some(addr),
+        }
+    }
+
+    /// Gets the history of addresses pushed to each target (oldest first), keyed by target.
+    pub fn history(&self) -> impl Iterator<Item = (&str, &[AddressChange])> {
+        (self.targets.iter())
+            .filter(|(_, cached)| !cached.history.is_empty())
+            .map(|(key, cached)| (key.as_str(), &cached.history[..]))
+    }
+
+    /// Checks whether an address of the given family was ever pushed to this target.
+    pub fn remembers(&self, target: &Target, ipv6: bool) -> bool {
+        self.targets.get(&target.to_string()).is_some_and(|cached| match ipv6 {
+            true => cached.ipv6.is_some(),
+            false => cached.ipv4.is_some(),
+        })
+    }
+
+    /// Gets the address of the given family that was last pushed to this target, if there is one.
+    pub fn last_pushed(&self, target: &Target, ipv6: bool) -> Option<IpAddr> {
+        let cached = self.targets.get(&target.to_string())?;
+        match ipv6 {
+            true => cached.ipv6.map(IpAddr::V6),
+            false => cached.ipv4.map(IpAddr::V4),
+        }
+    }
+
+    /// Forgets the address of the given family that was last pushed to this target, once its records are gone.
+    pub fn forget(&mut self, target: &Target, ipv6: bool) {
+        if let Some(cached) = self.targets.get_mut(&target.to_string()) {
+            match ipv6 {
+                true => cached.ipv6 = None,
+                false => cached.ipv4 = None,
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! What has happened so far while running as a daemon, served as JSON by its HTTP API.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::report::{Report, TargetAction};
+
+/// How many of the most recent errors to hold on to.
+const MAX_RECENT_ERRORS: usize = 20;
+
+/// The latest known state of the daemon and each of its targets.
+#[derive(Debug)]
+pub struct Status {
+    /// When the daemon started, in RFC 3339 format.
+    started: String,
+    started_at: Instant,
+    /// How often updates are meant to happen, if they happen regularly.
+    interval: Option<Duration>,
+    /// When the last run finished.
+    finished_at: Option<Instant>,
+    dry_run: bool,
+    updates: u64,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    /// When the last run without any errors finished, in RFC 3339 format.
+    last_success: Option<String>,
+    last_run: Option<Report>,
+    /// Each target's record, by target and record type.
+    targets: BTreeMap<(String, &'static str), TargetStatus>,
+    /// The most recent errors, newest first.
+    recent_errors: VecDeque<RecentError>,
+}
+
+/// A summary of the daemon's state, for `/status`.
+#[derive(Debug, Serialize)]
+pub struct Summary<'a> {
+    pub started: &'a str,
+    pub dry_run: bool,
+    pub updates: u64,
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
+    pub last_update: Option<&'a str>,
+    pub last_success: Option<&'a str>,
+    /// How many errors were encountered during the last run.
+    pub errors: usize,
+    pub targets: usize,
+    pub recent_errors: &'a VecDeque<RecentError>,
+}
+
+/// An error from a past run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentError {
+    /// When the run that hit the error finished, in RFC 3339 format.
+    pub timestamp: String,
+    /// The target the error was for, if it was for one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub error: String,
+}
+
+/// The latest known state of a single target's record.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetStatus {
+    pub target: String,
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    /// What the record was last set to (or found to already hold). Missing if it has never been updated successfully.
+    pub address: Option<String>,
+    /// What happened to the record during the last run.
+    pub action: TargetAction,
+    /// When the record was last checked, in RFC 3339 format.
+    pub last_checked: String,
+    /// When the record was last created or updated, in RFC 3339 format, if that has happened since the daemon started.
+    pub last_changed: Option<String>,
+    /// Why the last run failed to update the record, if it did.
+    pub error: Option<String>,
+}
+
+impl Status {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            started: chrono::Utc::now().to_rfc3339(),
+            started_at: Instant::now(),
+            interval: None,
+            finished_at: None,
+            dry_run,
+            updates: 0,
+            ipv4: None,
+            ipv6: None,
+            last_success: None,
+            last_run: None,
+            targets: BTreeMap::new(),
+            recent_errors: VecDeque::new(),
+        }
+    }
+
+    /// Takes note of a finished run.
+    pub fn record(&mut self, report: &Report) {
+        self.updates += 1;
+        self.finished_at = Some(Instant::now());
+        self.ipv4 = report.ipv4.or(self.ipv4);
+        self.ipv6 = report.ipv6.or(self.ipv6);
+        if report.errors == 0 {
+            self.last_success = Some(report.timestamp.clone());
+        }
+
+        let target_errors = (report.targets.iter())
+            .filter_map(|result| Some((Some(format!("{} ({})", result.target, result.typ)), result.error.clone()?)));
+        for (target, error) in report.error.clone().map(|err| (None, err)).into_iter().chain(target_errors) {
+            self.recent_errors.push_front(RecentError {
+                timestamp: report.timestamp.clone(),
+                target,
+                error,
+            });
+        }
+        self.recent_errors.truncate(MAX_RECENT_ERRORS);
+
+        for result in &report.targets {
+            let key = (result.target.clone(), result.typ);
+            let prev = self.targets.remove(&key);
+            let (address, last_changed) = match result.action {
+                TargetAction::Created | TargetAction::Updated => {
+                    (Some(result.new.clone()), Some(report.timestamp.clone()))
+                },
+                TargetAction::Unchanged => (Some(result.new.clone()), prev.and_then(|prev| prev.last_changed)),
+                TargetAction::Failed => prev.map_or((None, None), |prev| (prev.address, prev.last_changed)),
+            };
+
+            self.targets.insert(key, TargetStatus {
+                target: result.target.clone(),
+                typ: result.typ,
+                address,
+                action: result.action,
+                last_checked: report.timestamp.clone(),
+                last_changed,
+                error: result.error.clone(),
+            });
+        }
+
+        self.last_run = Some(report.clone());
+    }
+
+    /// Sets how often updates are meant to happen, so that [`health`][Self::health] can tell when one is overdue.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = Some(interval);
+    }
+
+    /// Checks whether the last run went well and the next one isn't overdue. Runs are overdue once twice the interval
+    /// has passed without one finishing, to leave plenty of room for slow ones.
+    pub fn health(&self) -> Result<(), String> {
+        if let Some(report) = &self.last_run
+            && report.errors > 0
+        {
+            let n = report.errors;
+            return Err(format!("the last update had {n} {errors}", errors = pluralize!("error", "errors", n)));
+        }
+
+        let since = self.finished_at.unwrap_or(self.started_at).elapsed();
+        match self.interval {
+            Some(interval) if since > interval * 2 => Err(match self.finished_at {
+                Some(_) => format!("no update has finished in the last {}s", since.as_secs()),
+                None => format!("no update has finished in the {}s since starting", since.as_secs()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn summary(&self) -> Summary<'_> {
+        let last_run = self.last_run.as_ref();
+        Summary {
+            started: &self.started,
+            dry_run: self.dry_run,
+            updates: self.updates,
+            ipv4: self.ipv4,
+            ipv6: self.ipv6,
+            last_update: last_run.map(|report| &report.timestamp[..]),
+            last_success: self.last_success.as_deref(),
+            errors: last_run.map_or(0, |report| report.errors),
+            targets: self.targets.len(),
+            recent_errors: &self.recent_errors,
+        }
+    }
+
+    pub fn targets(&self) -> Vec<&TargetStatus> {
+        self.targets.values().collect()
+    }
+
+    pub fn last_run(&self) -> Option<&Report> {
+        self.last_run.as_ref()
+    }
+}
@@ -0,0 +1,143 @@
+//! Checks that changed records have actually propagated, so that "updated" also means "resolvable".
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::rc::Rc;
+use std::time::Duration;
+
+use eyre::{WrapErr, eyre};
+use futures::lock::Mutex;
+use tokio::time::Instant;
+
+use crate::api::{IpAddrExt, PorkbunClient};
+use crate::config::{Target, VerifyConfig, VerifyServers};
+use crate::names::DomainName;
+use crate::sources::query_addresses;
+
+/// How long to wait for records to propagate when no timeout is configured.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long to wait between each round of queries.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// The resolvers asked in [`VerifyServers::Public`] mode: Cloudflare, Google, and Quad9.
+const PUBLIC_RESOLVERS: [Ipv4Addr; 3] = [
+    Ipv4Addr::new(1, 1, 1, 1),
+    Ipv4Addr::new(8, 8, 8, 8),
+    Ipv4Addr::new(9, 9, 9, 9),
+];
+
+/// Asks DNS servers for a target's records until they give out the new address.
+#[derive(Debug)]
+pub struct Verifier {
+    client: Rc<PorkbunClient>,
+    servers: VerifyServers,
+    timeout: Duration,
+    /// The addresses of each domain's nameservers, looked up once per domain.
+    nameservers: Mutex<HashMap<DomainName, Vec<SocketAddr>>>,
+}
+
+impl Verifier {
+    pub fn new(client: Rc<PorkbunClient>, config: VerifyConfig) -> Self {
+        Self {
+            client,
+            servers: config.servers,
+            timeout: config.timeout.unwrap_or(DEFAULT_TIMEOUT),
+            nameservers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until every server answers with the given address for the target's record, or until the timeout runs
+    /// out. Returns whether the record was verified.
+    pub async fn verify(&self, target: &Target, addr: IpAddr) -> bool {
+        let typ = addr.dns_type();
+        let mut pending = match self.servers_for(target.domain()).await {
+            Ok(servers) => servers,
+            Err(err) => {
+                log::warn!("{target}: Could not verify {typ} record: {err:#}");
+                return false;
+            },
+        };
+
+        let fqdn = target.fqdn();
+        let recursive = self.servers == VerifyServers::Public;
+        let deadline = Instant::now() + self.timeout;
+        log::debug!("{target}: Waiting for {typ} record to resolve to {addr}...");
+
+        loop {
+            let queries = pending
+                .iter()
+                .map(|&server| query_addresses(server, &fqdn, addr.is_ipv6(), recursive));
+            let answers = futures::future::join_all(queries).await;
+
+            // Servers that give out the new address are done; everything else gets asked again next round.
+            pending = (pending.into_iter().zip(answers))
+                .filter(|(server, res)| match res {
+                    Ok(found) if found.contains(&addr) => false,
+                    Ok(found) => {
+                        log::trace!("{target}: {server} still answers with {found:?}.");
+                        true
+                    },
+                    Err(err) => {
+                        log::trace!("{target}: {server} did not answer: {err:#}");
+                        true
+                    },
+                })
+                .map(|(server, _)| server)
+                .collect::<Vec<_>>();
+
+            if pending.is_empty() {
+                log::info!("{target}: Verified that {typ} record resolves to {addr}.");
+                return true;
+            } else if Instant::now() + INTERVAL > deadline {
+                log::warn!(
+                    "{target}: {typ} record still does not resolve to {addr} on {n} {servers} after {:?}.",
+                    self.timeout,
+                    n = pending.len(),
+                    servers = pluralize!("server", "servers", pending.len()),
+                );
+                return false;
+            }
+
+            tokio::time::sleep(INTERVAL).await;
+        }
+    }
+
+    /// Works out which servers to ask about records on the given domain.
+    async fn servers_for(&self, domain: &DomainName) -> eyre::Result<Vec<SocketAddr>> {
+        if self.servers == VerifyServers::Public {
+            return Ok(PUBLIC_RESOLVERS.map(|ip| SocketAddr::from((ip, 53))).to_vec());
+        }
+
+        // Held for the whole lookup, so that targets on the same domain don't all look up the same nameservers at once.
+        let mut cache = self.nameservers.lock().await;
+        if let Some(servers) = cache.get(domain) {
+            return Ok(servers.clone());
+        }
+
+        let names = (self.client.get_nameservers(domain).await)
+            .wrap_err_with(|| format!("Failed to get nameservers for {domain}"))?;
+
+        let mut servers = Vec::with_capacity(names.len());
+        for name in &names {
+            let name = name.trim_end_matches('.');
+            let addrs = tokio::net::lookup_host((name, 53))
+                .await
+                .wrap_err_with(|| format!("Failed to look up nameserver {name}"))?
+                .collect::<Vec<_>>();
+
+            // Not every machine can reach IPv6 servers, so IPv4 ones are preferred whenever there's a choice.
+            match addrs.iter().find(|addr| addr.is_ipv4()).or(addrs.first()) {
+                Some(&addr) => servers.push(addr),
+                None => log::debug!("Nameserver {name} has no addresses."),
+            }
+        }
+
+        if servers.is_empty() {
+            return Err(eyre!("Found no nameservers for {domain}"));
+        }
+
+        cache.insert(domain.clone(), servers.clone());
+        Ok(servers)
+    }
+}
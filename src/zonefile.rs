@@ -0,0 +1,303 @@
+//! A small parser for standard (BIND-style) DNS zone files.
+//!
+//! This only handles as much of the format as is needed to import records into Porkbun: `$ORIGIN` and `$TTL`
+//! directives, comments, parenthesized multi-line records, relative names, and blank owner names. `$INCLUDE` is not
+//! supported.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use eyre::{WrapErr, eyre};
+
+use crate::api::{RecordData, RecordType};
+
+/// A single record read from a zone file.
+#[derive(Debug, Clone)]
+pub struct ZoneRecord {
+    /// The fully-qualified name of the record, lowercase and without a trailing dot.
+    pub name: String,
+    /// The record's TTL, either given directly or from the most recent `$TTL` directive.
+    pub ttl: Option<u32>,
+    /// The record's type and content, converted into the form Porkbun expects.
+    pub data: RecordData,
+}
+
+/// A single token from a line of a zone file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A bare word.
+    Word(String),
+    /// A double-quoted string, with its escapes already decoded.
+    Quoted(String),
+}
+
+impl Token {
+    fn as_str(&self) -> &str {
+        match self {
+            Token::Word(s) | Token::Quoted(s) => s,
+        }
+    }
+}
+
+/// Parses every record out of a zone file.
+///
+/// `origin` is the starting value for `$ORIGIN`; it may be left as `None` if the file sets its own.
+pub fn parse(text: &str, origin: Option<&str>) -> eyre::Result<Vec<ZoneRecord>> {
+    let mut origin = origin.map(normalize_name);
+    let mut default_ttl = None;
+    let mut last_owner = None::<String>;
+    let mut records = Vec::new();
+
+    for (line_no, starts_blank, tokens) in logical_lines(text)? {
+        let res = (|| -> eyre::Result<()> {
+            let mut tokens = tokens.into_iter().peekable();
+
+            // Directives:
+            if let Some(Token::Word(first)) = tokens.peek()
+                && first.starts_with('$')
+            {
+                let directive = first.to_ascii_uppercase();
+                tokens.next();
+                let arg = tokens.next().ok_or_else(|| eyre!("{directive} requires an argument"))?;
+                match &directive[..] {
+                    "$ORIGIN" => origin = Some(absolute_name(arg.as_str(), origin.as_deref())?),
+                    "$TTL" => default_ttl = Some(parse_ttl(arg.as_str())?),
+                    other => return Err(eyre!("{other} directives are not supported")),
+                }
+                return Ok(());
+            }
+
+            // Owner name is only present when the line doesn't start with whitespace; otherwise it's the same as the
+            // previous record's.
+            let owner = if starts_blank {
+                last_owner.clone().ok_or_else(|| eyre!("first record has no owner name"))?
+            } else {
+                let name = tokens.next().ok_or_else(|| eyre!("missing owner name"))?;
+                absolute_name(name.as_str(), origin.as_deref())?
+            };
+
+            // The TTL and class can come in either order, and both are optional.
+            let mut ttl = None;
+            let typ = loop {
+                let tok = tokens.next().ok_or_else(|| eyre!("missing record type"))?;
+                let word = tok.as_str();
+                if ttl.is_none()
+                    && let Ok(parsed) = parse_ttl(word)
+                {
+                    ttl = Some(parsed);
+                } else if ["IN", "CH", "HS"].iter().any(|class| word.eq_ignore_ascii_case(class)) {
+                    continue;
+                } else {
+                    break word.to_ascii_uppercase();
+                }
+            };
+
+            let rdata = tokens.collect::<Vec<_>>();
+            let data = convert_rdata(&typ, &rdata, origin.as_deref())?;
+
+            last_owner = Some(owner.clone());
+            records.push(ZoneRecord {
+                name: owner,
+                ttl: ttl.or(default_ttl),
+                data,
+            });
+            Ok(())
+        })();
+
+        res.wrap_err_with(|| format!("Invalid zone file (line {line_no})"))?;
+    }
+
+    Ok(records)
+}
+
+/// Splits a zone file into logical lines (joining parenthesized records that span several physical lines), each
+/// broken into tokens. Each line comes with its starting line number and whether it started with whitespace.
+fn logical_lines(text: &str) -> eyre::Result<Vec<(usize, bool, Vec<Token>)>> {
+    let mut lines = Vec::new();
+    let mut current = None::<(usize, bool, Vec<Token>)>;
+    let mut depth = 0usize;
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let (start_no, _, tokens) = current.get_or_insert_with(|| (line_no, line.starts_with([' ', '\t']), Vec::new()));
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => break,
+                '(' => depth += 1,
+                ')' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| eyre!("Invalid zone file (line {line_no}): unbalanced parentheses"))?;
+                },
+                c if c.is_whitespace() => {},
+                '"' => {
+                    let mut str = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => str.push(unescape(&mut chars)),
+                            Some(c) => str.push(c),
+                            None => return Err(eyre!("Invalid zone file (line {start_no}): unterminated string")),
+                        }
+                    }
+                    tokens.push(Token::Quoted(str));
+                },
+                c => {
+                    let mut word = String::from(c);
+                    while let Some(&next) = chars.peek() {
+                        if next.is_whitespace() || matches!(next, ';' | '(' | ')' | '"') {
+                            break;
+                        }
+                        word.push(next);
+                        chars.next();
+                    }
+                    tokens.push(Token::Word(word));
+                },
+            }
+        }
+
+        if depth == 0 {
+            let line = current.take().unwrap();
+            if !line.2.is_empty() {
+                lines.push(line);
+            }
+        }
+    }
+
+    if let Some((start_no, ..)) = current {
+        return Err(eyre!("Invalid zone file (line {start_no}): unbalanced parentheses"));
+    }
+
+    Ok(lines)
+}
+
+/// Decodes a single backslash escape (the backslash itself having already been consumed): either `\DDD` for a decimal
+/// byte value, or `\X` for a literal `X`.
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>) -> char {
+    let mut digits = String::new();
+    while digits.len() < 3
+        && let Some(&c) = chars.peek()
+        && c.is_ascii_digit()
+    {
+        digits.push(c);
+        chars.next();
+    }
+
+    match digits.len() {
+        0 => chars.next().unwrap_or('\\'),
+        _ => digits.parse::<u8>().map(char::from).unwrap_or('?'),
+    }
+}
+
+/// Lowercases a name and strips its trailing dot.
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Resolves a possibly-relative name from a zone file against the current origin.
+fn absolute_name(name: &str, origin: Option<&str>) -> eyre::Result<String> {
+    if name.ends_with('.') {
+        return Ok(normalize_name(name));
+    }
+
+    let origin = origin.ok_or_else(|| eyre!("relative name {name:?} used without an $ORIGIN"))?;
+    if name == "@" {
+        Ok(origin.to_string())
+    } else {
+        Ok(format!("{}.{origin}", name.to_ascii_lowercase()))
+    }
+}
+
+/// Parses a TTL, which may either be a plain number of seconds or use BIND's unit suffixes (e.g. `1h30m`).
+fn parse_ttl(ttl: &str) -> eyre::Result<u32> {
+    if let Ok(secs) = ttl.parse() {
+        return Ok(secs);
+    }
+
+    let mut total = 0u32;
+    let mut num = String::new();
+    for c in ttl.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+
+        let mult = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            _ => return Err(eyre!("invalid TTL {ttl:?}")),
+        };
+
+        let n = num.parse::<u32>().map_err(|_| eyre!("invalid TTL {ttl:?}"))?;
+        total = n
+            .checked_mul(mult)
+            .and_then(|n| total.checked_add(n))
+            .ok_or_else(|| eyre!("TTL is too large"))?;
+        num.clear();
+    }
+
+    if num.is_empty() {
+        Ok(total)
+    } else {
+        Err(eyre!("invalid TTL {ttl:?}"))
+    }
+}
+
+/// Converts a record's RDATA into the content (and priority) that Porkbun expects for that type.
+fn convert_rdata(typ: &str, rdata: &[Token], origin: Option<&str>) -> eyre::Result<RecordData> {
+    let words = rdata.iter().map(Token::as_str).collect::<Vec<_>>();
+    let arity = |n: usize| -> eyre::Result<()> {
+        match words.len() == n {
+            true => Ok(()),
+            false => Err(eyre!("{typ} record should have {n} fields, found {}", words.len())),
+        }
+    };
+
+    let (content, prio) = match typ {
+        "A" => {
+            arity(1)?;
+            let addr = words[0].parse::<Ipv4Addr>().wrap_err("invalid IPv4 address")?;
+            (addr.to_string(), None)
+        },
+        "AAAA" => {
+            arity(1)?;
+            let addr = words[0].parse::<Ipv6Addr>().wrap_err("invalid IPv6 address")?;
+            (addr.to_string(), None)
+        },
+        "CNAME" | "ALIAS" | "NS" | "PTR" => {
+            arity(1)?;
+            (absolute_name(words[0], origin)?, None)
+        },
+        "MX" => {
+            arity(2)?;
+            let prio = words[0].parse().wrap_err("invalid MX preference")?;
+            (absolute_name(words[1], origin)?, Some(prio))
+        },
+        "SRV" => {
+            arity(4)?;
+            let prio = words[0].parse().wrap_err("invalid SRV priority")?;
+            let target = absolute_name(words[3], origin)?;
+            (format!("{} {} {target}", words[1], words[2]), Some(prio))
+        },
+        // Porkbun stores TXT records' content unquoted. Multiple strings in one record get joined together.
+        "TXT" | "SPF" => (words.concat(), None),
+        // Anything else (CAA, TLSA, SSHFP, SOA, ...) is passed through as-is, re-quoting anything that was quoted.
+        _ => {
+            let parts = rdata.iter().map(|tok| match tok {
+                Token::Word(word) => word.clone(),
+                Token::Quoted(str) => format!("{str:?}"),
+            });
+            (parts.collect::<Vec<_>>().join(" "), None)
+        },
+    };
+
+    Ok(RecordData {
+        typ: RecordType::from(typ),
+        content,
+        prio,
+    })
+}